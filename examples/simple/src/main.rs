@@ -1,16 +1,27 @@
-use include_fs::{include_fs, IncludeFs};
+use include_fs::{include_fs, StaticFs};
 
-static SOURCE: IncludeFs = include_fs!("source");
+include_fs!(static SOURCE = "source");
+
+// The same bundle, with an index generated at compile time
+include_fs!(static SOURCE_TABLE: StaticFs = "source");
+
+// The same bundle with a constant for the path of every file
+include_fs!(mod source = "source");
 
 fn main() {
+  println!("{SOURCE_FILE_COUNT} files, {SOURCE_TOTAL_SIZE} bytes");
   println!("paths: {:?}", SOURCE.list_paths());
+  assert_eq!(
+    SOURCE_TABLE.get("src/main.rs").ok(),
+    SOURCE.get("src/main.rs").ok()
+  );
 
   // Check if a specific file exists in the embedded file system
   let file_exists = SOURCE.exists("example.txt");
   println!("Does 'example.txt' exist? {}", file_exists);
 
   // Retrieve the content of a file
-  match SOURCE.get("src/main.rs") {
+  match SOURCE.get(source::paths::MAIN_RS) {
     Ok(content) => {
       println!("{}", String::from_utf8_lossy(content));
     }
@@ -0,0 +1,3 @@
+fn main() {
+  include_fs::bundle("assets", "assets").expect("embed assets dir");
+}
@@ -0,0 +1,51 @@
+//! Assets embedded into a wasm module, for environments without a filesystem.
+//!
+//! Build the module with `cargo build -p wasm --target wasm32-unknown-unknown --release`, and
+//! read files from JavaScript without copying them out of the module's memory:
+//!
+//! ```js
+//! const { instance } = await WebAssembly.instantiateStreaming(fetch("wasm.wasm"));
+//! const { memory, alloc, lookup } = instance.exports;
+//!
+//! const path = new TextEncoder().encode("assets/index.html");
+//! const ptr = alloc(path.length);
+//! new Uint8Array(memory.buffer, ptr, path.length).set(path);
+//!
+//! const found = lookup(ptr, path.length);
+//! const html = new Uint8Array(memory.buffer, Number(found >> 32n), Number(found & 0xffffffffn));
+//! ```
+
+use include_fs::include_fs;
+
+include_fs!(static ASSETS = "assets");
+
+/// The number of embedded files.
+#[no_mangle]
+pub extern "C" fn file_count() -> usize {
+  ASSETS_FILE_COUNT
+}
+
+/// Allocate `len` bytes in the module's memory, for passing a path to [`lookup`].
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+  Box::leak(vec![0; len].into_boxed_slice()).as_mut_ptr()
+}
+
+/// Look up an embedded file, returning the address of its data in the upper and its length in
+/// the lower 32 bits, or 0 if it doesn't exist. The path is freed.
+///
+/// # Safety
+///
+/// `path` has to be a pointer returned by [`alloc`] for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn lookup(path: *mut u8, len: usize) -> u64 {
+  let path = Box::from_raw(std::ptr::slice_from_raw_parts_mut(path, len));
+  let Ok(path) = std::str::from_utf8(&path) else {
+    return 0;
+  };
+
+  match ASSETS.get(path) {
+    Ok(data) => (data.as_ptr() as u64) << 32 | data.len() as u64,
+    Err(_) => 0,
+  }
+}
@@ -0,0 +1,126 @@
+//! Expansion of `include_fs_dir!("dir")`, which archives a directory without a build script.
+
+use quote::quote;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{HEADER_LEN, MAGIC};
+
+/// The archive format version written by the macro, see `FORMAT_VERSION` in `include-fs`.
+const FORMAT_VERSION: u8 = 1;
+
+pub(crate) fn expand(dir: &syn::LitStr) -> syn::Result<proc_macro2::TokenStream> {
+  let error = |message: String| syn::Error::new_spanned(dir, message);
+  let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") else {
+    return Err(error("CARGO_MANIFEST_DIR not set".to_string()));
+  };
+
+  if Path::new(&dir.value()).is_absolute() {
+    return Err(error("Directory path must be relative".to_string()));
+  }
+
+  let manifest_dir = Path::new(&manifest_dir)
+    .canonicalize()
+    .map_err(|err| error(format!("Failed to read CARGO_MANIFEST_DIR: {err}")))?;
+  let Ok(source_dir) = manifest_dir.join(dir.value()).canonicalize() else {
+    return Err(error(format!("Directory {} does not exist", dir.value())));
+  };
+
+  if !source_dir.starts_with(&manifest_dir) || !source_dir.is_dir() {
+    return Err(error(format!(
+      "{} is not a directory inside the crate",
+      dir.value()
+    )));
+  }
+
+  let mut files = Vec::new();
+  collect(&source_dir, &mut files).map_err(|err| error(format!("Failed to read files: {err}")))?;
+
+  // Paths are relative to the manifest directory, like those of bundles built by build scripts
+  let mut entries = Vec::with_capacity(files.len());
+  for file in &files {
+    let relative = file.strip_prefix(&manifest_dir).unwrap();
+    let components: Option<Vec<_>> = relative.iter().map(|part| part.to_str()).collect();
+    let Some(components) = components else {
+      return Err(error(format!("{} is not valid UTF-8", relative.display())));
+    };
+
+    entries.push((components.join("/"), file));
+  }
+
+  entries.sort();
+  let mut data = Vec::new();
+  let mut sizes = Vec::with_capacity(entries.len());
+  for (_, file) in &entries {
+    let contents =
+      fs::read(file).map_err(|err| error(format!("Failed to read {}: {err}", file.display())))?;
+    sizes.push(contents.len() as u64);
+    data.extend_from_slice(&contents);
+  }
+
+  let archive = write_archive(&entries, &sizes, &data).map_err(error)?;
+  let archive = proc_macro2::Literal::byte_string(&archive);
+
+  // The file contents are already in the archive literal, the includes only make cargo rebuild
+  // the crate when a file changes
+  let includes = entries.iter().map(|(_, file)| {
+    let file = file.to_str().expect("path is valid UTF-8");
+    quote! { const _: &[u8] = include_bytes!(#file); }
+  });
+
+  Ok(quote! {
+    include_fs::IncludeFs::new(|| {
+      #(#includes)*
+      include_fs::IncludeFsInner::new(#archive).expect("Failed to initialize IncludeFs")
+    })
+  })
+}
+
+/// Collect the files in `dir` and its subdirectories, following symlinks.
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    let meta = fs::metadata(&path)?;
+    if meta.is_dir() {
+      collect(&path, files)?;
+    } else if meta.is_file() {
+      files.push(path);
+    }
+  }
+
+  Ok(())
+}
+
+/// Write an archive without attributes, with the data of every file in the order of `entries`.
+pub(crate) fn write_archive(
+  entries: &[(String, &PathBuf)],
+  sizes: &[u64],
+  data: &[u8],
+) -> Result<Vec<u8>, String> {
+  let file_count = u32::try_from(entries.len()).map_err(|_| "Too many files".to_string())?;
+  let header_len: usize = HEADER_LEN
+    + entries
+      .iter()
+      .map(|(path, _)| 2 + path.len() + 8 + 8 + 2)
+      .sum::<usize>();
+
+  let mut archive = Vec::with_capacity(header_len + data.len());
+  archive.extend_from_slice(MAGIC);
+  archive.push(FORMAT_VERSION);
+  archive.extend_from_slice(&file_count.to_le_bytes());
+  archive.extend_from_slice(&0u16.to_le_bytes());
+
+  let mut offset = header_len as u64;
+  for ((path, _), size) in entries.iter().zip(sizes) {
+    let path_len = u16::try_from(path.len()).map_err(|_| format!("{path} is too long"))?;
+    archive.extend_from_slice(&path_len.to_le_bytes());
+    archive.extend_from_slice(path.as_bytes());
+    archive.extend_from_slice(&size.to_le_bytes());
+    archive.extend_from_slice(&offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    offset += size;
+  }
+
+  archive.extend_from_slice(data);
+  Ok(archive)
+}
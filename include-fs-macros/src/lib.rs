@@ -1,25 +1,267 @@
-use quote::quote;
+use quote::{format_ident, quote};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::PathBuf;
+use syn::parse::{Parse, ParseStream};
+
+mod dir;
+mod modules;
+
+const MAGIC: &[u8; 4] = b"INFS";
+/// Size of the magic, format version, file count and archive attribute length.
+const HEADER_LEN: usize = 4 + 1 + 4 + 2;
+/// Archive attribute tag of case-insensitive bundles, see `ARCHIVE_ATTR_CASE_INSENSITIVE`.
+const CASE_INSENSITIVE_ATTR: u8 = 1;
+/// Archive attribute tag of encrypted bundles, see `ARCHIVE_ATTR_ENCRYPTED` in `include-fs`.
+const ENCRYPTED_ATTR: u8 = 7;
+/// Archive attribute tag of bundles linked from a static library, see `ARCHIVE_ATTR_STATIC_LIB`.
+const STATIC_LIB_ATTR: u8 = 11;
+/// Archive attribute tag of the data alignment, see `ARCHIVE_ATTR_ALIGNMENT` in `include-fs`.
+const ALIGNMENT_ATTR: u8 = 16;
+/// Archive attribute tag of bundles without file data, see `ARCHIVE_ATTR_STUB` in `include-fs`.
+const STUB_ATTR: u8 = 18;
+/// File attribute tag of compressed files, see `ATTR_COMPRESSION` in `include-fs`.
+const COMPRESSION_ATTR: u8 = 5;
+
+struct Input {
+  kind: InputKind,
+  /// Shared data for bundles built with `Bundle::build_shared`, from `shared = EXPR`.
+  shared: Option<syn::Expr>,
+}
+
+enum InputKind {
+  /// `include_fs!("assets")`, used as an expression.
+  Expr(syn::LitStr),
+
+  /// `include_fs!(pub static ASSETS = "assets");`, which also emits constants describing the
+  /// bundle contents. With `static ASSETS: StaticFs`, the index is generated as a table.
+  Static {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    ident: syn::Ident,
+    name: syn::LitStr,
+    /// The `StaticFs` type, as it was written.
+    table: Option<syn::TypePath>,
+  },
+
+  /// `include_fs!(pub mod assets = "assets");`, which emits a module containing the bundle as
+  /// `FS`, nested modules with the contents of every file and a `paths` module with their paths.
+  Module {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    ident: syn::Ident,
+    name: syn::LitStr,
+  },
+}
+
+impl Parse for Input {
+  fn parse(input: ParseStream) -> syn::Result<Self> {
+    let kind = if input.peek(syn::LitStr) {
+      InputKind::Expr(input.parse()?)
+    } else {
+      let attrs = input.call(syn::Attribute::parse_outer)?;
+      let vis = input.parse()?;
+      let is_module = input.peek(syn::Token![mod]);
+      if is_module {
+        input.parse::<syn::Token![mod]>()?;
+      } else {
+        input.parse::<syn::Token![static]>()?;
+      }
+
+      let ident = input.parse()?;
+      let mut table = None;
+      if !is_module && input.peek(syn::Token![:]) {
+        input.parse::<syn::Token![:]>()?;
+        let ty: syn::TypePath = input.parse()?;
+        if ty
+          .path
+          .segments
+          .last()
+          .is_none_or(|segment| segment.ident != "StaticFs")
+        {
+          return Err(syn::Error::new_spanned(
+            ty,
+            "Expected `StaticFs`, or no type for an `IncludeFs`",
+          ));
+        }
+
+        table = Some(ty);
+      }
+
+      input.parse::<syn::Token![=]>()?;
+      let name = input.parse()?;
+
+      if is_module {
+        InputKind::Module {
+          attrs,
+          vis,
+          ident,
+          name,
+        }
+      } else {
+        InputKind::Static {
+          attrs,
+          vis,
+          ident,
+          name,
+          table,
+        }
+      }
+    };
+
+    let mut shared = None;
+    if input.peek(syn::Token![,]) {
+      input.parse::<syn::Token![,]>()?;
+      let key: syn::Ident = input.parse()?;
+      if key != "shared" {
+        return Err(syn::Error::new_spanned(key, "Expected `shared = ...`"));
+      }
+
+      input.parse::<syn::Token![=]>()?;
+      shared = Some(input.parse()?);
+    }
+
+    if input.peek(syn::Token![;]) {
+      input.parse::<syn::Token![;]>()?;
+    }
+
+    Ok(Input { kind, shared })
+  }
+}
 
 /// Include a bundled directory in the binary.
 /// To use this macro, include a matching call to `include_fs::bundle` in your build script.
 ///
+/// The macro can either be used as an expression, or declare the static itself. When declaring
+/// the static, two constants are emitted alongside it: `<NAME>_FILE_COUNT: usize` and
+/// `<NAME>_TOTAL_SIZE: u64`, which describe the bundle without touching the runtime index.
+///
 /// # Example
 ///
-/// ```rust
+/// ```rust,ignore
 /// // In build.rs
 /// include_fs::bundle("assets", "assets").unwrap();
 /// include_fs::bundle("./static/public", "public").unwrap();
 ///
 /// // In main.rs
 /// static ASSETS: IncludeFs = include_fs!("assets");
-/// static PUBLIC: IncludeFs = include_fs!("public");
+/// include_fs!(pub static PUBLIC = "public");
+///
+/// println!("{PUBLIC_FILE_COUNT} public files ({PUBLIC_TOTAL_SIZE} bytes)");
+/// ```
+///
+/// Declaring a module instead emits the bundle as `FS` inside it, along with nested modules that
+/// mirror the directory structure and contain the contents of every file as a `&'static [u8]`.
+/// The directory that all files have in common is left out, and names are converted to valid
+/// identifiers:
+///
+/// ```rust,ignore
+/// include_fs!(pub mod assets = "assets");
+///
+/// let menu: &[u8] = assets::icons::MENU_SVG; // assets/icons/menu.svg
+/// let logo = assets::FS.get("assets/logo.png")?;
+/// ```
+///
+/// The module also contains a `paths` module with the same structure, holding the path of every
+/// file as a `&'static str`, so typos in lookups are compile errors. Compressed files can't be
+/// borrowed, so they only get a path constant and are read through `FS`:
+///
+/// ```rust,ignore
+/// let logo = assets::FS.get(assets::paths::LOGO_PNG)?; // "assets/logo.png"
+/// ```
+///
+/// Declaring the static as a `StaticFs` generates the index at compile time, as a sorted table
+/// of paths and data that needs no initialization on first access. This only works for bundles
+/// without compressed or encrypted files:
+///
+/// ```rust,ignore
+/// include_fs!(pub static ASSETS: StaticFs = "assets");
+/// ```
+///
+/// Bundles built with `Bundle::build_shared` need their shared data, passed as a `&'static [u8]`:
+///
+/// ```rust,ignore
+/// static THEME_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/themes.embed_data"));
+/// static DARK: IncludeFs = include_fs!("dark", shared = THEME_DATA);
 /// ```
 #[proc_macro]
 pub fn include_fs(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-  let input = syn::parse_macro_input!(input as syn::LitStr);
+  let input = syn::parse_macro_input!(input as Input);
+
+  let shared = input.shared.as_ref();
+  let result = match input.kind {
+    InputKind::Expr(name) => resolve_bundle(&name).map(|bundle| init_expr(&bundle, shared)),
+    InputKind::Static {
+      attrs,
+      vis,
+      ident,
+      name,
+      table,
+    } => resolve_bundle(&name).and_then(|bundle| {
+      let file_count = bundle.entries.len();
+      let total_size: u64 = bundle.entries.iter().map(|entry| entry.size).sum();
+
+      let (ty, init) = match table {
+        Some(ty) => (quote! { #ty }, table_expr(&name, &bundle, shared)?),
+        None => (quote! { include_fs::IncludeFs }, init_expr(&bundle, shared)),
+      };
+      let file_count_ident = format_ident!("{}_FILE_COUNT", ident);
+      let total_size_ident = format_ident!("{}_TOTAL_SIZE", ident);
+
+      Ok(quote! {
+        #(#attrs)*
+        #vis static #ident: #ty = #init;
+
+        /// Number of files in the bundle.
+        #vis const #file_count_ident: usize = #file_count;
+
+        /// Total size of all files in the bundle, in bytes.
+        #vis const #total_size_ident: u64 = #total_size;
+      })
+    }),
+    InputKind::Module {
+      attrs,
+      vis,
+      ident,
+      name,
+    } => resolve_bundle(&name)
+      .and_then(|bundle| modules::expand(&name, attrs, vis, ident, &bundle, shared)),
+  };
+
+  match result {
+    Ok(tokens) => tokens.into(),
+    Err(err) => err.into_compile_error().into(),
+  }
+}
+
+/// Include a directory in the binary without a build script, archiving it while the macro is
+/// expanded.
+///
+/// Paths are relative to the crate root, like those of bundles built with `include_fs::bundle`.
+/// The crate is rebuilt when an included file changes, but not when files are added or removed,
+/// and none of the `Bundle` options are available. Use a build script for larger directories or
+/// more control over the bundle.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// static ASSETS: IncludeFs = include_fs_dir!("assets");
+///
+/// let logo = ASSETS.get("assets/logo.png")?;
+/// ```
+#[proc_macro]
+pub fn include_fs_dir(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  let dir = syn::parse_macro_input!(input as syn::LitStr);
+  match dir::expand(&dir) {
+    Ok(tokens) => tokens.into(),
+    Err(err) => err.into_compile_error().into(),
+  }
+}
 
+/// Find the archive for the given bundle name in `OUT_DIR`.
+fn resolve_bundle(input: &syn::LitStr) -> syn::Result<Bundle> {
   let Ok(out_dir) = std::env::var("OUT_DIR") else {
-    return quote! { compile_error!("OUT_DIR not set") }.into();
+    return Err(syn::Error::new_spanned(input, "OUT_DIR not set"));
   };
 
   let mut input_value = input.value();
@@ -33,42 +275,281 @@ pub fn include_fs(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
   let bundle_path = std::path::Path::new(&input_value);
   if bundle_path.is_absolute() {
-    return syn::Error::new_spanned(input, "Bundle path must be relative")
-      .into_compile_error()
-      .into();
+    return Err(syn::Error::new_spanned(
+      input,
+      "Bundle path must be relative",
+    ));
   }
 
-  let Ok(bundle_path) = std::path::Path::new(&out_dir)
-    .join(input_value)
+  // Compare canonical paths, since OUT_DIR may contain symlinks, like `/var` on macOS or a
+  // symlinked CARGO_TARGET_DIR
+  let out_dir = std::path::Path::new(&out_dir);
+  let canonical_out_dir = out_dir
     .canonicalize()
-  else {
-    return syn::Error::new_spanned(input, not_found_err)
-      .into_compile_error()
-      .into();
+    .unwrap_or_else(|_| out_dir.to_path_buf());
+
+  let Ok(bundle_path) = out_dir.join(input_value).canonicalize() else {
+    return Err(syn::Error::new_spanned(input, not_found_err));
   };
 
-  if !bundle_path.starts_with(&out_dir) {
-    return syn::Error::new_spanned(input, "Bundle path can not escape OUT_DIR")
-      .into_compile_error()
-      .into();
+  if !bundle_path.starts_with(&canonical_out_dir) {
+    return Err(syn::Error::new_spanned(
+      input,
+      "Bundle path can not escape OUT_DIR",
+    ));
   }
 
   if !bundle_path.exists() {
-    return syn::Error::new_spanned(input, not_found_err)
-      .into_compile_error()
-      .into();
+    return Err(syn::Error::new_spanned(input, not_found_err));
   }
 
-  let include_path = bundle_path
+  let Some(bundle) = Bundle::read(bundle_path) else {
+    return Err(syn::Error::new_spanned(
+      input,
+      "Bundle is not a valid archive",
+    ));
+  };
+
+  if bundle.attribute(ENCRYPTED_ATTR).is_some() {
+    return Err(syn::Error::new_spanned(
+      input,
+      "Bundle is encrypted, open it with `IncludeFsInner::with_passphrase` or `IncludeFsInner::with_key` instead",
+    ));
+  }
+
+  Ok(bundle)
+}
+
+fn init_expr(bundle: &Bundle, shared: Option<&syn::Expr>) -> proc_macro2::TokenStream {
+  let archive_bytes = archive_bytes_expr(bundle);
+  let init = init_call(
+    quote! { archived_bytes },
+    shared.map(|shared| quote! { #shared }),
+  );
+
+  quote! {
+    include_fs::IncludeFs::new(|| {
+      let archived_bytes: &'static [u8] = #archive_bytes;
+      #init
+    })
+  }
+}
+
+/// An expression evaluating to a `StaticFs` with a table of the files, sorted by path.
+fn table_expr(
+  name: &syn::LitStr,
+  bundle: &Bundle,
+  shared: Option<&syn::Expr>,
+) -> syn::Result<proc_macro2::TokenStream> {
+  bundle.check_embedded(name, "a StaticFs")?;
+  if let Some(entry) = bundle.entries.iter().find(|entry| entry.compressed) {
+    return Err(syn::Error::new_spanned(
+      name,
+      format!(
+        "{} is compressed and can't be borrowed by a StaticFs, use an IncludeFs instead",
+        entry.path
+      ),
+    ));
+  }
+
+  let mut entries: Vec<_> = bundle.entries.iter().collect();
+  entries.sort_by(|a, b| a.path.cmp(&b.path));
+  let archive_bytes = archive_bytes_expr(bundle);
+  let data = match shared {
+    Some(shared) => quote! { #shared },
+    None => quote! { __ARCHIVE },
+  };
+
+  let len = entries.len();
+  let files = entries.iter().map(|entry| {
+    let path = &entry.path;
+    let offset = entry.data_offset as usize;
+    let size = entry.size as usize;
+    quote! {
+      (#path, unsafe { ::core::slice::from_raw_parts(__DATA.as_ptr().add(#offset), #size) })
+    }
+  });
+
+  let case_insensitive = bundle.attribute(CASE_INSENSITIVE_ATTR).is_some();
+  Ok(quote! {
+    {
+      static __ARCHIVE: &[u8] = #archive_bytes;
+      static __DATA: &[u8] = #data;
+      static __FILES: [(&str, &[u8]); #len] = [#(#files),*];
+      include_fs::StaticFs::__new(&__FILES, #case_insensitive)
+    }
+  })
+}
+
+/// Call the constructor matching the archive type.
+fn init_call(
+  archived_bytes: proc_macro2::TokenStream,
+  shared: Option<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+  let init = match shared {
+    Some(shared) => {
+      quote! { include_fs::IncludeFsInner::with_shared_data(#archived_bytes, #shared) }
+    }
+    None => quote! { include_fs::IncludeFsInner::new(#archived_bytes) },
+  };
+
+  quote! { #init.expect("Failed to initialize IncludeFs") }
+}
+
+/// Find the value of the attribute with `tag` in a list of attributes.
+fn find_attribute(mut attrs: &[u8], tag: u8) -> Option<&[u8]> {
+  while let [attr_tag, len_lo, len_hi, rest @ ..] = attrs {
+    let len = u16::from_le_bytes([*len_lo, *len_hi]) as usize;
+    let value = rest.get(..len)?;
+    if *attr_tag == tag {
+      return Some(value);
+    }
+
+    attrs = &rest[len..];
+  }
+
+  None
+}
+
+/// An expression evaluating to the archive bytes as a `&'static [u8]`.
+///
+/// Bundles built with `Bundle::static_lib` are linked from a static library, and referenced
+/// through an extern static instead of being included as a byte literal.
+fn archive_bytes_expr(bundle: &Bundle) -> proc_macro2::TokenStream {
+  let include_path = bundle
+    .path
     .to_str()
     .expect("bundle path is not valid unicode");
 
+  let Some(symbol) = bundle.attribute(STATIC_LIB_ATTR) else {
+    let alignment = bundle
+      .attribute(ALIGNMENT_ATTR)
+      .and_then(|value| Some(u32::from_le_bytes(value.get(..4)?.try_into().ok()?)));
+    let Some(alignment) = alignment else {
+      return quote! { include_bytes!(#include_path) };
+    };
+
+    // The data offsets are aligned relative to the start of the archive
+    let alignment = proc_macro2::Literal::u32_unsuffixed(alignment);
+    return quote! {
+      {
+        #[repr(C, align(#alignment))]
+        struct Aligned<T: ?Sized>(T);
+
+        static ALIGNED: &Aligned<[u8]> = &Aligned(*include_bytes!(#include_path));
+        &ALIGNED.0
+      }
+    };
+  };
+
+  let symbol = format_ident!("{}", String::from_utf8_lossy(symbol));
+  let len = bundle.len as usize;
+
+  // Cargo recompiles the crate whenever the build script reruns, so the length is never stale
   quote! {
-    std::sync::LazyLock::new(|| {
-      let archived_bytes: &[u8] = include_bytes!(#include_path);
-      include_fs::IncludeFsInner::new(archived_bytes)
-        .expect("Failed to initialize IncludeFs")
+    {
+      unsafe extern "C" {
+        static #symbol: [u8; #len];
+      }
+
+      unsafe { &#symbol }
+    }
+  }
+}
+
+/// A file in an archive header.
+struct ArchiveEntry {
+  path: String,
+  size: u64,
+  data_offset: u64,
+  compressed: bool,
+}
+
+/// The archive of a bundle, with its header read once while the macro is expanded.
+pub(crate) struct Bundle {
+  path: PathBuf,
+  /// Size of the archive file, in bytes.
+  len: u64,
+  /// The encoded archive attributes.
+  attrs: Vec<u8>,
+  entries: Vec<ArchiveEntry>,
+  /// The files have no data and are read from the source directory at runtime, see
+  /// `Bundle::embed_in_debug`.
+  stub: bool,
+}
+
+impl Bundle {
+  /// Read the archive attributes and file entries from the header, without the file data.
+  fn read(path: PathBuf) -> Option<Self> {
+    let file = File::open(&path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut header = [0; HEADER_LEN];
+    reader.read_exact(&mut header).ok()?;
+    if &header[0..4] != MAGIC {
+      return None;
+    }
+
+    let file_count = u32::from_le_bytes(header[5..9].try_into().ok()?) as usize;
+    let attrs_len = u16::from_le_bytes(header[9..11].try_into().ok()?) as usize;
+    let attrs = read_vec(&mut reader, attrs_len)?;
+    let mut entries = Vec::with_capacity(file_count);
+
+    for _ in 0..file_count {
+      let path_len = u16::from_le_bytes(read_array(&mut reader)?) as usize;
+      let path = String::from_utf8(read_vec(&mut reader, path_len)?).ok()?;
+      let size = u64::from_le_bytes(read_array(&mut reader)?);
+      let data_offset = u64::from_le_bytes(read_array(&mut reader)?);
+      let attrs_len = u16::from_le_bytes(read_array(&mut reader)?) as usize;
+      let attrs = read_vec(&mut reader, attrs_len)?;
+
+      entries.push(ArchiveEntry {
+        path,
+        size,
+        data_offset,
+        compressed: find_attribute(&attrs, COMPRESSION_ATTR).is_some(),
+      });
+    }
+
+    Some(Self {
+      stub: find_attribute(&attrs, STUB_ATTR).is_some(),
+      path,
+      len,
+      attrs,
+      entries,
     })
   }
-  .into()
+
+  /// The value of an archive attribute.
+  fn attribute(&self, tag: u8) -> Option<&[u8]> {
+    find_attribute(&self.attrs, tag)
+  }
+
+  /// Fail for stubs, whose files can't be borrowed by `form` since they aren't embedded.
+  pub(crate) fn check_embedded(&self, name: &syn::LitStr, form: &str) -> syn::Result<()> {
+    if !self.stub {
+      return Ok(());
+    }
+
+    Err(syn::Error::new_spanned(
+      name,
+      format!(
+        "Bundle is not embedded in debug builds because of `Bundle::embed_in_debug(false)`, \
+         so its files can't be borrowed by {form}, use an IncludeFs instead"
+      ),
+    ))
+  }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Option<[u8; N]> {
+  let mut buf = [0; N];
+  reader.read_exact(&mut buf).ok()?;
+  Some(buf)
+}
+
+fn read_vec(reader: &mut impl Read, len: usize) -> Option<Vec<u8>> {
+  let mut buf = vec![0; len];
+  reader.read_exact(&mut buf).ok()?;
+  Some(buf)
 }
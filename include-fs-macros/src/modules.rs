@@ -0,0 +1,297 @@
+//! Expansion of `include_fs!(mod name = "bundle")`.
+
+use quote::{format_ident, quote};
+use std::collections::BTreeMap;
+
+use crate::{ArchiveEntry, Bundle, archive_bytes_expr, init_call};
+
+/// Name of the static containing the bundle inside the generated module.
+const FS_IDENT: &str = "FS";
+/// Name of the module containing the path constants inside the generated module.
+const PATHS_IDENT: &str = "paths";
+
+#[derive(Default)]
+struct Dir<'a> {
+  /// The directory name this module was generated from.
+  name: &'a str,
+  dirs: BTreeMap<String, Dir<'a>>,
+  files: BTreeMap<String, &'a ArchiveEntry>,
+}
+
+pub(crate) fn expand(
+  name: &syn::LitStr,
+  attrs: Vec<syn::Attribute>,
+  vis: syn::Visibility,
+  ident: syn::Ident,
+  bundle: &Bundle,
+  shared: Option<&syn::Expr>,
+) -> syn::Result<proc_macro2::TokenStream> {
+  bundle.check_embedded(name, "`include_fs!(mod ..)`")?;
+  let entries = &bundle.entries;
+  let prefix_len = common_prefix_len(entries);
+  let mut root = Dir::default();
+
+  for entry in entries {
+    let components: Vec<_> = entry.path.split('/').skip(prefix_len).collect();
+    let (file_name, dirs) = components.split_last().unwrap();
+
+    if dirs
+      .first()
+      .is_some_and(|dir| module_name(dir) == PATHS_IDENT)
+    {
+      return Err(syn::Error::new_spanned(
+        name,
+        format!(
+          "{} conflicts with the generated {PATHS_IDENT} module",
+          entry.path
+        ),
+      ));
+    }
+
+    let mut dir = &mut root;
+    for component in dirs {
+      dir = dir
+        .dirs
+        .entry(module_name(component))
+        .or_insert_with(|| Dir {
+          name: component,
+          ..Default::default()
+        });
+
+      if dir.name != *component {
+        return Err(syn::Error::new_spanned(
+          name,
+          format!(
+            "Directories {} and {component} both map to module {}",
+            dir.name,
+            module_name(component),
+          ),
+        ));
+      }
+    }
+
+    let const_name = const_name(file_name);
+    if dirs.is_empty() && const_name == FS_IDENT {
+      return Err(syn::Error::new_spanned(
+        name,
+        format!(
+          "{} conflicts with the generated {FS_IDENT} static",
+          entry.path
+        ),
+      ));
+    }
+
+    if let Some(existing) = dir.files.insert(const_name.clone(), entry) {
+      return Err(syn::Error::new_spanned(
+        name,
+        format!(
+          "Files {} and {} both map to {const_name}",
+          existing.path, entry.path
+        ),
+      ));
+    }
+  }
+
+  let archive_bytes = archive_bytes_expr(bundle);
+  let fs_ident = format_ident!("{}", FS_IDENT);
+  let init = init_call(quote! { __ARCHIVE }, shared.map(|_| quote! { __DATA }));
+  let (data, imports) = match shared {
+    Some(shared) => (
+      quote! { #shared },
+      quote! { #[allow(unused_imports)] use super::*; },
+    ),
+    None => (quote! { __ARCHIVE }, quote! {}),
+  };
+
+  let contents = expand_dir(&root, 0);
+  let paths_ident = format_ident!("{}", PATHS_IDENT);
+  let paths = expand_paths(&root);
+
+  Ok(quote! {
+    #(#attrs)*
+    #vis mod #ident {
+      #imports
+
+      #[doc(hidden)]
+      static __ARCHIVE: &[u8] = #archive_bytes;
+
+      #[doc(hidden)]
+      static __DATA: &[u8] = #data;
+
+      /// The embedded bundle.
+      pub static #fs_ident: include_fs::IncludeFs = include_fs::IncludeFs::new(|| #init);
+
+      #contents
+
+      /// The path of every file, mirroring the directory structure, so typos in lookups are
+      /// compile errors.
+      pub mod #paths_ident {
+        #paths
+      }
+    }
+  })
+}
+
+fn expand_dir(dir: &Dir, depth: usize) -> proc_macro2::TokenStream {
+  let supers = (0..depth).map(|_| quote! { super:: });
+  let data = quote! { #(#supers)* __DATA };
+
+  // Compressed files can't be borrowed, they are only available through the bundle
+  let files = dir.files.iter().filter(|(_, entry)| !entry.compressed);
+  let files = files.map(|(const_name, entry)| {
+    let ident = format_ident!("{}", const_name);
+    let doc = format!("`{}`", entry.path);
+    let offset = entry.data_offset as usize;
+    let size = entry.size as usize;
+
+    quote! {
+      #[doc = #doc]
+      pub static #ident: &[u8] =
+        unsafe { ::core::slice::from_raw_parts(#data.as_ptr().add(#offset), #size) };
+    }
+  });
+
+  let dirs = dir.dirs.iter().map(|(module_name, child)| {
+    let ident = format_ident!("{}", module_name);
+    let doc = format!("`{}/`", child.name);
+    let contents = expand_dir(child, depth + 1);
+
+    quote! {
+      #[doc = #doc]
+      pub mod #ident {
+        #contents
+      }
+    }
+  });
+
+  quote! {
+    #(#files)*
+    #(#dirs)*
+  }
+}
+
+fn expand_paths(dir: &Dir) -> proc_macro2::TokenStream {
+  let files = dir.files.iter().map(|(const_name, entry)| {
+    let ident = format_ident!("{}", const_name);
+    let path = &entry.path;
+    let doc = format!("`{path}`");
+
+    quote! {
+      #[doc = #doc]
+      pub const #ident: &str = #path;
+    }
+  });
+
+  let dirs = dir.dirs.iter().map(|(module_name, child)| {
+    let ident = format_ident!("{}", module_name);
+    let doc = format!("`{}/`", child.name);
+    let contents = expand_paths(child);
+
+    quote! {
+      #[doc = #doc]
+      pub mod #ident {
+        #contents
+      }
+    }
+  });
+
+  quote! {
+    #(#files)*
+    #(#dirs)*
+  }
+}
+
+/// Number of leading directories that all paths have in common.
+fn common_prefix_len(entries: &[ArchiveEntry]) -> usize {
+  let mut dirs = entries.iter().map(|entry| {
+    let mut components: Vec<_> = entry.path.split('/').collect();
+    components.pop();
+    components
+  });
+
+  let Some(mut prefix) = dirs.next() else {
+    return 0;
+  };
+
+  for components in dirs {
+    let common = prefix
+      .iter()
+      .zip(&components)
+      .take_while(|(a, b)| a == b)
+      .count();
+    prefix.truncate(common);
+  }
+
+  prefix.len()
+}
+
+/// Convert a file name to an uppercase static name, e.g. `menu.svg` to `MENU_SVG`.
+fn const_name(name: &str) -> String {
+  identifier(name.to_ascii_uppercase())
+}
+
+/// Convert a directory name to a lowercase module name, e.g. `Sound Effects` to `sound_effects`.
+fn module_name(name: &str) -> String {
+  identifier(name.to_ascii_lowercase())
+}
+
+fn identifier(name: String) -> String {
+  let mut ident: String = name
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect();
+
+  if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) || ident == "_" {
+    ident.insert(0, '_');
+  }
+
+  // Keywords like `mod` or `self` can not be used as identifiers
+  if syn::parse_str::<syn::Ident>(&ident).is_err() {
+    ident.push('_');
+  }
+
+  ident
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::path::PathBuf;
+
+  #[test]
+  fn test_expand_stub() {
+    // Bundles written with `INCLUDE_FS_STUB` keep their paths, but have no data
+    let source = PathBuf::new();
+    let entries = [
+      ("assets/img/logo.png".to_string(), &source),
+      ("assets/index.html".to_string(), &source),
+    ];
+    let archive = crate::dir::write_archive(&entries, &[0, 0], &[]).unwrap();
+    let path = std::env::temp_dir().join(format!(
+      "include-fs-macros-stub-{}.embed_fs",
+      std::process::id()
+    ));
+    fs::write(&path, archive).unwrap();
+    let bundle = Bundle::read(path.clone()).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let name = syn::LitStr::new("assets", proc_macro2::Span::call_site());
+    let vis = syn::parse_quote!(pub);
+    let tokens = expand(
+      &name,
+      Vec::new(),
+      vis,
+      format_ident!("assets"),
+      &bundle,
+      None,
+    )
+    .unwrap()
+    .to_string();
+
+    assert!(tokens.contains("pub static INDEX_HTML"));
+    assert!(tokens.contains("pub mod img"));
+    assert!(tokens.contains("pub static LOGO_PNG"));
+    assert!(tokens.contains("\"assets/img/logo.png\""));
+  }
+}
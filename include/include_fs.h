@@ -0,0 +1,41 @@
+/* C interface for include-fs, enabled with the `ffi` feature. */
+
+#ifndef INCLUDE_FS_H
+#define INCLUDE_FS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque handle to an embedded bundle. */
+typedef struct InfsFs InfsFs;
+
+/* Called by infs_list for every path. The path is not NUL-terminated. */
+typedef void (*InfsListCallback)(const uint8_t *path, size_t len, void *user_data);
+
+/*
+ * Open a bundle registered with `include_fs::register` by name.
+ * Returns NULL if no such bundle is registered. The handle does not need to be freed.
+ */
+const InfsFs *infs_open(const char *name);
+
+/*
+ * Get the contents of a file, writing its length to out_len.
+ * Returns NULL if the file does not exist. The data does not need to be freed.
+ */
+const uint8_t *infs_get(const InfsFs *fs, const char *path, size_t *out_len);
+
+/*
+ * Call callback for every path in the bundle. Returns the number of paths, or 0 without
+ * listing anything if callback is NULL.
+ */
+size_t infs_list(const InfsFs *fs, InfsListCallback callback, void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* INCLUDE_FS_H */
@@ -0,0 +1,133 @@
+//! Serving embedded files with actix-web, like `actix-files` does for directories on disk.
+//!
+//! ```rust,ignore
+//! static ASSETS: IncludeFs = include_fs!("assets");
+//!
+//! HttpServer::new(|| App::new().service(Files::new("/static", &ASSETS).root("assets")))
+//! ```
+
+use actix_web::dev::{AppService, HttpServiceFactory};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{HttpRequest, HttpResponse, web};
+
+use crate::IncludeFsInner;
+use crate::serve::Server;
+
+/// A service that serves the files of an embedded filesystem below a mount path.
+///
+/// Requests are answered as described in [`http`](crate::http#serving), and missing files are
+/// `404 Not Found`.
+#[derive(Debug, Clone)]
+pub struct Files {
+  mount_path: String,
+  server: Server,
+}
+
+impl Files {
+  /// Serve the files of `fs` for requests below `mount_path`, so `/static/logo.png` is looked
+  /// up as `logo.png` for the mount path `/static`.
+  pub fn new(mount_path: &str, fs: &IncludeFsInner) -> Self {
+    Self {
+      mount_path: mount_path.trim_end_matches('/').to_string(),
+      server: Server::new(fs),
+    }
+  }
+
+  /// Serve the files below this directory, so `/static/logo.png` is looked up as
+  /// `{root}/logo.png`.
+  pub fn root(mut self, root: &str) -> Self {
+    self.server.set_root(root);
+    self
+  }
+
+  /// The file served for requests to a directory. Defaults to `index.html`, `None` answers
+  /// them with `404 Not Found`.
+  pub fn index_file(mut self, index_file: Option<&str>) -> Self {
+    self.server.set_index_file(index_file);
+    self
+  }
+
+  fn respond(&self, req: &HttpRequest) -> HttpResponse {
+    // actix-web strips the body of responses to HEAD requests, keeping its length
+    let method = match *req.method() {
+      Method::HEAD => "GET",
+      ref method => method.as_str(),
+    };
+
+    let path = req.match_info().get("tail").unwrap_or("");
+    let served = self
+      .server
+      .respond(method, path, |name| req.headers().get(name)?.to_str().ok());
+
+    let mut response = HttpResponse::build(StatusCode::from_u16(served.status).unwrap());
+    for header in served.headers {
+      response.insert_header(header);
+    }
+
+    response.body(served.body)
+  }
+}
+
+impl HttpServiceFactory for Files {
+  fn register(self, config: &mut AppService) {
+    let mut patterns = vec![format!("{}/{{tail:.*}}", self.mount_path)];
+    if !self.mount_path.is_empty() {
+      patterns.push(self.mount_path.clone());
+    }
+
+    web::resource(patterns)
+      .route(web::route().to(move |req: HttpRequest| {
+        let files = self.clone();
+        async move { files.respond(&req) }
+      }))
+      .register(config);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::serve::test_site;
+  use actix_web::body::MessageBody;
+  use actix_web::http::header;
+  use actix_web::test::TestRequest;
+
+  #[test]
+  fn test_files() {
+    let files = Files::new("/static/", &test_site()).root("public");
+    let request = |method, tail: &str| {
+      TestRequest::default()
+        .method(method)
+        .param("tail", tail.to_string())
+    };
+
+    let response = files.respond(&request(Method::GET, "index.html").to_http_request());
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+      response.headers().get(header::CONTENT_TYPE).unwrap(),
+      "text/html"
+    );
+    let etag = response.headers().get(header::ETAG).unwrap().clone();
+    assert_eq!(
+      response.into_body().try_into_bytes().unwrap(),
+      "<html></html>"
+    );
+
+    let req = request(Method::GET, "")
+      .insert_header((header::IF_NONE_MATCH, etag))
+      .to_http_request();
+    assert_eq!(files.respond(&req).status(), StatusCode::NOT_MODIFIED);
+
+    let response = files.respond(&request(Method::HEAD, "app.1234abcd.js").to_http_request());
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+      response.headers().get(header::CACHE_CONTROL).unwrap(),
+      crate::http::IMMUTABLE
+    );
+
+    let response = files.respond(&request(Method::GET, "missing.js").to_http_request());
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = files.respond(&request(Method::DELETE, "index.html").to_http_request());
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+  }
+}
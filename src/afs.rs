@@ -0,0 +1,247 @@
+//! Async functions over an embedded filesystem, named like their `tokio::fs` counterparts.
+//!
+//! Porting code from async filesystem I/O to embedded assets is mostly a matter of passing the
+//! filesystem along with the path:
+//!
+//! ```rust,ignore
+//! // Before
+//! let config = tokio::fs::read_to_string("assets/config.toml").await?;
+//!
+//! // After
+//! let config = include_fs::afs::read_to_string(&ASSETS, "assets/config.toml").await?;
+//! ```
+//!
+//! Errors are [`io::Error`]s with the same kinds the real filesystem would return. The data is
+//! already in memory, so the futures never block and work with any executor.
+
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{FsError, IncludeFsInner};
+
+/// Read the contents of a file.
+pub async fn read(fs: &IncludeFsInner, path: impl AsRef<str>) -> io::Result<Vec<u8>> {
+  Ok(fs.get(path).map_err(io_error)?.to_vec())
+}
+
+/// Read the contents of a file as a string.
+pub async fn read_to_string(fs: &IncludeFsInner, path: impl AsRef<str>) -> io::Result<String> {
+  let data = fs.get(path).map_err(io_error)?;
+  String::from_utf8(data.to_vec()).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Whether a file or directory exists.
+pub async fn try_exists(fs: &IncludeFsInner, path: impl AsRef<str>) -> io::Result<bool> {
+  let path = path.as_ref();
+  Ok(fs.exists(path) || fs.is_dir(path))
+}
+
+/// Query the metadata of a file or directory.
+pub async fn metadata(fs: &IncludeFsInner, path: impl AsRef<str>) -> io::Result<Metadata> {
+  metadata_of(fs, path.as_ref())
+}
+
+/// List the entries of a directory. The root directory is `""`.
+///
+/// Directories are not stored in the archive, so only directories that contain files exist.
+pub async fn read_dir(fs: &IncludeFsInner, path: impl AsRef<str>) -> io::Result<ReadDir> {
+  let entries = fs
+    .read_dir(path)
+    .map_err(io_error)?
+    .into_iter()
+    .map(|entry| (entry.path().to_string(), entry.is_dir()))
+    .collect::<Vec<_>>()
+    .into_iter();
+
+  Ok(ReadDir {
+    fs: fs.clone(),
+    entries,
+  })
+}
+
+/// Entries of a directory, returned by [`read_dir`]. Entries are sorted by name.
+pub struct ReadDir {
+  fs: IncludeFsInner,
+  entries: std::vec::IntoIter<(String, bool)>,
+}
+
+impl ReadDir {
+  /// The next entry of the directory, or `None` if all entries have been returned.
+  pub async fn next_entry(&mut self) -> io::Result<Option<DirEntry>> {
+    Ok(self.entries.next().map(|(path, is_dir)| DirEntry {
+      fs: self.fs.clone(),
+      path,
+      is_dir,
+    }))
+  }
+}
+
+/// An entry of a directory, returned by [`ReadDir::next_entry`].
+pub struct DirEntry {
+  fs: IncludeFsInner,
+  path: String,
+  is_dir: bool,
+}
+
+impl DirEntry {
+  /// The embedded path of the entry.
+  pub fn path(&self) -> &str {
+    &self.path
+  }
+
+  /// The name of the entry, without its directory.
+  pub fn file_name(&self) -> &str {
+    self.path.rsplit('/').next().unwrap_or(&self.path)
+  }
+
+  /// Whether the entry is a directory.
+  pub fn is_dir(&self) -> bool {
+    self.is_dir
+  }
+
+  /// Query the metadata of the entry.
+  pub async fn metadata(&self) -> io::Result<Metadata> {
+    metadata_of(&self.fs, &self.path)
+  }
+}
+
+/// Metadata of an embedded file or directory, returned by [`metadata`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+  len: u64,
+  is_dir: bool,
+  modified: Option<u64>,
+}
+
+impl Metadata {
+  /// The size of the file in bytes, or 0 for directories.
+  pub fn len(&self) -> u64 {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn is_file(&self) -> bool {
+    !self.is_dir
+  }
+
+  pub fn is_dir(&self) -> bool {
+    self.is_dir
+  }
+
+  /// The modification time, see [`IncludeFsInner::last_modified`]. Returns an
+  /// [`io::ErrorKind::Unsupported`] error if neither file metadata nor the build time were
+  /// stored.
+  pub fn modified(&self) -> io::Result<SystemTime> {
+    let secs = self.modified.ok_or_else(|| {
+      io::Error::new(
+        io::ErrorKind::Unsupported,
+        "modification time was not stored",
+      )
+    })?;
+
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+  }
+}
+
+fn metadata_of(fs: &IncludeFsInner, path: &str) -> io::Result<Metadata> {
+  if let Ok(data) = fs.get(path) {
+    return Ok(Metadata {
+      len: data.len() as u64,
+      is_dir: false,
+      modified: fs.last_modified(path).ok().flatten(),
+    });
+  }
+
+  if fs.is_dir(path) {
+    return Ok(Metadata {
+      len: 0,
+      is_dir: true,
+      modified: fs.build_info().timestamp,
+    });
+  }
+
+  Err(io_error(FsError::NotFound))
+}
+
+fn io_error(err: FsError) -> io::Error {
+  match err {
+    FsError::NotFound => io::Error::new(io::ErrorKind::NotFound, err),
+    FsError::NotADirectory => io::Error::new(io::ErrorKind::NotADirectory, err),
+    FsError::Io(err) => err,
+    err => io::Error::other(err),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+  use std::pin::pin;
+  use std::task::{Context, Poll, Waker};
+
+  /// Poll a future that is immediately ready.
+  fn ready<F: Future>(future: F) -> F::Output {
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+      Poll::Ready(output) => output,
+      Poll::Pending => panic!("future is not ready"),
+    }
+  }
+
+  #[test]
+  fn test_afs() {
+    let fs = test_fs(&[
+      ("assets/config.toml", b"debug = true"),
+      ("assets/img/logo.png", b"png"),
+      ("readme.md", b"readme"),
+    ]);
+
+    assert_eq!(
+      ready(read_to_string(&fs, "assets/config.toml")).unwrap(),
+      "debug = true"
+    );
+    assert_eq!(
+      ready(read(&fs, "missing")).unwrap_err().kind(),
+      io::ErrorKind::NotFound
+    );
+    assert!(ready(try_exists(&fs, "assets/img")).unwrap());
+    assert!(!ready(try_exists(&fs, "assets/im")).unwrap());
+
+    let meta = ready(metadata(&fs, "assets/img/logo.png")).unwrap();
+    assert!(meta.is_file());
+    assert_eq!(meta.len(), 3);
+    assert!(ready(metadata(&fs, "assets")).unwrap().is_dir());
+    assert!(meta.modified().is_err());
+
+    let mut dir = ready(read_dir(&fs, "assets/")).unwrap();
+    let mut entries = Vec::new();
+    while let Some(entry) = ready(dir.next_entry()).unwrap() {
+      entries.push((
+        entry.path().to_string(),
+        entry.file_name().to_string(),
+        entry.is_dir(),
+      ));
+    }
+
+    assert_eq!(
+      entries,
+      [
+        (
+          "assets/config.toml".to_string(),
+          "config.toml".to_string(),
+          false
+        ),
+        ("assets/img".to_string(), "img".to_string(), true),
+      ]
+    );
+
+    let mut root = ready(read_dir(&fs, "")).unwrap();
+    assert_eq!(ready(root.next_entry()).unwrap().unwrap().path(), "assets");
+    assert_eq!(
+      ready(read_dir(&fs, "readme.md")).err().unwrap().kind(),
+      io::ErrorKind::NotADirectory
+    );
+  }
+}
@@ -0,0 +1,788 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::io::Write;
+
+#[cfg(feature = "std")]
+use crate::http::Encoding;
+#[cfg(feature = "std")]
+use crate::{ArchiveError, ChecksumAlgorithm, Compression, SharedData};
+
+pub(crate) const MAGIC: &[u8; 4] = b"INFS";
+
+/// The file data is followed by a NUL byte, which is not included in the file size.
+pub(crate) const ATTR_NUL_TERMINATED: u8 = 1;
+/// The file data is encrypted with the given nonce. The stored data is the ciphertext of the file
+/// data and its NUL terminator, followed by the authentication tag.
+pub(crate) const ATTR_NONCE: u8 = 2;
+/// Checksum of the file data, computed with the algorithm of the archive.
+pub(crate) const ATTR_CHECKSUM: u8 = 3;
+/// The file was renamed by fingerprinting, and this is its path before renaming (UTF-8).
+pub(crate) const ATTR_ORIGINAL_PATH: u8 = 4;
+/// The file data and its NUL terminator are compressed with the given codec (u8), see
+/// [`Compression`]. The value also contains the size of the stored data (u64).
+pub(crate) const ATTR_COMPRESSION: u8 = 5;
+/// Modification time of the source file in seconds since the Unix epoch (u64).
+pub(crate) const ATTR_MODIFIED: u8 = 6;
+/// Unix permission bits of the source file (u32).
+pub(crate) const ATTR_MODE: u8 = 7;
+/// MIME type of the file, guessed from its extension at build time (UTF-8).
+pub(crate) const ATTR_MIME: u8 = 8;
+/// Quoted strong `ETag` of the file data (UTF-8).
+pub(crate) const ATTR_ETAG: u8 = 9;
+/// Precompressed variants of the file data, each the encoding (u8), see [`Encoding`], and the
+/// data offset (u64) and size (u64) of the variant. They don't include the NUL terminator.
+pub(crate) const ATTR_ENCODINGS: u8 = 10;
+/// Size of a variant in the value of [`ATTR_ENCODINGS`].
+pub(crate) const ENCODING_LEN: usize = 1 + 8 + 8;
+
+/// Length of the nonces of encrypted files.
+pub(crate) const NONCE_LEN: usize = 24;
+/// Length of the authentication tag appended to encrypted data.
+pub(crate) const TAG_LEN: usize = 16;
+
+/// Version of the archive format written by this crate, stored in the byte after the magic.
+///
+/// Bump this for incompatible changes, and keep the reader able to parse every version since
+/// [`MIN_FORMAT_VERSION`].
+pub(crate) const FORMAT_VERSION: u8 = 1;
+/// Oldest format version the reader can parse.
+pub(crate) const MIN_FORMAT_VERSION: u8 = 1;
+/// Size of the magic, format version, file count and archive attribute length.
+pub(crate) const HEADER_LEN: usize = 4 + 1 + 4 + 2;
+
+/// Paths are stored lowercased, and lookups must be lowercased as well.
+pub(crate) const ARCHIVE_ATTR_CASE_INSENSITIVE: u8 = 1;
+/// Version of the crate that built the bundle (UTF-8).
+pub(crate) const ARCHIVE_ATTR_CRATE_VERSION: u8 = 3;
+/// Build time in seconds since the Unix epoch (u64).
+pub(crate) const ARCHIVE_ATTR_BUILD_TIMESTAMP: u8 = 4;
+/// User-provided build label (UTF-8).
+pub(crate) const ARCHIVE_ATTR_BUILD_LABEL: u8 = 5;
+/// File data is stored in a separate shared data file with the given name (UTF-8), and data
+/// offsets point into that file.
+pub(crate) const ARCHIVE_ATTR_SHARED_DATA: u8 = 6;
+/// File data is encrypted. The value is a nonce followed by the encryption of an empty message,
+/// which is used to check the key. Without [`ARCHIVE_ATTR_KDF`], the key is supplied as it is.
+pub(crate) const ARCHIVE_ATTR_ENCRYPTED: u8 = 7;
+/// The key is derived from a passphrase with Argon2id. The value is the memory cost, time cost
+/// and parallelism (u32 each), followed by the salt.
+pub(crate) const ARCHIVE_ATTR_KDF: u8 = 8;
+/// Paths of the license and attribution files in the archive (UTF-8), sorted by directory and
+/// separated by NUL bytes.
+pub(crate) const ARCHIVE_ATTR_ATTRIBUTIONS: u8 = 9;
+/// Algorithm of the file checksums (u8), see [`ChecksumAlgorithm`].
+pub(crate) const ARCHIVE_ATTR_CHECKSUM: u8 = 10;
+/// The archive is linked from a static library, exported under the given symbol (UTF-8). Only
+/// read by `include_fs!`.
+#[cfg(feature = "std")]
+pub(crate) const ARCHIVE_ATTR_STATIC_LIB: u8 = 11;
+/// The archive ends with a lookup table for the entries. The value is the offset of the table
+/// (u64) and its number of buckets (u32).
+///
+/// The table consists of the buckets, each the lower 32 bits of the FNV-1a hash of a path (u32)
+/// and the index of its entry plus one (u32), or zero for empty buckets, followed by the offset
+/// of every entry in the header (u32). Collisions are resolved with linear probing.
+pub(crate) const ARCHIVE_ATTR_LOOKUP_TABLE: u8 = 12;
+/// Files are read from the given directory (UTF-8) at runtime, and paths are relative to it.
+/// Only written for debug builds of bundles with passthrough enabled.
+pub(crate) const ARCHIVE_ATTR_SOURCE_DIR: u8 = 13;
+/// Readers keep the index as a list sorted by path instead of a map. Has no value, and is
+/// ignored for archives with a lookup table.
+pub(crate) const ARCHIVE_ATTR_SORTED_INDEX: u8 = 14;
+/// Paths of directories without any files (UTF-8), sorted and separated by NUL bytes. Their
+/// parent directories are not listed.
+pub(crate) const ARCHIVE_ATTR_EMPTY_DIRS: u8 = 15;
+/// The data offset of every file is a multiple of the given alignment (u32), and `include_fs!`
+/// aligns the archive to it.
+#[cfg(feature = "std")]
+pub(crate) const ARCHIVE_ATTR_ALIGNMENT: u8 = 16;
+/// Ed25519 signature of the SHA-512 hash of the whole archive, with the signature itself
+/// zeroed.
+#[cfg(feature = "std")]
+pub(crate) const ARCHIVE_ATTR_SIGNATURE: u8 = 17;
+/// The files have no data and are only read from the source directory. Has no value, and is
+/// only written for debug builds of bundles that aren't embedded in them.
+pub(crate) const ARCHIVE_ATTR_STUB: u8 = 18;
+
+/// Length of the value of [`ARCHIVE_ATTR_SIGNATURE`].
+#[cfg(feature = "std")]
+pub(crate) const SIGNATURE_LEN: usize = 64;
+
+/// Size of a lookup table bucket.
+pub(crate) const BUCKET_SIZE: usize = 8;
+
+/// Parameters for deriving the key of an encrypted archive from a passphrase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct KdfParams {
+  pub m_cost: u32,
+  pub t_cost: u32,
+  pub p_cost: u32,
+  pub salt: Vec<u8>,
+}
+
+impl KdfParams {
+  #[cfg(feature = "std")]
+  pub fn encode(&self) -> Vec<u8> {
+    let mut value = Vec::with_capacity(12 + self.salt.len());
+    value.extend_from_slice(&self.m_cost.to_le_bytes());
+    value.extend_from_slice(&self.t_cost.to_le_bytes());
+    value.extend_from_slice(&self.p_cost.to_le_bytes());
+    value.extend_from_slice(&self.salt);
+    value
+  }
+
+  pub fn decode(value: &[u8]) -> Option<Self> {
+    let u32_at = |i: usize| Some(u32::from_le_bytes(value.get(i..i + 4)?.try_into().ok()?));
+
+    Some(Self {
+      m_cost: u32_at(0)?,
+      t_cost: u32_at(4)?,
+      p_cost: u32_at(8)?,
+      salt: value[12..].to_vec(),
+    })
+  }
+}
+
+/// Options that apply to the archive as a whole.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub(crate) struct ArchiveOptions {
+  pub case_insensitive: bool,
+  pub crate_version: Option<String>,
+  pub build_timestamp: Option<u64>,
+  pub build_label: Option<String>,
+  pub shared_data: Option<String>,
+  pub key_check: Option<Vec<u8>>,
+  pub kdf: Option<KdfParams>,
+  pub attributions: Vec<String>,
+  pub checksum: Option<ChecksumAlgorithm>,
+  pub static_lib: Option<String>,
+  pub lookup_table: bool,
+  pub sorted_index: bool,
+  pub source_dir: Option<String>,
+  pub empty_dirs: Vec<String>,
+  pub alignment: Option<u32>,
+  /// Reserve space for a signature, which is filled in after the archive is written.
+  pub signature: bool,
+  pub stub: bool,
+}
+
+#[cfg(feature = "std")]
+impl ArchiveOptions {
+  fn attributes(&self) -> Result<Vec<u8>, ArchiveError> {
+    let mut attrs = Vec::new();
+    if self.case_insensitive {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_CASE_INSENSITIVE, &[])?;
+    }
+
+    if let Some(version) = &self.crate_version {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_CRATE_VERSION, version.as_bytes())?;
+    }
+
+    if let Some(timestamp) = self.build_timestamp {
+      push_attribute(
+        &mut attrs,
+        ARCHIVE_ATTR_BUILD_TIMESTAMP,
+        &timestamp.to_le_bytes(),
+      )?;
+    }
+
+    if let Some(label) = &self.build_label {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_BUILD_LABEL, label.as_bytes())?;
+    }
+
+    if let Some(name) = &self.shared_data {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_SHARED_DATA, name.as_bytes())?;
+    }
+
+    if let Some(key_check) = &self.key_check {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_ENCRYPTED, key_check)?;
+    }
+
+    if let Some(kdf) = &self.kdf {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_KDF, &kdf.encode())?;
+    }
+
+    if !self.attributions.is_empty() {
+      let value = self.attributions.join("\0");
+      push_attribute(&mut attrs, ARCHIVE_ATTR_ATTRIBUTIONS, value.as_bytes())?;
+    }
+
+    if let Some(algorithm) = self.checksum {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_CHECKSUM, &[algorithm.id()])?;
+    }
+
+    if let Some(symbol) = &self.static_lib {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_STATIC_LIB, symbol.as_bytes())?;
+    }
+
+    if let Some(dir) = &self.source_dir {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_SOURCE_DIR, dir.as_bytes())?;
+    }
+
+    if self.sorted_index {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_SORTED_INDEX, &[])?;
+    }
+
+    if !self.empty_dirs.is_empty() {
+      let value = self.empty_dirs.join("\0");
+      push_attribute(&mut attrs, ARCHIVE_ATTR_EMPTY_DIRS, value.as_bytes())?;
+    }
+
+    if let Some(alignment) = self.alignment {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_ALIGNMENT, &alignment.to_le_bytes())?;
+    }
+
+    if self.signature {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_SIGNATURE, &[0; SIGNATURE_LEN])?;
+    }
+
+    if self.stub {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_STUB, &[])?;
+    }
+
+    // The value is filled in once the size of the header is known, so this has to come last
+    if self.lookup_table {
+      push_attribute(&mut attrs, ARCHIVE_ATTR_LOOKUP_TABLE, &[0; 12])?;
+    }
+
+    Ok(attrs)
+  }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct FileEntry {
+  pub path: String,
+  pub data: Vec<u8>,
+  pub nul_terminated: bool,
+  /// The nonce, if `data` has been encrypted.
+  pub nonce: Option<[u8; NONCE_LEN]>,
+  /// Checksum of the unencrypted data.
+  pub checksum: Option<Vec<u8>>,
+  /// The path before fingerprinting, if the file was renamed.
+  pub original_path: Option<String>,
+  /// The codec and uncompressed size, if `data` has been compressed.
+  pub compression: Option<(Compression, u64)>,
+  /// Modification time in seconds since the Unix epoch.
+  pub modified: Option<u64>,
+  /// Unix permission bits.
+  pub mode: Option<u32>,
+  /// MIME type of the file.
+  pub mime: Option<String>,
+  /// Quoted `ETag` of the unencrypted data.
+  pub etag: Option<String>,
+  /// Precompressed variants of the data, sorted by encoding.
+  pub encodings: Vec<(Encoding, Vec<u8>)>,
+}
+
+#[cfg(feature = "std")]
+impl FileEntry {
+  pub fn new(path: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+    Self {
+      path: path.into(),
+      data: data.into(),
+      nul_terminated: false,
+      nonce: None,
+      checksum: None,
+      original_path: None,
+      compression: None,
+      modified: None,
+      mode: None,
+      mime: None,
+      etag: None,
+      encodings: Vec::new(),
+    }
+  }
+
+  /// Size of the file, excluding any trailing NUL byte and encryption overhead.
+  fn size(&self) -> u64 {
+    match (self.compression, self.nonce) {
+      (Some((_, size)), _) => size,
+      (None, Some(_)) => (self.data.len() - TAG_LEN) as u64 - self.nul_terminated as u64,
+      (None, None) => self.data.len() as u64,
+    }
+  }
+
+  /// Whether the data has been compressed or encrypted, which includes the NUL terminator in
+  /// `data`.
+  fn is_encoded(&self) -> bool {
+    self.compression.is_some() || self.nonce.is_some()
+  }
+
+  /// Size of the file data in the archive, including any trailing NUL byte.
+  pub fn stored_size(&self) -> u64 {
+    match self.is_encoded() {
+      true => self.data.len() as u64,
+      false => self.data.len() as u64 + self.nul_terminated as u64,
+    }
+  }
+
+  /// The data stored for this file, followed by its precompressed variants. Each is returned
+  /// without the NUL terminator that is written after it if `true`.
+  pub fn stored_parts(&self) -> impl Iterator<Item = (&[u8], bool)> {
+    let variants = self
+      .encodings
+      .iter()
+      .map(|(_, data)| (data.as_slice(), false));
+    core::iter::once((
+      self.data.as_slice(),
+      self.nul_terminated && !self.is_encoded(),
+    ))
+    .chain(variants)
+  }
+
+  /// Encode the optional attributes of this entry as a sequence of
+  /// `tag (u8), length (u16), value` records, given the data offsets of its precompressed
+  /// variants.
+  fn attributes(&self, encoding_offsets: &[u64]) -> Result<Vec<u8>, ArchiveError> {
+    let mut attrs = Vec::new();
+    if self.nul_terminated {
+      push_attribute(&mut attrs, ATTR_NUL_TERMINATED, &[])?;
+    }
+
+    if let Some(nonce) = &self.nonce {
+      push_attribute(&mut attrs, ATTR_NONCE, nonce)?;
+    }
+
+    if let Some(checksum) = &self.checksum {
+      push_attribute(&mut attrs, ATTR_CHECKSUM, checksum)?;
+    }
+
+    if let Some(original_path) = &self.original_path {
+      push_attribute(&mut attrs, ATTR_ORIGINAL_PATH, original_path.as_bytes())?;
+    }
+
+    if let Some((compression, _)) = self.compression {
+      let mut value = vec![compression.id()];
+      value.extend_from_slice(&self.stored_size().to_le_bytes());
+      push_attribute(&mut attrs, ATTR_COMPRESSION, &value)?;
+    }
+
+    if let Some(modified) = self.modified {
+      push_attribute(&mut attrs, ATTR_MODIFIED, &modified.to_le_bytes())?;
+    }
+
+    if let Some(mode) = self.mode {
+      push_attribute(&mut attrs, ATTR_MODE, &mode.to_le_bytes())?;
+    }
+
+    if let Some(mime) = &self.mime {
+      push_attribute(&mut attrs, ATTR_MIME, mime.as_bytes())?;
+    }
+
+    if let Some(etag) = &self.etag {
+      push_attribute(&mut attrs, ATTR_ETAG, etag.as_bytes())?;
+    }
+
+    if !self.encodings.is_empty() {
+      let mut value = Vec::with_capacity(self.encodings.len() * ENCODING_LEN);
+      for ((encoding, data), offset) in self.encodings.iter().zip(encoding_offsets) {
+        value.push(encoding.id());
+        value.extend_from_slice(&offset.to_le_bytes());
+        value.extend_from_slice(&(data.len() as u64).to_le_bytes());
+      }
+
+      push_attribute(&mut attrs, ATTR_ENCODINGS, &value)?;
+    }
+
+    Ok(attrs)
+  }
+}
+
+#[cfg(feature = "std")]
+fn push_attribute(attrs: &mut Vec<u8>, tag: u8, value: &[u8]) -> Result<(), ArchiveError> {
+  attrs.push(tag);
+  attrs.extend_from_slice(&attributes_len(value)?.to_le_bytes());
+  attrs.extend_from_slice(value);
+  Ok(())
+}
+
+/// The length of an attribute value or a sequence of attributes, which has to fit in a `u16`.
+#[cfg(feature = "std")]
+fn attributes_len(attrs: &[u8]) -> Result<u16, ArchiveError> {
+  u16::try_from(attrs.len()).map_err(|_| ArchiveError::AttributesTooLong {
+    len: attrs.len(),
+    max: u16::MAX as usize,
+  })
+}
+
+/// 64-bit FNV-1a, used because its output is stable across Rust versions.
+pub(crate) struct Fnv1a(pub u64);
+
+impl Default for Fnv1a {
+  fn default() -> Self {
+    Self(0xcbf29ce484222325)
+  }
+}
+
+impl Fnv1a {
+  pub fn write(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      self.0 ^= byte as u64;
+      self.0 = self.0.wrapping_mul(0x100000001b3);
+    }
+  }
+
+  pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hash = Self::default();
+    hash.write(bytes);
+    hash.0
+  }
+}
+
+#[cfg(test)]
+pub(crate) fn compute_header(
+  files: &[FileEntry],
+  options: &ArchiveOptions,
+) -> Result<Vec<u8>, ArchiveError> {
+  Ok(compute_header_with_offsets(files, options, None)?.0)
+}
+
+/// Compute the header and the lookup table, if enabled. If `offsets` is set, they are used as
+/// the data offsets of the [stored parts](FileEntry::stored_parts) of every file instead of
+/// placing the data directly after the header.
+#[cfg(feature = "std")]
+fn compute_header_with_offsets(
+  files: &[FileEntry],
+  options: &ArchiveOptions,
+  offsets: Option<&[Vec<u64>]>,
+) -> Result<(Vec<u8>, Vec<u8>), ArchiveError> {
+  // Validate file count fits in u32
+  if files.len() > u32::MAX as usize {
+    return Err(ArchiveError::TooManyFiles {
+      count: files.len(),
+      max: u32::MAX as usize,
+    });
+  }
+
+  let mut archive_attrs = options.attributes()?;
+  let archive_attrs_len = attributes_len(&archive_attrs)?;
+
+  // The length of the attributes doesn't depend on the offsets of the variants, which are only
+  // known once the size of the header is
+  let mut header_size = HEADER_LEN + archive_attrs.len();
+  for file in files {
+    let path_len = file.path.len();
+
+    if path_len > u16::MAX as usize {
+      return Err(ArchiveError::PathTooLong {
+        path: file.path.clone(),
+        len: path_len,
+        max: u16::MAX as usize,
+      });
+    }
+
+    // path_len + path + size + offset + attrs_len + attrs
+    let attrs_len = attributes_len(&file.attributes(&vec![0; file.encodings.len()])?)?;
+    header_size += 2 + path_len + 8 + 8 + 2 + attrs_len as usize;
+  }
+
+  let (offsets, data_end) = match offsets {
+    Some(offsets) => (offsets.to_vec(), header_size as u64),
+    None => inline_offsets(files, header_size as u64, options.alignment),
+  };
+
+  let bucket_count = (files.len() * 2).max(1) as u32;
+  if options.lookup_table {
+    let table_offset = data_end;
+    let value_start = archive_attrs.len() - 12;
+    archive_attrs[value_start..value_start + 8].copy_from_slice(&table_offset.to_le_bytes());
+    archive_attrs[value_start + 8..].copy_from_slice(&bucket_count.to_le_bytes());
+  }
+
+  let mut header = Vec::with_capacity(header_size);
+
+  header.extend_from_slice(MAGIC);
+  header.push(FORMAT_VERSION);
+  header.extend_from_slice(&(files.len() as u32).to_le_bytes());
+  header.extend_from_slice(&archive_attrs_len.to_le_bytes());
+  header.extend_from_slice(&archive_attrs);
+
+  let mut entry_offsets = Vec::with_capacity(files.len());
+  for (file, offsets) in files.iter().zip(offsets) {
+    let path_bytes = file.path.as_bytes();
+    let size = file.size();
+    let data_offset = offsets[0];
+    let attrs = file.attributes(&offsets[1..])?;
+
+    entry_offsets.push(header.len() as u32);
+    header.extend_from_slice(&(path_bytes.len() as u16).to_le_bytes());
+    header.extend_from_slice(path_bytes);
+    header.extend_from_slice(&size.to_le_bytes());
+    header.extend_from_slice(&data_offset.to_le_bytes());
+    header.extend_from_slice(&attributes_len(&attrs)?.to_le_bytes());
+    header.extend_from_slice(&attrs);
+  }
+
+  let table = match options.lookup_table {
+    true => lookup_table(files, &entry_offsets, bucket_count),
+    false => Vec::new(),
+  };
+
+  Ok((header, table))
+}
+
+/// The offsets of the [stored parts](FileEntry::stored_parts) of every file when they are
+/// stored from `start` on, and the end of the data.
+///
+/// Files with identical stored data, like the same icon in several directories, share their
+/// data, so it is only stored once. Data is stored in the order of `files` otherwise, padded to
+/// `alignment`.
+#[cfg(feature = "std")]
+fn inline_offsets(files: &[FileEntry], start: u64, alignment: Option<u32>) -> (Vec<Vec<u64>>, u64) {
+  let alignment = alignment.unwrap_or(1) as u64;
+  let mut stored = HashMap::new();
+  let mut offsets = Vec::with_capacity(files.len());
+  let mut end = start;
+  for file in files {
+    let file_offsets = file.stored_parts().map(|(data, nul_terminated)| {
+      *stored.entry((data, nul_terminated)).or_insert_with(|| {
+        let offset = end.next_multiple_of(alignment);
+        end = offset + data.len() as u64 + nul_terminated as u64;
+        offset
+      })
+    });
+
+    offsets.push(file_offsets.collect());
+  }
+
+  (offsets, end)
+}
+
+/// Build the lookup table described in [`ARCHIVE_ATTR_LOOKUP_TABLE`].
+#[cfg(feature = "std")]
+fn lookup_table(files: &[FileEntry], entry_offsets: &[u32], bucket_count: u32) -> Vec<u8> {
+  let mut buckets = vec![0; bucket_count as usize * BUCKET_SIZE];
+  for (i, file) in files.iter().enumerate() {
+    let hash = Fnv1a::hash(file.path.as_bytes());
+    let mut bucket = (hash % bucket_count as u64) as usize;
+    while buckets[bucket * BUCKET_SIZE + 4..bucket * BUCKET_SIZE + 8] != [0; 4] {
+      bucket = (bucket + 1) % bucket_count as usize;
+    }
+
+    let start = bucket * BUCKET_SIZE;
+    buckets[start..start + 4].copy_from_slice(&(hash as u32).to_le_bytes());
+    buckets[start + 4..start + 8].copy_from_slice(&(i as u32 + 1).to_le_bytes());
+  }
+
+  for offset in entry_offsets {
+    buckets.extend_from_slice(&offset.to_le_bytes());
+  }
+
+  buckets
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn write_archive<W: Write>(
+  files: &[FileEntry],
+  options: &ArchiveOptions,
+  out: &mut W,
+) -> Result<(), ArchiveError> {
+  // Write header
+  let (header, table) = compute_header_with_offsets(files, options, None)?;
+  out.write_all(&header)?;
+
+  // Write file data, skipping files that share the data of an earlier file
+  let mut written = header.len() as u64;
+  let (offsets, _) = inline_offsets(files, written, options.alignment);
+  for (file, offsets) in files.iter().zip(offsets) {
+    for ((data, nul_terminated), offset) in file.stored_parts().zip(offsets) {
+      if offset >= written {
+        out.write_all(&vec![0; (offset - written) as usize])?;
+        out.write_all(data)?;
+        if nul_terminated {
+          out.write_all(&[0])?;
+        }
+
+        written = offset + data.len() as u64 + nul_terminated as u64;
+      }
+    }
+  }
+
+  out.write_all(&table)?;
+  Ok(())
+}
+
+/// Write an archive that only contains the header, adding the file data to `shared`.
+#[cfg(feature = "std")]
+pub(crate) fn write_shared_archive<W: Write>(
+  files: &[FileEntry],
+  options: &ArchiveOptions,
+  shared: &mut SharedData,
+  out: &mut W,
+) -> Result<(), ArchiveError> {
+  let offsets: Vec<Vec<_>> = files
+    .iter()
+    .map(|file| {
+      let parts = file.stored_parts();
+      parts
+        .map(|(data, nul_terminated)| shared.insert(data, nul_terminated))
+        .collect()
+    })
+    .collect();
+  let (header, table) = compute_header_with_offsets(files, options, Some(&offsets))?;
+  out.write_all(&header)?;
+  out.write_all(&table)?;
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_compute_header() {
+    let files = vec![
+      FileEntry::new("src/main.rs", vec![0; 1024]),
+      FileEntry::new("assets/image.png", vec![0; 2048]),
+    ];
+
+    let header = compute_header(&files, &ArchiveOptions::default()).unwrap();
+
+    // Verify magic
+    assert_eq!(&header[0..4], b"INFS");
+
+    assert_eq!(header[4], FORMAT_VERSION);
+
+    // Verify file count
+    let file_count = u32::from_le_bytes([header[5], header[6], header[7], header[8]]);
+    assert_eq!(file_count, 2);
+
+    // Basic size check (exact calculation depends on path lengths)
+    let expected_min_size = HEADER_LEN +
+      2 + "src/main.rs".len() + 8 + 8 + 2 + // first file
+      2 + "assets/image.png".len() + 8 + 8 + 2; // second file
+
+    assert_eq!(header.len(), expected_min_size);
+  }
+
+  #[test]
+  fn test_deduplicates_data() {
+    let files = vec![
+      FileEntry::new("dark/icon.svg", "icon"),
+      FileEntry::new("light/icon.svg", "icon"),
+      FileEntry::new("readme.md", "readme"),
+    ];
+
+    let options = ArchiveOptions::default();
+    let header = compute_header(&files, &options).unwrap();
+    let mut archive = Vec::new();
+    write_archive(&files, &options, &mut archive).unwrap();
+    assert_eq!(&archive[header.len()..], b"iconreadme");
+
+    let fs = crate::IncludeFsInner::new(archive.leak()).unwrap();
+    assert_eq!(fs.get("light/icon.svg").unwrap(), b"icon");
+    assert_eq!(fs.get("readme.md").unwrap(), b"readme");
+    assert_eq!(
+      fs.raw_parts("dark/icon.svg").unwrap(),
+      fs.raw_parts("light/icon.svg").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_alignment() {
+    let files = vec![
+      FileEntry::new("a.bin", vec![1; 3]),
+      FileEntry::new("b.bin", vec![2; 70]),
+    ];
+
+    let options = ArchiveOptions {
+      alignment: Some(64),
+      lookup_table: true,
+      ..Default::default()
+    };
+    let mut archive = Vec::new();
+    write_archive(&files, &options, &mut archive).unwrap();
+
+    let fs = crate::IncludeFsInner::new(archive.leak()).unwrap();
+    for (path, data) in [("a.bin", [1; 3].as_slice()), ("b.bin", &[2; 70])] {
+      let (offset, len) = fs.raw_parts(path).unwrap();
+      assert_eq!(offset % 64, 0);
+      assert_eq!(&fs.archive_bytes()[offset..offset + len], data);
+    }
+  }
+
+  #[test]
+  fn test_encodings() {
+    let mut style = FileEntry::new("style.css", "body {}");
+    style.nul_terminated = true;
+    style.encodings = vec![
+      (Encoding::Brotli, b"brotli".to_vec()),
+      (Encoding::Gzip, b"gzip".to_vec()),
+    ];
+    let files = vec![style, FileEntry::new("script.js", "gzip")];
+
+    let options = ArchiveOptions {
+      alignment: Some(4),
+      ..Default::default()
+    };
+    let mut archive = Vec::new();
+    write_archive(&files, &options, &mut archive).unwrap();
+
+    let fs = crate::IncludeFsInner::new(archive.leak()).unwrap();
+    assert_eq!(fs.get_cstr("style.css").unwrap().to_bytes(), b"body {}");
+    let entry = fs.metadata("style.css").unwrap();
+    assert_eq!(
+      entry.encodings().collect::<Vec<_>>(),
+      [Encoding::Brotli, Encoding::Gzip]
+    );
+    assert_eq!(
+      fs.get_encoded("style.css", Encoding::Brotli).unwrap(),
+      Some(&b"brotli"[..])
+    );
+    assert_eq!(
+      fs.get_encoded("style.css", Encoding::Gzip).unwrap(),
+      Some(&b"gzip"[..])
+    );
+
+    // The second file shares its data with the gzip variant of the first
+    assert_eq!(fs.get("script.js").unwrap(), b"gzip");
+    assert_eq!(fs.get_encoded("script.js", Encoding::Gzip).unwrap(), None);
+    assert!(fs.get_encoded("missing.js", Encoding::Gzip).is_err());
+  }
+
+  #[test]
+  fn test_path_too_long() {
+    let long_path = "a".repeat(u16::MAX as usize + 1);
+    let files = vec![FileEntry::new(long_path.clone(), vec![0; 100])];
+
+    let result = compute_header(&files, &ArchiveOptions::default());
+    assert!(matches!(result, Err(ArchiveError::PathTooLong { .. })));
+
+    if let Err(ArchiveError::PathTooLong { path, len, max }) = result {
+      assert_eq!(path, long_path);
+      assert_eq!(len, u16::MAX as usize + 1);
+      assert_eq!(max, u16::MAX as usize);
+    }
+  }
+
+  #[test]
+  fn test_attributes_too_long() {
+    let files = [FileEntry::new("a.txt", "a")];
+
+    // Each directory fits in an attribute, but not all of them together
+    let options = ArchiveOptions {
+      empty_dirs: (0..10_000).map(|i| format!("empty/{i:05}")).collect(),
+      ..Default::default()
+    };
+    assert!(matches!(
+      compute_header(&files, &options),
+      Err(ArchiveError::AttributesTooLong { len, max: 65535 }) if len > 65535
+    ));
+
+    // A value that fits on its own, with other attributes that push the total over the limit
+    let options = ArchiveOptions {
+      build_label: Some("a".repeat(u16::MAX as usize - 3)),
+      case_insensitive: true,
+      ..Default::default()
+    };
+    assert!(matches!(
+      compute_header(&files, &options),
+      Err(ArchiveError::AttributesTooLong { .. })
+    ));
+  }
+}
@@ -0,0 +1,169 @@
+//! Assertion macros for tests that depend on embedded files.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::IncludeFsInner;
+
+/// Maximum number of paths listed in a failure message.
+const MAX_NEARBY: usize = 10;
+
+/// Assert that a file is embedded in the given filesystem.
+///
+/// On failure, the panic message lists embedded paths close to the one that was requested,
+/// which usually makes typos and wrong directory prefixes obvious.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// static TEMPLATES: IncludeFs = include_fs!("templates");
+///
+/// #[test]
+/// fn welcome_template_is_embedded() {
+///   assert_embedded!(TEMPLATES, "templates/welcome.html");
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_embedded {
+  ($fs:expr, $path:expr $(,)?) => {{
+    let fs: &$crate::IncludeFsInner = &$fs;
+    let path: &str = ::core::convert::AsRef::as_ref(&$path);
+    if !fs.exists(path) {
+      ::core::panic!("{}", $crate::assert::__not_embedded_message(fs, path));
+    }
+  }};
+}
+
+/// Assert that an embedded file exists and has the expected contents.
+///
+/// The expected value can be anything that implements `AsRef<[u8]>`, such as `&str`, `&[u8]` or
+/// `Vec<u8>`. Like [`assert_embedded!`], a missing file produces a message listing nearby paths.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// static CONFIG: IncludeFs = include_fs!("config");
+///
+/// #[test]
+/// fn default_config_is_embedded() {
+///   assert_embedded_eq!(CONFIG, "config/default.toml", include_str!("../config/default.toml"));
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_embedded_eq {
+  ($fs:expr, $path:expr, $expected:expr $(,)?) => {{
+    let fs: &$crate::IncludeFsInner = &$fs;
+    let path: &str = ::core::convert::AsRef::as_ref(&$path);
+    let expected: &[u8] = ::core::convert::AsRef::as_ref(&$expected);
+    match fs.get(path) {
+      Ok(actual) if actual == expected => {}
+      Ok(actual) => {
+        ::core::panic!(
+          "{}",
+          $crate::assert::__mismatch_message(path, actual, expected)
+        );
+      }
+      Err(_) => {
+        ::core::panic!("{}", $crate::assert::__not_embedded_message(fs, path));
+      }
+    }
+  }};
+}
+
+#[doc(hidden)]
+pub fn __not_embedded_message(fs: &IncludeFsInner, path: &str) -> String {
+  let nearby = nearby_paths(fs, path);
+  let mut message = format!("file `{path}` is not embedded");
+
+  if nearby.is_empty() {
+    message.push_str(" (the filesystem is empty)");
+    return message;
+  }
+
+  message.push_str("\nnearby paths:");
+  for candidate in &nearby {
+    message.push_str("\n  ");
+    message.push_str(candidate);
+  }
+
+  let total = fs.list_paths().len();
+  if total > nearby.len() {
+    message.push_str(&format!("\n  ... ({} more)", total - nearby.len()));
+  }
+
+  message
+}
+
+#[doc(hidden)]
+pub fn __mismatch_message(path: &str, actual: &[u8], expected: &[u8]) -> String {
+  match (core::str::from_utf8(actual), core::str::from_utf8(expected)) {
+    (Ok(actual), Ok(expected)) => format!(
+      "embedded file `{path}` does not match expected contents\n  actual: {actual:?}\nexpected: {expected:?}"
+    ),
+    _ => format!(
+      "embedded file `{path}` does not match expected contents ({} bytes embedded, {} bytes expected)",
+      actual.len(),
+      expected.len(),
+    ),
+  }
+}
+
+/// Find embedded paths that are likely to be what the caller meant.
+///
+/// Paths are ranked by the length of the prefix they share with the requested path, with paths
+/// that have the same file name ranked first.
+fn nearby_paths<'a>(fs: &'a IncludeFsInner, path: &str) -> Vec<&'a str> {
+  let file_name = path.rsplit('/').next().unwrap_or(path);
+
+  let mut candidates: Vec<(bool, usize, &str)> = fs
+    .list_paths()
+    .into_iter()
+    .map(|candidate| {
+      let same_name = candidate.rsplit('/').next() == Some(file_name);
+      let common = candidate
+        .bytes()
+        .zip(path.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+      (same_name, common, candidate.as_str())
+    })
+    .collect();
+
+  candidates.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then(a.2.cmp(b.2)));
+  candidates
+    .into_iter()
+    .take(MAX_NEARBY)
+    .map(|(_, _, candidate)| candidate)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_assert_embedded() {
+    let fs = test_fs(&[("templates/welcome.html", b"<h1>hi</h1>")]);
+    assert_embedded!(fs, "templates/welcome.html");
+    assert_embedded_eq!(fs, "templates/welcome.html", "<h1>hi</h1>");
+  }
+
+  #[test]
+  #[should_panic(expected = "nearby paths:\n  templates/welcome.html")]
+  fn test_assert_embedded_lists_nearby() {
+    let fs = test_fs(&[
+      ("config/default.toml", b""),
+      ("templates/welcome.html", b""),
+    ]);
+    assert_embedded!(fs, "template/welcome.html");
+  }
+
+  #[test]
+  #[should_panic(expected = "does not match expected contents")]
+  fn test_assert_embedded_eq_mismatch() {
+    let fs = test_fs(&[("config/default.toml", b"a = 1")]);
+    assert_embedded_eq!(fs, "config/default.toml", "a = 2");
+  }
+}
@@ -0,0 +1,187 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{EmbeddedPath, FsError, IncludeFsInner};
+
+/// File names, without extension, that are collected by [`Bundle::attributions`]. Suffixes like
+/// in `LICENSE-MIT` are allowed as well.
+///
+/// [`Bundle::attributions`]: crate::Bundle::attributions
+#[cfg(feature = "std")]
+const ATTRIBUTION_NAMES: [&str; 6] = [
+  "license",
+  "licence",
+  "copying",
+  "notice",
+  "attribution",
+  "credits",
+];
+
+/// Whether a file contains license or attribution information, based on its name.
+#[cfg(feature = "std")]
+pub(crate) fn is_attribution_file(path: &str) -> bool {
+  let name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+  let stem = name.split('.').next().unwrap_or(&name);
+
+  ATTRIBUTION_NAMES.iter().any(|attribution| {
+    stem
+      .strip_prefix(attribution)
+      .is_some_and(|rest| rest.is_empty() || rest.starts_with(['-', '_']))
+  })
+}
+
+/// License and attribution files that apply to a directory of an embedded filesystem, see
+/// [`IncludeFsInner::attributions`].
+#[derive(Clone)]
+pub struct Attribution<'a> {
+  fs: &'a IncludeFsInner,
+  directory: &'a str,
+  files: Vec<&'a EmbeddedPath>,
+}
+
+impl fmt::Debug for Attribution<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Attribution")
+      .field("directory", &self.directory)
+      .field("files", &self.files)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<'a> Attribution<'a> {
+  /// The directory containing the attribution files, or an empty string for the root.
+  pub fn directory(&self) -> &'a str {
+    self.directory
+  }
+
+  /// The attribution files in the directory, sorted by path.
+  pub fn files(&self) -> &[&'a EmbeddedPath] {
+    &self.files
+  }
+
+  /// The contents of the attribution files, in the same order as [`files`](Self::files).
+  pub fn texts(&self) -> Result<Vec<&'a str>, FsError> {
+    self
+      .files
+      .iter()
+      .map(|path| {
+        let data = self.fs.get(path)?;
+        core::str::from_utf8(data).map_err(|_| FsError::InvalidArchive)
+      })
+      .collect()
+  }
+
+  /// Whether the attribution applies to the file at `path`, i.e. the file is in its directory or
+  /// one of its subdirectories.
+  pub fn covers(&self, path: impl AsRef<str>) -> bool {
+    let path = path.as_ref();
+    self.directory.is_empty()
+      || path
+        .strip_prefix(self.directory)
+        .is_some_and(|rest| rest.starts_with('/'))
+  }
+}
+
+impl IncludeFsInner {
+  /// The license and attribution files collected with [`Bundle::attributions`], grouped by
+  /// directory and sorted by directory.
+  ///
+  /// This is meant for building credits screens for third-party assets:
+  ///
+  /// ```rust,ignore
+  /// for attribution in ASSETS.attributions() {
+  ///   println!("Assets in {}/:", attribution.directory());
+  ///   for text in attribution.texts()? {
+  ///     println!("{text}");
+  ///   }
+  /// }
+  /// ```
+  ///
+  /// [`Bundle::attributions`]: crate::Bundle::attributions
+  pub fn attributions(&self) -> Vec<Attribution<'_>> {
+    let mut attributions: Vec<Attribution> = Vec::new();
+    for path in &self.index.attributions {
+      let path = EmbeddedPath::new_unchecked(path);
+      let directory = path.rsplit_once('/').map_or("", |(directory, _)| directory);
+
+      match attributions.last_mut() {
+        Some(last) if last.directory == directory => last.files.push(path),
+        _ => attributions.push(Attribution {
+          fs: self,
+          directory,
+          files: vec![path],
+        }),
+      }
+    }
+
+    attributions
+  }
+
+  /// The attribution of the closest directory containing the file at `path`.
+  pub fn attribution_for(&self, path: impl AsRef<str>) -> Option<Attribution<'_>> {
+    let path = path.as_ref();
+    self
+      .attributions()
+      .into_iter()
+      .filter(|attribution| attribution.covers(path))
+      .max_by_key(|attribution| attribution.directory.len())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::archive::{ArchiveOptions, FileEntry};
+  use crate::tests::test_fs_with;
+
+  #[test]
+  fn test_is_attribution_file() {
+    assert!(is_attribution_file("LICENSE"));
+    assert!(is_attribution_file("fonts/inter/LICENSE-OFL.txt"));
+    assert!(is_attribution_file("sounds/Credits.md"));
+    assert!(is_attribution_file("NOTICE"));
+    assert!(!is_attribution_file("licensed.png"));
+    assert!(!is_attribution_file("license/readme.txt"));
+  }
+
+  #[test]
+  fn test_attributions() {
+    let files = [
+      FileEntry::new("LICENSE", "MIT"),
+      FileEntry::new("fonts/inter/LICENSE.txt", "OFL"),
+      FileEntry::new("fonts/inter/NOTICE", "Copyright Inter"),
+      FileEntry::new("fonts/inter/inter.ttf", "font"),
+      FileEntry::new("fonts/other.ttf", "font"),
+    ];
+
+    let options = ArchiveOptions {
+      attributions: vec![
+        "LICENSE".to_string(),
+        "fonts/inter/LICENSE.txt".to_string(),
+        "fonts/inter/NOTICE".to_string(),
+      ],
+      ..Default::default()
+    };
+
+    let fs = test_fs_with(&files, &options);
+
+    let attributions = fs.attributions();
+    assert_eq!(attributions.len(), 2);
+    assert_eq!(attributions[0].directory(), "");
+    assert_eq!(attributions[1].directory(), "fonts/inter");
+    assert_eq!(
+      attributions[1].files(),
+      ["fonts/inter/LICENSE.txt", "fonts/inter/NOTICE"]
+    );
+    assert_eq!(attributions[1].texts().unwrap(), ["OFL", "Copyright Inter"]);
+
+    let inter = fs.attribution_for("fonts/inter/inter.ttf").unwrap();
+    assert_eq!(inter.directory(), "fonts/inter");
+    assert_eq!(
+      fs.attribution_for("fonts/other.ttf").unwrap().directory(),
+      ""
+    );
+    assert!(!inter.covers("fonts/interior.ttf"));
+  }
+}
@@ -0,0 +1,154 @@
+use std::convert::Infallible;
+use std::future::{Ready, ready};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::header::{self, HeaderName, HeaderValue};
+use http::{Request, Response, StatusCode};
+use http_body_util::Full;
+use tower_service::Service;
+
+use crate::IncludeFsInner;
+use crate::serve::Server;
+
+/// A [`tower_service::Service`] that serves the files of an embedded filesystem, for axum or
+/// any other server built on tower.
+///
+/// ```rust,ignore
+/// static ASSETS: IncludeFs = include_fs!("assets");
+///
+/// let app = Router::new().fallback_service(ServeEmbedded::new(&ASSETS).root("assets"));
+/// ```
+///
+/// Requests are answered as described in [`http`](crate::http#serving), and missing files are
+/// `404 Not Found`.
+#[derive(Debug, Clone)]
+pub struct ServeEmbedded {
+  server: Server,
+}
+
+impl ServeEmbedded {
+  pub fn new(fs: &IncludeFsInner) -> Self {
+    Self {
+      server: Server::new(fs),
+    }
+  }
+
+  /// Serve the files below this directory, so `/logo.png` is looked up as `{root}/logo.png`.
+  pub fn root(mut self, root: &str) -> Self {
+    self.server.set_root(root);
+    self
+  }
+
+  /// The file served for requests to a directory. Defaults to `index.html`, `None` answers
+  /// them with `404 Not Found`.
+  pub fn index_file(mut self, index_file: Option<&str>) -> Self {
+    self.server.set_index_file(index_file);
+    self
+  }
+}
+
+impl<B> Service<Request<B>> for ServeEmbedded {
+  type Response = Response<Full<Bytes>>;
+  type Error = Infallible;
+  type Future = Ready<Result<Self::Response, Infallible>>;
+
+  fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn call(&mut self, req: Request<B>) -> Self::Future {
+    let served = self
+      .server
+      .respond(req.method().as_str(), req.uri().path(), |name| {
+        req.headers().get(name)?.to_str().ok()
+      });
+
+    let mut response = Response::new(Full::new(served.body));
+    *response.status_mut() = StatusCode::from_u16(served.status).unwrap();
+
+    let headers = response.headers_mut();
+    if served.status == 200 {
+      headers.insert(header::CONTENT_LENGTH, HeaderValue::from(served.len));
+    }
+
+    for (name, value) in served.headers {
+      if let Ok(value) = HeaderValue::from_str(&value) {
+        headers.insert(HeaderName::from_static(name), value);
+      }
+    }
+
+    ready(Ok(response))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::serve::test_site;
+  use http::Method;
+  use std::pin::pin;
+  use std::task::Waker;
+
+  fn get(
+    service: &mut ServeEmbedded,
+    method: Method,
+    uri: &str,
+    headers: &[(HeaderName, &str)],
+  ) -> Response<Full<Bytes>> {
+    let mut req = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+      req = req.header(name, *value);
+    }
+
+    let future = service.call(req.body(()).unwrap());
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+      Poll::Ready(response) => response.unwrap(),
+      Poll::Pending => panic!("future is not ready"),
+    }
+  }
+
+  fn body(response: Response<Full<Bytes>>) -> Bytes {
+    use http_body_util::BodyExt;
+
+    let future = response.into_body().collect();
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+      Poll::Ready(collected) => collected.unwrap().to_bytes(),
+      Poll::Pending => panic!("future is not ready"),
+    }
+  }
+
+  #[test]
+  fn test_serve_embedded() {
+    let mut service = ServeEmbedded::new(&test_site()).root("public");
+
+    let response = get(&mut service, Method::GET, "/", &[]);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html");
+    assert_eq!(response.headers()[header::CONTENT_LENGTH], "13");
+    let etag = response.headers()[header::ETAG].clone();
+    assert_eq!(body(response), "<html></html>");
+
+    let if_none_match = etag.to_str().unwrap();
+    let response = get(
+      &mut service,
+      Method::GET,
+      "/index.html",
+      &[(header::IF_NONE_MATCH, if_none_match)],
+    );
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], etag);
+
+    let response = get(&mut service, Method::HEAD, "/app.1234abcd.js", &[]);
+    assert_eq!(response.headers()[header::CONTENT_LENGTH], "3");
+    assert!(body(response).is_empty());
+
+    let response = get(&mut service, Method::POST, "/index.html", &[]);
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[header::ALLOW], "GET, HEAD");
+    assert_eq!(
+      get(&mut service, Method::GET, "/missing.js", &[]).status(),
+      StatusCode::NOT_FOUND
+    );
+  }
+}
@@ -0,0 +1,321 @@
+//! Inspect and extract `.embed_fs` archives, or the bundles embedded in a binary.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use include_fs::{FsError, IncludeFsInner};
+
+/// Magic bytes at the start of every archive.
+const MAGIC: &[u8; 4] = b"INFS";
+
+#[derive(Parser)]
+#[command(name = "include-fs", version, about)]
+struct Cli {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// List the embedded files.
+  List {
+    #[command(flatten)]
+    source: Source,
+    /// Show the size and encoding of every file.
+    #[arg(short, long)]
+    long: bool,
+  },
+  /// Write a file to stdout.
+  Cat {
+    #[command(flatten)]
+    source: Source,
+    path: String,
+  },
+  /// Write the embedded files into a directory.
+  Extract {
+    #[command(flatten)]
+    source: Source,
+    dir: PathBuf,
+    /// Only extract the files below this directory, relative to it.
+    #[arg(long, default_value = "")]
+    subtree: String,
+  },
+  /// Show how an archive was built, or the metadata of a file.
+  Stat {
+    #[command(flatten)]
+    source: Source,
+    path: Option<String>,
+  },
+}
+
+#[derive(Args)]
+struct Source {
+  /// An `.embed_fs` archive, or a binary that embeds one.
+  file: PathBuf,
+  /// Which bundle to use if the binary embeds several, as listed when none is given.
+  #[arg(long)]
+  archive: Option<usize>,
+}
+
+/// An archive found in a file.
+struct Found {
+  offset: usize,
+  fs: Result<IncludeFsInner, FsError>,
+}
+
+/// Find the archives in `bytes`, which is an archive itself or a binary that embeds them.
+///
+/// Every occurrence of the magic bytes is a candidate, and those without a valid header are
+/// skipped. Archives that are valid but can't be opened, like encrypted ones, are kept.
+fn find_archives(bytes: &'static [u8]) -> Vec<Found> {
+  let mut found = Vec::new();
+  let mut start = 0;
+  while let Some(pos) = bytes[start..].windows(4).position(|window| window == MAGIC) {
+    let offset = start + pos;
+    start = offset + 1;
+
+    let fs = IncludeFsInner::new(&bytes[offset..]);
+    if !matches!(
+      fs,
+      Err(FsError::InvalidArchive | FsError::UnsupportedVersion(_))
+    ) {
+      found.push(Found { offset, fs });
+    }
+  }
+
+  found
+}
+
+impl Source {
+  fn open(&self) -> Result<IncludeFsInner, Box<dyn Error>> {
+    let bytes: &'static [u8] = fs::read(&self.file)?.leak();
+    let mut found = find_archives(bytes);
+
+    let index = match (self.archive, found.len()) {
+      (_, 0) => return Err(format!("no archive found in {}", self.file.display()).into()),
+      (Some(index), len) if index >= len => {
+        return Err(format!("--archive {index} is out of range, found {len} archives").into());
+      }
+      (Some(index), _) => index,
+      (None, 1) => 0,
+      (None, _) => {
+        let mut message = format!("{} embeds several archives:", self.file.display());
+        for (index, archive) in found.iter().enumerate() {
+          let description = match &archive.fs {
+            Ok(fs) => format!("{} files", fs.list_paths().len()),
+            Err(err) => err.to_string(),
+          };
+
+          message.push_str(&format!(
+            "\n  {index}: at offset {:#x}, {description}",
+            archive.offset
+          ));
+        }
+
+        message.push_str("\nselect one with --archive");
+        return Err(message.into());
+      }
+    };
+
+    Ok(found.swap_remove(index).fs?)
+  }
+}
+
+fn list(fs: &IncludeFsInner, long: bool) -> Result<(), Box<dyn Error>> {
+  let mut paths = fs.list_paths();
+  paths.sort();
+
+  let mut out = io::stdout().lock();
+  for path in paths {
+    if !long {
+      writeln!(out, "{path}")?;
+      continue;
+    }
+
+    let entry = fs.metadata(path)?;
+    let mut encoding = Vec::new();
+    if let Some(compression) = entry.compression() {
+      encoding.push(format!("{compression:?}").to_lowercase());
+    }
+
+    if entry.is_encrypted() {
+      encoding.push("encrypted".to_string());
+    }
+
+    writeln!(
+      out,
+      "{:>10}  {:<16}  {path}",
+      entry.size(),
+      encoding.join(",")
+    )?;
+  }
+
+  Ok(())
+}
+
+fn extract(fs: &IncludeFsInner, dir: &Path, subtree: &str) -> Result<(), Box<dyn Error>> {
+  let subtree = subtree.trim_matches('/');
+  for path in fs.list_paths() {
+    let relative = match subtree {
+      "" => path.as_str(),
+      subtree => match path
+        .strip_prefix(subtree)
+        .and_then(|rest| rest.strip_prefix('/'))
+      {
+        Some(relative) => relative,
+        None => continue,
+      },
+    };
+
+    // Paths are checked to be relative without `..` components when the archive is parsed, so
+    // they stay inside `dir`
+    let target = dir.join(relative);
+    if let Some(parent) = target.parent() {
+      fs::create_dir_all(parent)?;
+    }
+
+    fs::write(&target, fs.get(path)?)?;
+    println!("{}", target.display());
+  }
+
+  Ok(())
+}
+
+fn stat(fs: &IncludeFsInner, path: Option<&str>) -> Result<(), Box<dyn Error>> {
+  let Some(path) = path else {
+    let info = fs.build_info();
+    let paths = fs.list_paths();
+    let total: u64 = paths
+      .iter()
+      .filter_map(|path| fs.metadata(path).ok())
+      .map(|entry| entry.size())
+      .sum();
+
+    println!("format version: {}", info.format_version);
+    if let Some(version) = &info.crate_version {
+      println!("crate version:  {version}");
+    }
+
+    if let Some(timestamp) = info.timestamp {
+      println!(
+        "built:          {}",
+        include_fs::http::format_date(timestamp)
+      );
+    }
+
+    if let Some(label) = &info.label {
+      println!("label:          {label}");
+    }
+
+    if let Some(algorithm) = fs.checksum_algorithm() {
+      println!("checksums:      {algorithm:?}");
+    }
+
+    println!("files:          {}", paths.len());
+    println!("total size:     {total} bytes");
+    return Ok(());
+  };
+
+  let entry = fs.metadata(path)?;
+  println!("size:        {} bytes", entry.size());
+  if let Some(compression) = entry.compression() {
+    println!("compression: {compression:?}");
+  }
+
+  println!("encrypted:   {}", entry.is_encrypted());
+  if let Some(checksum) = entry.checksum() {
+    let hex: String = checksum.iter().map(|byte| format!("{byte:02x}")).collect();
+    println!("checksum:    {hex}");
+  }
+
+  if let Some(modified) = entry.modified() {
+    println!("modified:    {}", include_fs::http::format_date(modified));
+  }
+
+  if let Some(mode) = entry.mode() {
+    println!("mode:        {mode:o}");
+  }
+
+  if let Some(etag) = entry.etag() {
+    println!("etag:        {etag}");
+  }
+
+  Ok(())
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+  match cli.command {
+    Command::List { source, long } => list(&source.open()?, long),
+    Command::Cat { source, path } => {
+      let fs = source.open()?;
+      io::stdout().lock().write_all(fs.get(&path)?)?;
+      Ok(())
+    }
+    Command::Extract {
+      source,
+      dir,
+      subtree,
+    } => extract(&source.open()?, &dir, &subtree),
+    Command::Stat { source, path } => stat(&source.open()?, path.as_deref()),
+  }
+}
+
+fn main() -> ExitCode {
+  match run(Cli::parse()) {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(err) => {
+      eprintln!("include-fs: {err}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use include_fs::ArchiveWriter;
+
+  #[test]
+  fn test_find_archives() {
+    let mut writer = ArchiveWriter::new();
+    writer.add_bytes("a.txt", "a").unwrap();
+    writer.add_bytes("dir/b.txt", "b").unwrap();
+
+    // An archive after unrelated data, including a stray magic
+    let mut binary = b"\x7fELF....INFS\x01junk".to_vec();
+    let offset = binary.len();
+    writer.write_to(&mut binary).unwrap();
+    binary.extend_from_slice(b"trailing data");
+
+    let found = find_archives(binary.leak());
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].offset, offset);
+
+    let fs = found[0].fs.as_ref().unwrap();
+    assert_eq!(fs.get("dir/b.txt").unwrap(), b"b");
+    assert!(find_archives(b"no archives here").is_empty());
+  }
+
+  #[test]
+  fn test_rejects_escaping_paths() {
+    let mut writer = ArchiveWriter::new();
+    writer.add_bytes("_tmp/escaped.txt", "x").unwrap();
+    let mut archive = Vec::new();
+    writer.write_to(&mut archive).unwrap();
+
+    // Archives with paths that would be written outside the target directory are not opened
+    for escaping in ["/tmp/escaped.txt", "../t/escaped.txt"] {
+      let mut archive = archive.clone();
+      let pos = archive
+        .windows(4)
+        .position(|window| window == b"_tmp")
+        .unwrap();
+      archive[pos..pos + escaping.len()].copy_from_slice(escaping.as_bytes());
+      assert!(find_archives(archive.leak()).is_empty());
+    }
+  }
+}
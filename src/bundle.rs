@@ -0,0 +1,2746 @@
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::{self, File, Metadata};
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::archive::{ArchiveOptions, FileEntry, write_archive, write_shared_archive};
+use crate::attribution::is_attribution_file;
+use crate::compression::{compress_file, precompress_file};
+use crate::fingerprint::fingerprint;
+use crate::http::Encoding;
+use crate::incremental::{BuildCache, InputHasher};
+use crate::{ArchiveError, ChecksumAlgorithm, Compression, EmbeddedPath, SharedData};
+
+/// Name of the ignore file in the source directory root.
+const EMBEDIGNORE: &str = ".embedignore";
+
+/// Environment variable that makes build scripts write bundles without file data, see
+/// [`Bundle::build`].
+const STUB_ENV: &str = "INCLUDE_FS_STUB";
+
+/// How deep the walk descends when following symlinks, unless [`Bundle::max_depth`] is set.
+const FOLLOW_SYMLINKS_MAX_DEPTH: usize = 64;
+
+/// The largest alignment supported by [`Bundle::align_data`], the page size on most targets.
+const MAX_ALIGNMENT: usize = 4096;
+
+/// How many of the largest files are listed when [`Bundle::max_total_size`] is exceeded.
+const LARGEST_FILES_LISTED: usize = 10;
+
+/// Bundle a directory to be embedded in the binary.
+/// This function must be called in a build script.
+///
+/// The directory path must be a subdirectory of the manifest directory. The name of the bundle
+/// must later be used as an argument to the `include_fs!` macro.
+///
+/// This is a shorthand for `Bundle::new(dir).build(bundle_name)`. See [`Bundle`] for more options.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// // In build.rs
+/// include_fs::bundle("assets", "assets").unwrap();
+/// include_fs::bundle("./static/public", "public").unwrap();
+///
+/// // In main.rs
+/// static ASSETS: IncludeFs = include_fs!("assets");
+/// static PUBLIC: IncludeFs = include_fs!("public");
+/// ```
+pub fn bundle<P: AsRef<Path>>(dir: P, bundle_name: &str) -> Result<(), ArchiveError> {
+  Bundle::new(dir).build(bundle_name)
+}
+
+/// Builder for bundling a directory with custom options.
+/// The bundle must be built in a build script.
+///
+/// Files can be excluded by placing a `.embedignore` file in the root of the source directory.
+/// It uses the same syntax as `.gitignore`, with paths relative to the source directory.
+///
+/// Archives are reproducible: the same source tree produces byte-identical output regardless of
+/// the platform or filesystem it is built on. Entries are sorted by path, paths always use `/`
+/// as their separator and are relative to the manifest directory, and compression is
+/// deterministic. The exceptions are options that store information about the build itself:
+///
+/// - [`build_timestamp`](Self::build_timestamp) and [`file_metadata`](Self::file_metadata) store
+///   times, which are taken from and clamped to `SOURCE_DATE_EPOCH` if it is set
+/// - Encryption uses random nonces and salts
+/// - [`passthrough`](Self::passthrough) and [`embed_in_debug`](Self::embed_in_debug) store the
+///   absolute source directory, in debug builds only
+///
+/// # Example
+///
+/// ```rust,ignore
+/// // In build.rs
+/// include_fs::Bundle::new("templates")
+///   .normalize_line_endings(true)
+///   .build("templates")
+///   .unwrap();
+/// ```
+pub struct Bundle {
+  source: Source,
+  /// Additional source directories, with the prefix they are mounted under.
+  extra_dirs: Vec<(PathBuf, Option<String>)>,
+  normalize_line_endings: bool,
+  nul_terminated_extensions: Vec<String>,
+  included_extensions: Option<Vec<String>>,
+  strip_source_dir: bool,
+  allow_external: bool,
+  include_globs: Vec<String>,
+  exclude_globs: Vec<String>,
+  exclude_larger_than: Option<u64>,
+  max_file_size: Option<u64>,
+  max_total_size: Option<u64>,
+  filters: Vec<Box<FilterFn>>,
+  validators: Vec<(String, Box<ValidateFn>)>,
+  precompilers: Vec<(String, Box<PrecompileFn>)>,
+  breadth_first: bool,
+  sort_by: Option<Box<SortFn>>,
+  max_depth: Option<usize>,
+  same_file_system: bool,
+  skip_hidden: bool,
+  respect_gitignore: bool,
+  follow_symlinks: bool,
+  empty_dirs: bool,
+  broken_symlinks: BrokenSymlinks,
+  case_insensitive: bool,
+  #[cfg(feature = "unicode-normalization")]
+  normalize_unicode: bool,
+  report_skipped: bool,
+  build_timestamp: bool,
+  build_label: Option<String>,
+  optional: bool,
+  attributions: bool,
+  checksum: Option<ChecksumAlgorithm>,
+  etags: bool,
+  compression: Option<Compression>,
+  precompress: Vec<Encoding>,
+  lookup_table: bool,
+  sorted_index: bool,
+  alignment: Option<usize>,
+  passthrough: bool,
+  embed_in_debug: bool,
+  file_metadata: bool,
+  #[cfg(feature = "mime")]
+  mime_types: bool,
+  fingerprint: Vec<String>,
+  #[cfg(feature = "static-lib")]
+  static_lib: bool,
+  #[cfg(feature = "encryption")]
+  secret: Option<Secret>,
+  #[cfg(feature = "signing")]
+  signing_key: Option<[u8; 32]>,
+  incremental: bool,
+  /// The `cargo:` directives printed so far, to print them again when the archive is reused.
+  directives: RefCell<Vec<String>>,
+}
+
+/// What the key of an encrypted bundle comes from.
+#[cfg(feature = "encryption")]
+enum Secret {
+  Passphrase(String),
+  Key([u8; 32]),
+}
+
+#[derive(Debug)]
+enum Source {
+  Dir(PathBuf),
+  Archive(PathBuf),
+}
+
+type FilterFn = dyn Fn(&str, &Metadata, &[u8]) -> Decision;
+type ValidateFn = dyn Fn(&[u8]) -> Result<(), String>;
+type PrecompileFn = dyn Fn(&str, &[u8]) -> Result<Vec<u8>, String>;
+type SortFn = dyn Fn(&str, &str) -> Ordering;
+
+/// How to handle symlinks that point to a file that doesn't exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BrokenSymlinks {
+  /// Fail the build.
+  Error,
+  /// Skip the symlink silently.
+  Skip,
+  /// Skip the symlink and print a cargo warning.
+  #[default]
+  Warn,
+}
+
+/// Whether a file should be embedded, as returned by [`Bundle::filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+  Include,
+  Skip,
+}
+
+impl fmt::Debug for Bundle {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Bundle")
+      .field("source", &self.source)
+      .field("extra_dirs", &self.extra_dirs)
+      .field("normalize_line_endings", &self.normalize_line_endings)
+      .field("nul_terminated_extensions", &self.nul_terminated_extensions)
+      .field("included_extensions", &self.included_extensions)
+      .field("strip_source_dir", &self.strip_source_dir)
+      .field("allow_external", &self.allow_external)
+      .field("include_globs", &self.include_globs)
+      .field("exclude_globs", &self.exclude_globs)
+      .field("exclude_larger_than", &self.exclude_larger_than)
+      .field("max_file_size", &self.max_file_size)
+      .field("max_total_size", &self.max_total_size)
+      .field("breadth_first", &self.breadth_first)
+      .field("max_depth", &self.max_depth)
+      .field("same_file_system", &self.same_file_system)
+      .field("skip_hidden", &self.skip_hidden)
+      .field("respect_gitignore", &self.respect_gitignore)
+      .field("follow_symlinks", &self.follow_symlinks)
+      .field("empty_dirs", &self.empty_dirs)
+      .field("broken_symlinks", &self.broken_symlinks)
+      .field("case_insensitive", &self.case_insensitive)
+      .field("report_skipped", &self.report_skipped)
+      .field("build_timestamp", &self.build_timestamp)
+      .field("build_label", &self.build_label)
+      .field("optional", &self.optional)
+      .field("attributions", &self.attributions)
+      .field("checksum", &self.checksum)
+      .field("etags", &self.etags)
+      .field("compression", &self.compression)
+      .field("precompress", &self.precompress)
+      .field("lookup_table", &self.lookup_table)
+      .field("sorted_index", &self.sorted_index)
+      .field("alignment", &self.alignment)
+      .field("passthrough", &self.passthrough)
+      .field("embed_in_debug", &self.embed_in_debug)
+      .field("file_metadata", &self.file_metadata)
+      .field("fingerprint", &self.fingerprint)
+      .field("incremental", &self.incremental)
+      .finish_non_exhaustive()
+  }
+}
+
+impl Bundle {
+  /// Create a new bundle for the given directory, relative to the manifest directory.
+  pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+    Self::with_source(Source::Dir(dir.as_ref().to_path_buf()))
+  }
+
+  /// Create a new bundle from the contents of a `.tar`, `.tar.gz`, `.tgz` or `.zip` file.
+  ///
+  /// The path is relative to the manifest directory, or absolute. Paths in the bundle are the
+  /// paths inside the archive. Tar files require the `tar` feature, and zip files require the
+  /// `zip` feature.
+  ///
+  /// ```rust,ignore
+  /// // In build.rs, after downloading the frontend build
+  /// include_fs::Bundle::from_archive("frontend-dist.tar.gz")
+  ///   .build("frontend")
+  ///   .unwrap();
+  /// ```
+  pub fn from_archive<P: AsRef<Path>>(path: P) -> Self {
+    Self::with_source(Source::Archive(path.as_ref().to_path_buf()))
+  }
+
+  /// Add the files of another directory, relative to the manifest directory, to the bundle.
+  ///
+  /// Paths are stored like the paths of the main source directory. Use
+  /// [`add_dir_at`](Self::add_dir_at) to avoid collisions between directories, which fail the
+  /// build with [`ArchiveError::DuplicatePath`].
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("frontend/dist")
+  ///   .add_dir("shared/assets")
+  ///   .build("web")
+  ///   .unwrap();
+  /// ```
+  pub fn add_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+    self.extra_dirs.push((dir.as_ref().to_path_buf(), None));
+    self
+  }
+
+  /// Add the files of another directory to the bundle, with paths relative to that directory
+  /// under `prefix`. Adding `shared/assets` at `static` stores `shared/assets/logo.png` as
+  /// `static/logo.png`, and an empty prefix mounts the directory at the root.
+  ///
+  /// Files of mounted directories are not read from disk in [`passthrough`](Self::passthrough)
+  /// mode.
+  pub fn add_dir_at<P: AsRef<Path>>(mut self, dir: P, prefix: &str) -> Self {
+    self
+      .extra_dirs
+      .push((dir.as_ref().to_path_buf(), Some(prefix.to_string())));
+    self
+  }
+
+  fn with_source(source: Source) -> Self {
+    Self {
+      source,
+      extra_dirs: Vec::new(),
+      normalize_line_endings: false,
+      nul_terminated_extensions: Vec::new(),
+      included_extensions: None,
+      strip_source_dir: false,
+      allow_external: false,
+      include_globs: Vec::new(),
+      exclude_globs: Vec::new(),
+      exclude_larger_than: None,
+      max_file_size: None,
+      max_total_size: None,
+      filters: Vec::new(),
+      validators: Vec::new(),
+      precompilers: Vec::new(),
+      breadth_first: false,
+      sort_by: None,
+      max_depth: None,
+      same_file_system: false,
+      skip_hidden: false,
+      respect_gitignore: false,
+      follow_symlinks: false,
+      empty_dirs: false,
+      broken_symlinks: BrokenSymlinks::default(),
+      case_insensitive: false,
+      #[cfg(feature = "unicode-normalization")]
+      normalize_unicode: false,
+      report_skipped: false,
+      build_timestamp: false,
+      build_label: None,
+      optional: false,
+      attributions: false,
+      checksum: None,
+      etags: false,
+      compression: None,
+      precompress: Vec::new(),
+      lookup_table: false,
+      sorted_index: false,
+      alignment: None,
+      passthrough: false,
+      embed_in_debug: true,
+      file_metadata: false,
+      #[cfg(feature = "mime")]
+      mime_types: false,
+      fingerprint: Vec::new(),
+      #[cfg(feature = "static-lib")]
+      static_lib: false,
+      #[cfg(feature = "encryption")]
+      secret: None,
+      #[cfg(feature = "signing")]
+      signing_key: None,
+      incremental: true,
+      directives: RefCell::new(Vec::new()),
+    }
+  }
+
+  /// Convert `\r\n` line endings to `\n` in text files.
+  ///
+  /// Git checkouts on Windows commonly convert line endings, which makes the same commit
+  /// produce different archives depending on the platform. Files are treated as text if they
+  /// are valid UTF-8 and contain no NUL bytes. Disabled by default.
+  pub fn normalize_line_endings(mut self, normalize: bool) -> Self {
+    self.normalize_line_endings = normalize;
+    self
+  }
+
+  /// Append a NUL byte to files with the given extensions, so they can be read with
+  /// [`IncludeFsInner::get_cstr`](crate::IncludeFsInner::get_cstr).
+  ///
+  /// This is useful for passing embedded shaders or scripts to C libraries that expect
+  /// NUL-terminated strings. Building fails if a matching file contains a NUL byte.
+  pub fn nul_terminate_extensions<I, S>(mut self, extensions: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    self
+      .nul_terminated_extensions
+      .extend(extensions.into_iter().map(Into::into));
+    self
+  }
+
+  /// Only embed files with one of the given extensions, skipping everything else.
+  ///
+  /// Extensions are given without the leading dot and compared case-insensitively. Calling this
+  /// multiple times adds to the allowed extensions.
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("public")
+  ///   .include_extensions(["html", "css", "js", "png"])
+  ///   .build("public")
+  ///   .unwrap();
+  /// ```
+  pub fn include_extensions<I, S>(mut self, extensions: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    self
+      .included_extensions
+      .get_or_insert_with(Vec::new)
+      .extend(extensions.into_iter().map(Into::into));
+    self
+  }
+
+  /// Store paths relative to the source directory instead of the manifest directory, so
+  /// `assets/logo.png` is looked up as `logo.png`. Disabled by default.
+  ///
+  /// Globs passed to other options are matched against the stripped paths. Bundles built from
+  /// source archives always use paths relative to the archive root.
+  pub fn strip_source_dir(mut self, strip: bool) -> Self {
+    self.strip_source_dir = strip;
+    self
+  }
+
+  /// Allow source directories outside the manifest directory, like shared assets in
+  /// `../assets` next to a workspace member. Disabled by default, so a typo in a relative path
+  /// doesn't embed an unrelated directory.
+  ///
+  /// Paths of files in external directories are relative to the directory itself, as with
+  /// [`strip_source_dir`](Self::strip_source_dir), since they have no path relative to the
+  /// manifest directory.
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("../assets")
+  ///   .allow_external(true)
+  ///   .build("assets")?;
+  /// ```
+  pub fn allow_external(mut self, allow: bool) -> Self {
+    self.allow_external = allow;
+    self
+  }
+
+  /// Whether archive paths are relative to the source directory itself, see
+  /// [`strip_source_dir`](Self::strip_source_dir) and [`allow_external`](Self::allow_external).
+  fn strips_dir(&self, dir: &Path, manifest_dir: &Path) -> bool {
+    self.strip_source_dir
+      || manifest_dir
+        .join(dir)
+        .canonicalize()
+        .is_ok_and(|dir| !dir.starts_with(manifest_dir))
+  }
+
+  /// Only embed files whose archive path matches `glob`, like `**/*.html`. Calling this
+  /// multiple times embeds files matching any of the globs.
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("source")
+  ///   .include("**/*.html")
+  ///   .exclude("**/*.map")
+  ///   .build("source")
+  ///   .unwrap();
+  /// ```
+  pub fn include(mut self, glob: &str) -> Self {
+    self.include_globs.push(glob.to_string());
+    self
+  }
+
+  /// Skip files whose archive path matches `glob`, like `**/.DS_Store`. Exclusions take
+  /// precedence over [`include`](Self::include).
+  pub fn exclude(mut self, glob: &str) -> Self {
+    self.exclude_globs.push(glob.to_string());
+    self
+  }
+
+  /// Only embed files whose archive path matches `glob` if `condition` is true, and skip them
+  /// otherwise, like [`exclude`](Self::exclude).
+  ///
+  /// Build scripts are compiled with the features of their crate, so this keeps the assets of
+  /// disabled features out of the binary:
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("assets")
+  ///   .include_if(cfg!(feature = "premium"), "assets/premium/**")
+  ///   .build("assets")?;
+  /// ```
+  pub fn include_if(self, condition: bool, glob: &str) -> Self {
+    match condition {
+      true => self,
+      false => self.exclude(glob),
+    }
+  }
+
+  /// Skip files larger than the given size in bytes.
+  ///
+  /// Unlike other filters, skipped files are always reported with a cargo warning, since
+  /// silently dropping a large asset is rarely intended.
+  pub fn exclude_larger_than(mut self, bytes: u64) -> Self {
+    self.exclude_larger_than = Some(bytes);
+    self
+  }
+
+  /// Fail the build if any embedded file is larger than the given size in bytes, listing every
+  /// file over the limit. Sizes are those of the files after precompilation and before
+  /// compression.
+  ///
+  /// Unlike [`exclude_larger_than`](Self::exclude_larger_than), this catches large files that
+  /// end up in the source directory by accident instead of leaving them out.
+  pub fn max_file_size(mut self, bytes: u64) -> Self {
+    self.max_file_size = Some(bytes);
+    self
+  }
+
+  /// Fail the build if the embedded files are larger than the given size in bytes in total,
+  /// listing the largest files. Sizes are counted like for [`max_file_size`](Self::max_file_size).
+  pub fn max_total_size(mut self, bytes: u64) -> Self {
+    self.max_total_size = Some(bytes);
+    self
+  }
+
+  /// Check the files against [`max_file_size`](Self::max_file_size) and
+  /// [`max_total_size`](Self::max_total_size).
+  fn check_sizes(&self, files: &[FileEntry]) -> Result<(), ArchiveError> {
+    let mut sizes: Vec<_> = files
+      .iter()
+      .map(|file| (file.path.clone(), file.data.len() as u64))
+      .collect();
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    if let Some(max) = self.max_file_size
+      && sizes.first().is_some_and(|(_, size)| *size > max)
+    {
+      sizes.retain(|(_, size)| *size > max);
+      return Err(ArchiveError::FilesTooLarge { max, files: sizes });
+    }
+
+    let total = sizes.iter().map(|(_, size)| size).sum();
+    if let Some(max) = self.max_total_size
+      && total > max
+    {
+      sizes.truncate(LARGEST_FILES_LISTED);
+      return Err(ArchiveError::BundleTooLarge {
+        total,
+        max,
+        largest: sizes,
+      });
+    }
+
+    Ok(())
+  }
+
+  /// Decide whether to embed a file based on its path, metadata and contents.
+  ///
+  /// This covers cases other filters can't express. The closure receives the archive path, the
+  /// file metadata and the file contents. Multiple filters can be added, and a file is only
+  /// embedded if every filter includes it.
+  ///
+  /// ```rust,ignore
+  /// use include_fs::Decision;
+  ///
+  /// include_fs::Bundle::new("assets")
+  ///   .filter(|_path, meta, contents| {
+  ///     if meta.len() == 0 || contents.windows(11).any(|w| w == b"DO NOT SHIP") {
+  ///       Decision::Skip
+  ///     } else {
+  ///       Decision::Include
+  ///     }
+  ///   })
+  ///   .build("assets")
+  ///   .unwrap();
+  /// ```
+  pub fn filter<F>(mut self, filter: F) -> Self
+  where
+    F: Fn(&str, &Metadata, &[u8]) -> Decision + 'static,
+  {
+    self.filters.push(Box::new(filter));
+    self
+  }
+
+  /// Fail the build if a file matching `glob` is rejected by `validate`.
+  ///
+  /// The glob is matched against archive paths, and `validate` receives the contents that would
+  /// be embedded. This catches malformed config files or translations at compile time instead
+  /// of at runtime:
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("assets")
+  ///   .validate("**/*.json", |bytes| serde_json::from_slice::<serde_json::Value>(bytes))
+  ///   .build("assets")
+  ///   .unwrap();
+  /// ```
+  ///
+  /// Multiple validators can be added, and files are checked against all matching ones.
+  pub fn validate<F, T, E>(mut self, glob: &str, validate: F) -> Self
+  where
+    F: Fn(&[u8]) -> Result<T, E> + 'static,
+    E: fmt::Display,
+  {
+    let validate = move |data: &[u8]| validate(data).map(|_| ()).map_err(|err| err.to_string());
+    self.validators.push((glob.to_string(), Box::new(validate)));
+    self
+  }
+
+  /// Replace the contents of files matching `glob` with the output of `precompile`, failing the
+  /// build if it returns an error.
+  ///
+  /// The closure receives the archive path and the file contents. This is meant for templates,
+  /// which can be syntax-checked and stored in a compiled or minified form, so errors surface at
+  /// build time and the first render is faster:
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("templates")
+  ///   .precompile("**/*.html", |path, source| {
+  ///     let source = std::str::from_utf8(source)?;
+  ///     minijinja::Environment::new().template_from_named_str(path, source)?;
+  ///     Ok::<_, Box<dyn std::error::Error>>(minify(source))
+  ///   })
+  ///   .build("templates")
+  ///   .unwrap();
+  /// ```
+  ///
+  /// Precompilers run after filters and before validators, in the order they were added.
+  pub fn precompile<F, E>(mut self, glob: &str, precompile: F) -> Self
+  where
+    F: Fn(&str, &[u8]) -> Result<Vec<u8>, E> + 'static,
+    E: fmt::Display,
+  {
+    let precompile =
+      move |path: &str, data: &[u8]| precompile(path, data).map_err(|err| err.to_string());
+    self
+      .precompilers
+      .push((glob.to_string(), Box::new(precompile)));
+    self
+  }
+
+  /// Order entries by depth first, so files closer to the source directory root come first.
+  /// Entries at the same depth are sorted by path. Disabled by default.
+  pub fn breadth_first(mut self, breadth_first: bool) -> Self {
+    self.breadth_first = breadth_first;
+    self
+  }
+
+  /// Order entries in the archive with a custom comparator on their archive paths.
+  ///
+  /// By default, entries are sorted by path. The comparator is applied with a stable sort on top
+  /// of the default (or breadth-first) order, so entries it considers equal keep a deterministic
+  /// order. This can be used to place frequently accessed files at the start of the archive:
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("public")
+  ///   .sort_by(|a, b| b.ends_with("index.html").cmp(&a.ends_with("index.html")))
+  ///   .build("public")
+  ///   .unwrap();
+  /// ```
+  pub fn sort_by<F>(mut self, compare: F) -> Self
+  where
+    F: Fn(&str, &str) -> Ordering + 'static,
+  {
+    self.sort_by = Some(Box::new(compare));
+    self
+  }
+
+  /// Only descend `depth` levels into the source directory.
+  ///
+  /// A depth of 1 embeds only the files directly inside the source directory, 2 also embeds
+  /// files in its immediate subdirectories, and so on. Unlimited by default.
+  pub fn max_depth(mut self, depth: usize) -> Self {
+    self.max_depth = Some(depth);
+    self
+  }
+
+  /// Don't descend into directories on a different filesystem than the source directory.
+  ///
+  /// This keeps mount points inside the source tree, like a bind-mounted cache directory on CI,
+  /// out of the bundle. Disabled by default.
+  pub fn same_file_system(mut self, same_file_system: bool) -> Self {
+    self.same_file_system = same_file_system;
+    self
+  }
+
+  /// Skip hidden files and directories, whose names start with a `.`. Disabled by default.
+  ///
+  /// This keeps files like `.DS_Store` or editor swap files out of the bundle.
+  pub fn skip_hidden(mut self, skip: bool) -> Self {
+    self.skip_hidden = skip;
+    self
+  }
+
+  /// Skip files and directories that are ignored by git. Disabled by default.
+  ///
+  /// The `.gitignore` files inside the source directory and in its parent directories up to the
+  /// root of the repository apply, along with `.git/info/exclude`. The global gitignore of the
+  /// user is not read, so bundles don't depend on the machine they are built on. Patterns only
+  /// apply to the paths inside the source directory, so a gitignored build output directory can
+  /// still be bundled.
+  pub fn respect_gitignore(mut self, respect: bool) -> Self {
+    self.respect_gitignore = respect;
+    self
+  }
+
+  /// Follow symlinks to files and directories, embedding them under the path of the symlink.
+  /// Disabled by default, which skips symlinks.
+  ///
+  /// This allows asset trees that share directories through symlinks. Symlinks that point to
+  /// one of their own parent directories are skipped with a cargo warning, and the walk
+  /// descends at most 64 levels unless [`max_depth`](Self::max_depth) is set.
+  pub fn follow_symlinks(mut self, follow: bool) -> Self {
+    self.follow_symlinks = follow;
+    self
+  }
+
+  /// Record directories without any files in the archive. Disabled by default, so only
+  /// directories that contain files exist.
+  ///
+  /// Empty directories are listed by [`IncludeFsInner::read_dir`] and recreated by
+  /// [`IncludeFsInner::unpack`], for trees with placeholder directories. They are subject to the
+  /// exclude globs, but not the include globs or extensions.
+  ///
+  /// [`IncludeFsInner::read_dir`]: crate::IncludeFsInner::read_dir
+  /// [`IncludeFsInner::unpack`]: crate::IncludeFsInner::unpack
+  pub fn empty_dirs(mut self, empty_dirs: bool) -> Self {
+    self.empty_dirs = empty_dirs;
+    self
+  }
+
+  /// Set how symlinks pointing to missing files are handled. Defaults to
+  /// [`BrokenSymlinks::Warn`].
+  pub fn broken_symlinks(mut self, policy: BrokenSymlinks) -> Self {
+    self.broken_symlinks = policy;
+    self
+  }
+
+  /// Store paths lowercased and make lookups case-insensitive. Disabled by default.
+  ///
+  /// Building fails if two paths only differ in case, naming both files.
+  pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+    self.case_insensitive = case_insensitive;
+    self
+  }
+
+  /// Normalize paths to Unicode NFC. Disabled by default.
+  ///
+  /// macOS filesystems may report paths in NFD, while string literals in source code are usually
+  /// NFC, so lookups for non-ASCII paths can fail depending on where the bundle was built.
+  /// Building fails if two paths normalize to the same path, naming both files.
+  #[cfg(feature = "unicode-normalization")]
+  pub fn normalize_unicode(mut self, normalize: bool) -> Self {
+    self.normalize_unicode = normalize;
+    self
+  }
+
+  /// Normalize an archive path according to the configured options.
+  fn normalize_path(&self, path: String) -> String {
+    #[cfg(feature = "unicode-normalization")]
+    let path = if self.normalize_unicode {
+      use unicode_normalization::UnicodeNormalization;
+      path.nfc().collect()
+    } else {
+      path
+    };
+
+    if self.case_insensitive {
+      path.to_lowercase()
+    } else {
+      path
+    }
+  }
+
+  /// Print a cargo warning for every file that is skipped by a filter. Disabled by default.
+  pub fn report_skipped(mut self, report: bool) -> Self {
+    self.report_skipped = report;
+    self
+  }
+
+  /// Store the build time in the archive, see [`IncludeFsInner::build_info`]. Disabled by
+  /// default, since it makes builds unreproducible.
+  ///
+  /// If `SOURCE_DATE_EPOCH` is set, it is used instead of the current time.
+  ///
+  /// [`IncludeFsInner::build_info`]: crate::IncludeFsInner::build_info
+  pub fn build_timestamp(mut self, enable: bool) -> Self {
+    self.build_timestamp = enable;
+    self
+  }
+
+  /// Store a label in the archive, such as the git commit hash of the assets. It is available at
+  /// runtime through [`IncludeFsInner::build_info`].
+  ///
+  /// [`IncludeFsInner::build_info`]: crate::IncludeFsInner::build_info
+  pub fn build_label(mut self, label: impl Into<String>) -> Self {
+    self.build_label = Some(label.into());
+    self
+  }
+
+  /// Read files from the source directory at runtime in debug builds, so assets can be edited
+  /// without rebuilding. Disabled by default.
+  ///
+  /// When the build script runs with the `debug` profile, the absolute path that archive paths
+  /// are relative to is stored in the archive, and Cargo is no longer told to rerun the build script
+  /// when the source directory changes. At runtime, [`IncludeFsInner::get`] then reads files
+  /// from disk, rereading them whenever their modification time changes, and falls back to the
+  /// embedded data for files that don't exist there. Only files in the bundle are read, so
+  /// excluded files aren't served, and [`IncludeFsInner::list_paths`] and other methods that
+  /// only read the index still describe the bundle at the time of the build.
+  ///
+  /// Release builds embed the archive as usual. Passthrough is ignored for encrypted bundles,
+  /// source archives and the statics generated by `include_fs!(mod ...)`.
+  ///
+  /// [`IncludeFsInner::get`]: crate::IncludeFsInner::get
+  /// [`IncludeFsInner::list_paths`]: crate::IncludeFsInner::list_paths
+  pub fn passthrough(mut self, passthrough: bool) -> Self {
+    self.passthrough = passthrough;
+    self
+  }
+
+  /// Whether debug builds embed the files. Enabled by default.
+  ///
+  /// When disabled, debug builds write an archive with the paths of the files but without their
+  /// data, and read all of them from the source directory at runtime, like
+  /// [`passthrough`](Self::passthrough). Large asset trees then don't bloat debug binaries, and
+  /// editing them doesn't rebuild the crate, while release builds embed them as usual.
+  ///
+  /// Only the files that the bundle includes are read, so excluded files like `.env` are never
+  /// served. Files added to the source directory are picked up the next time the build script
+  /// runs, and metadata like sizes and checksums isn't stored. The binary only works where the
+  /// source directory exists, reads fail with [`FsError::SourceDirMissing`] elsewhere. This is
+  /// ignored for encrypted bundles and bundles with
+  /// [mounted directories](Self::add_dir_at), which are embedded with passthrough instead.
+  ///
+  /// Only `include_fs!` statics of type `IncludeFs` can read such bundles. `StaticFs` tables
+  /// and `include_fs!(mod ..)` borrow the embedded data, so they fail to compile in debug
+  /// builds, and the generated `_TOTAL_SIZE` constant is 0.
+  ///
+  /// ```rust,ignore
+  /// include_fs::Bundle::new("assets")
+  ///   .embed_in_debug(false)
+  ///   .build("assets")?;
+  /// ```
+  ///
+  /// [`FsError::SourceDirMissing`]: crate::FsError::SourceDirMissing
+  pub fn embed_in_debug(mut self, embed: bool) -> Self {
+    self.embed_in_debug = embed;
+    self
+  }
+
+  /// Whether files are read from the source directory at runtime, see
+  /// [`passthrough`](Self::passthrough).
+  fn is_passthrough(&self) -> bool {
+    (self.passthrough || !self.embed_in_debug)
+      && matches!(self.source, Source::Dir(_))
+      && env::var("PROFILE").is_ok_and(|profile| profile == "debug")
+  }
+
+  /// Whether the archive is written without files, see [`embed_in_debug`](Self::embed_in_debug).
+  fn is_stub(&self) -> bool {
+    #[cfg(feature = "encryption")]
+    if self.secret.is_some() {
+      return false;
+    }
+
+    let mounted = self.extra_dirs.iter().any(|(_, prefix)| prefix.is_some());
+    !self.embed_in_debug && !mounted && self.is_passthrough()
+  }
+
+  /// The directory that archive paths are relative to, which passthrough reads from.
+  fn passthrough_dir(&self, manifest_dir: &str) -> String {
+    match &self.source {
+      Source::Dir(dir) if self.strips_dir(dir, Path::new(manifest_dir)) => Path::new(manifest_dir)
+        .join(dir)
+        .to_string_lossy()
+        .into_owned(),
+      _ => manifest_dir.to_string(),
+    }
+  }
+
+  /// Store the modification time and Unix permissions of every file. Disabled by default.
+  ///
+  /// They are available at runtime through [`FsEntry::modified`] and [`FsEntry::mode`], serve
+  /// as `Last-Modified` times, and are restored by [`Installer`]. Permissions are only stored on
+  /// Unix. This makes builds depend on file timestamps, so archives are no longer reproducible
+  /// across checkouts, unless `SOURCE_DATE_EPOCH` is set: modification times after it are
+  /// clamped to it, like other reproducible build tools do.
+  ///
+  /// [`FsEntry::modified`]: crate::FsEntry::modified
+  /// [`FsEntry::mode`]: crate::FsEntry::mode
+  /// [`Installer`]: crate::Installer
+  pub fn file_metadata(mut self, file_metadata: bool) -> Self {
+    self.file_metadata = file_metadata;
+    self
+  }
+
+  /// Store the MIME type of every file, guessed from its extension. Disabled by default.
+  ///
+  /// [`IncludeFsInner::mime`] guesses the type at runtime if it wasn't stored, so this only
+  /// saves the lookup, at the cost of a few bytes per file.
+  ///
+  /// [`IncludeFsInner::mime`]: crate::IncludeFsInner::mime
+  #[cfg(feature = "mime")]
+  pub fn mime_types(mut self, store: bool) -> Self {
+    self.mime_types = store;
+    self
+  }
+
+  /// Produce an empty bundle instead of failing if the source directory doesn't exist. Disabled
+  /// by default.
+  ///
+  /// This is useful for optional asset sets, like docs or samples, that may be missing from
+  /// minimal checkouts. Cargo reruns the build script on every build until the directory exists.
+  pub fn optional(mut self, optional: bool) -> Self {
+    self.optional = optional;
+    self
+  }
+
+  /// Record the license and attribution files in the bundle, so they can be listed at runtime
+  /// with [`IncludeFsInner::attributions`]. Disabled by default.
+  ///
+  /// Files named `LICENSE`, `LICENCE`, `COPYING`, `NOTICE`, `ATTRIBUTION` or `CREDITS` are
+  /// collected, in any case, with any extension and with suffixes like `LICENSE-MIT`. Each
+  /// applies to the assets in its directory and below.
+  ///
+  /// [`IncludeFsInner::attributions`]: crate::IncludeFsInner::attributions
+  pub fn attributions(mut self, collect: bool) -> Self {
+    self.attributions = collect;
+    self
+  }
+
+  /// Store a checksum of every file, computed with `algorithm`. Disabled by default.
+  ///
+  /// Checksums are available at runtime through [`IncludeFsInner::checksum`]. CRC-32 and xxHash
+  /// are cheap to verify but only detect accidental corruption, while BLAKE3 also detects
+  /// deliberate modification.
+  ///
+  /// [`IncludeFsInner::checksum`]: crate::IncludeFsInner::checksum
+  pub fn checksums(mut self, algorithm: ChecksumAlgorithm) -> Self {
+    self.checksum = Some(algorithm);
+    self
+  }
+
+  /// Store a strong `ETag` for every file, so HTTP servers can answer conditional requests
+  /// without hashing files at runtime. Disabled by default.
+  ///
+  /// The tag is a quoted 128-bit xxHash3 of the file contents, available at runtime through
+  /// [`FsEntry::etag`] and checked with [`http::etag_matches`].
+  ///
+  /// [`FsEntry::etag`]: crate::FsEntry::etag
+  /// [`http::etag_matches`]: crate::http::etag_matches
+  pub fn etags(mut self, etags: bool) -> Self {
+    self.etags = etags;
+    self
+  }
+
+  /// Compress every file with `compression`. Disabled by default.
+  ///
+  /// Files are decompressed transparently the first time they are read, and the result is kept
+  /// in memory for the lifetime of the filesystem. This makes the binary smaller at the cost of
+  /// startup time and memory, so it suits large, rarely read text assets better than data that
+  /// is used right away. Compressed files can't be borrowed from the binary with
+  /// [`IncludeFsInner::raw_parts`].
+  ///
+  /// Files in formats that are compressed already, like PNG, JPEG, WOFF2 or ZIP, and files that
+  /// don't get smaller are stored uncompressed, so they are still borrowed without decoding.
+  ///
+  /// [`IncludeFsInner::raw_parts`]: crate::IncludeFsInner::raw_parts
+  pub fn compress(mut self, compression: Compression) -> Self {
+    self.compression = Some(compression);
+    self
+  }
+
+  /// Store precompressed variants of every file in the given encodings, next to the file
+  /// itself. Disabled by default.
+  ///
+  /// Web servers can answer requests whose `Accept-Encoding` allows it with a variant from
+  /// [`IncludeFsInner::get_encoded`], so text assets are sent compressed without compressing
+  /// them per request. The serving integrations do this on their own. Variants are only kept if
+  /// they are smaller than the file, and files in formats that are compressed already get none.
+  ///
+  /// This makes the binary larger by the size of the variants, which are compressed at the
+  /// highest level. Brotli needs the `brotli` feature and gzip the `gzip` feature. Variants are
+  /// not stored for encrypted bundles, since they would contain the files unencrypted.
+  ///
+  /// ```rust,ignore
+  /// Bundle::new("public")
+  ///   .precompress([Encoding::Brotli, Encoding::Gzip])
+  ///   .build("public")?;
+  /// ```
+  ///
+  /// [`IncludeFsInner::get_encoded`]: crate::IncludeFsInner::get_encoded
+  pub fn precompress(mut self, encodings: impl IntoIterator<Item = Encoding>) -> Self {
+    self.precompress = encodings.into_iter().collect();
+    self.precompress.sort();
+    self.precompress.dedup();
+    self
+  }
+
+  /// Store a precomputed hash table of the paths in the archive. Disabled by default.
+  ///
+  /// Without it, the index is built by parsing every entry of the header when the filesystem is
+  /// first accessed, which takes noticeable time for bundles with tens of thousands of files.
+  /// With it, initialization only reads the archive attributes, and entries are parsed the
+  /// first time they are looked up. This adds 12 bytes per file to the archive.
+  pub fn lookup_table(mut self, lookup_table: bool) -> Self {
+    self.lookup_table = lookup_table;
+    self
+  }
+
+  /// Keep the index as a list sorted by path instead of a hash map at runtime, with lookups by
+  /// binary search. Disabled by default.
+  ///
+  /// This needs about half the memory for bundles with many small files, and makes
+  /// [`read_dir`](crate::IncludeFsInner::read_dir) only visit the files inside the directory,
+  /// at the cost of slightly slower lookups. Files are listed in sorted order. Ignored if the
+  /// bundle has a [`lookup_table`](Self::lookup_table), which isn't parsed up front at all.
+  pub fn sorted_index(mut self, sorted_index: bool) -> Self {
+    self.sorted_index = sorted_index;
+    self
+  }
+
+  /// Rename the files matching `glob` to include a hash of their contents, like
+  /// `css/app.1a2b3c4d.css`, and rewrite references to them in HTML and CSS files. Can be called
+  /// multiple times to fingerprint files matching any of the globs.
+  ///
+  /// Since the URL of a fingerprinted file changes whenever its contents change, it can be
+  /// served with a long-lived [`http::IMMUTABLE`] cache header without a separate bundler step.
+  /// URLs in `src`, `href`, `poster` and `srcset` attributes, CSS `url()` and `@import` are
+  /// rewritten. Relative URLs are resolved against the referencing file, root-relative URLs
+  /// against the bundled directory. Other references, such as URLs built in scripts, can use
+  /// [`IncludeFsInner::fingerprinted_path`].
+  ///
+  /// ```rust,ignore
+  /// Bundle::new("dist")
+  ///   .fingerprint("**/*.{css,js,png,svg,woff2}")
+  ///   .build("dist")?;
+  /// ```
+  ///
+  /// [`http::IMMUTABLE`]: crate::http::IMMUTABLE
+  /// [`IncludeFsInner::fingerprinted_path`]: crate::IncludeFsInner::fingerprinted_path
+  pub fn fingerprint(mut self, glob: &str) -> Self {
+    self.fingerprint.push(glob.to_string());
+    self
+  }
+
+  /// Align the data of every file to `alignment` bytes, padding the archive as needed. Disabled
+  /// by default.
+  ///
+  /// `include_fs!` aligns the embedded archive as well, so files like rkyv archives, flatbuffers
+  /// or GPU buffers can be reinterpreted in place instead of being copied to fix their
+  /// alignment. The alignment has to be a power of two up to 4096. Compressed and encrypted
+  /// files are decoded into unaligned buffers, and bundles built with
+  /// [`build_shared`](Self::build_shared) are not aligned.
+  pub fn align_data(mut self, alignment: usize) -> Self {
+    self.alignment = Some(alignment);
+    self
+  }
+
+  /// Link the archive from a static library instead of including it as a byte literal.
+  /// Disabled by default.
+  ///
+  /// Large byte literals are slow to compile, and `include_fs!` passes the whole archive to
+  /// rustc whenever the crate is rebuilt. With this option, the build script assembles the
+  /// archive into a static library once, and `include_fs!` refers to it through an extern
+  /// static, so changes to application code don't touch the archive.
+  ///
+  /// This requires a C toolchain that can assemble `.incbin` directives, which excludes MSVC.
+  /// Only [`build`](Self::build) supports static libraries.
+  #[cfg(feature = "static-lib")]
+  pub fn static_lib(mut self, static_lib: bool) -> Self {
+    self.static_lib = static_lib;
+    self
+  }
+
+  /// Encrypt the file data with a key derived from `passphrase`.
+  ///
+  /// The key is derived with Argon2id, and its parameters are stored in the archive, so the
+  /// bundle can be opened with [`IncludeFsInner::with_passphrase`] without the key being part of
+  /// the binary. Encrypted bundles can not be included with `include_fs!`:
+  ///
+  /// ```rust,ignore
+  /// // In build.rs
+  /// let passphrase = std::env::var("CONTENT_PASSPHRASE").unwrap();
+  /// Bundle::new("content").encrypt_with_passphrase(passphrase).build("content")?;
+  ///
+  /// // In main.rs
+  /// static CONTENT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/content.embed_fs"));
+  /// let content = IncludeFsInner::with_passphrase(CONTENT, &prompt_passphrase())?;
+  /// ```
+  ///
+  /// [`IncludeFsInner::with_passphrase`]: crate::IncludeFsInner::with_passphrase
+  #[cfg(feature = "encryption")]
+  pub fn encrypt_with_passphrase(mut self, passphrase: impl Into<String>) -> Self {
+    self.secret = Some(Secret::Passphrase(passphrase.into()));
+    self
+  }
+
+  /// Encrypt the file data with `key`, which replaces any passphrase.
+  ///
+  /// Unlike [`encrypt_with_passphrase`](Self::encrypt_with_passphrase), nothing is derived, so
+  /// opening the bundle with [`IncludeFsInner::with_key`] is instant. This suits keys that are
+  /// random already, like keys handed out by a license server or read from a secret store, and
+  /// keeps licensed content and seed credentials out of reach of `strings`:
+  ///
+  /// ```rust,ignore
+  /// // In build.rs
+  /// let key: [u8; 32] = std::fs::read(std::env::var("CONTENT_KEY_FILE")?)?.try_into().unwrap();
+  /// Bundle::new("content").encrypt_with_key(key).build("content")?;
+  ///
+  /// // In main.rs
+  /// static CONTENT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/content.embed_fs"));
+  /// let content = IncludeFsInner::with_key(CONTENT, &fetch_license_key()?)?;
+  /// ```
+  ///
+  /// [`IncludeFsInner::with_key`]: crate::IncludeFsInner::with_key
+  #[cfg(feature = "encryption")]
+  pub fn encrypt_with_key(mut self, key: [u8; 32]) -> Self {
+    self.secret = Some(Secret::Key(key));
+    self
+  }
+
+  /// Sign the archive with the Ed25519 `secret_key`, so it can be checked with
+  /// [`IncludeFsInner::verify_signature`] and the matching public key.
+  ///
+  /// Embedded archives are as trustworthy as the binary, but archives shipped next to it can be
+  /// replaced on their own. The signature covers every byte of the archive, so changed files,
+  /// paths or attributes are all rejected. Keep the secret key out of the repository, for
+  /// example by reading it from a file named in an environment variable on CI:
+  ///
+  /// ```rust,ignore
+  /// let key: [u8; 32] = std::fs::read(std::env::var("ASSETS_SIGNING_KEY")?)?.try_into().unwrap();
+  /// Bundle::new("assets").sign(key).build("assets")?;
+  /// ```
+  ///
+  /// Bundles built with [`build_shared`](Self::build_shared) are not signed.
+  ///
+  /// [`IncludeFsInner::verify_signature`]: crate::IncludeFsInner::verify_signature
+  #[cfg(feature = "signing")]
+  pub fn sign(mut self, secret_key: [u8; 32]) -> Self {
+    self.signing_key = Some(secret_key);
+    self
+  }
+
+  /// Reuse the archive of the previous build if nothing that goes into it changed. Enabled by
+  /// default.
+  ///
+  /// Cargo reruns a build script when any of its inputs change, which rebuilds every bundle
+  /// of the script. With this, [`build`](Self::build) first compares the size and modification
+  /// time of every file below the source directories, the configuration of the bundle and the
+  /// build script itself with the previous run, stored next to the archive in `OUT_DIR`, and
+  /// returns right away if they are the same. Otherwise the archive is built, but only written
+  /// if its contents changed, so the crate that includes it isn't recompiled for nothing.
+  ///
+  /// Bundles with [build timestamps](Self::build_timestamp), encryption or
+  /// [static libraries](Self::static_lib) and bundles built with
+  /// [`build_shared`](Self::build_shared) are always rebuilt.
+  pub fn incremental(mut self, incremental: bool) -> Self {
+    self.incremental = incremental;
+    self
+  }
+
+  /// Print a `cargo:` directive, and remember it for [`incremental`](Self::incremental) builds.
+  fn emit(&self, directive: String) {
+    println!("{directive}");
+    self.directives.borrow_mut().push(directive);
+  }
+
+  fn broken_symlink(&self, path: &Path) -> Result<(), ArchiveError> {
+    match self.broken_symlinks {
+      BrokenSymlinks::Error => Err(ArchiveError::BrokenSymlink {
+        path: path.to_path_buf(),
+      }),
+      BrokenSymlinks::Skip => Ok(()),
+      BrokenSymlinks::Warn => {
+        self.emit(format!(
+          "cargo:warning=Skipping broken symlink {}",
+          path.display()
+        ));
+        Ok(())
+      }
+    }
+  }
+
+  fn skip(&self, path: &str, reason: &str) {
+    if self.report_skipped {
+      self.emit(format!("cargo:warning=Skipping {path}: {reason}"));
+    }
+  }
+
+  /// Write the bundle to `OUT_DIR`, to be included with `include_fs!(bundle_name)`.
+  ///
+  /// If the `INCLUDE_FS_STUB` environment variable is set, a bundle with the paths of the files
+  /// but without their data is written instead, so the items generated by `include_fs!(mod ..)`
+  /// still exist. This skips compressing and embedding the source files, which can speed up
+  /// `cargo check`, clippy and rustdoc runs considerably for large bundles:
+  ///
+  /// ```sh
+  /// INCLUDE_FS_STUB=1 cargo clippy
+  /// ```
+  pub fn build(self, bundle_name: &str) -> Result<(), ArchiveError> {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("no CARGO_MANIFEST_DIR");
+    let cache_path = BuildCache::path(&output_path(bundle_name));
+    let inputs = self.input_hash(Path::new(&manifest_dir))?;
+    let cache = inputs.and_then(|_| BuildCache::read(&cache_path));
+    if let Some(cache) = &cache
+      && Some(cache.inputs) == inputs
+      && output_path(bundle_name).exists()
+    {
+      for directive in &cache.directives {
+        println!("{directive}");
+      }
+
+      return Ok(());
+    }
+
+    let (files, options, output_path) = self.prepare(bundle_name)?;
+
+    #[cfg(feature = "static-lib")]
+    let options = ArchiveOptions {
+      static_lib: self
+        .static_lib
+        .then(|| crate::static_lib::symbol(bundle_name)),
+      ..options
+    };
+
+    match inputs {
+      Some(inputs) => {
+        let mut archive = Vec::new();
+        self.write(&files, &options, &mut archive)?;
+
+        // Keep the modification time of an identical archive, so it isn't recompiled
+        let hash = xxhash_rust::xxh3::xxh3_128(&archive);
+        if cache.is_none_or(|cache| cache.archive != hash) || !output_path.exists() {
+          fs::write(&output_path, &archive)?;
+        }
+
+        let cache = BuildCache {
+          inputs,
+          archive: hash,
+          directives: self.directives.take(),
+        };
+        cache.write(&cache_path)?;
+      }
+      None => {
+        let _ = fs::remove_file(&cache_path);
+        let mut out = BufWriter::new(File::create(&output_path)?);
+        self.write(&files, &options, &mut out)?;
+        out.flush()?;
+      }
+    }
+
+    #[cfg(feature = "static-lib")]
+    if let Some(symbol) = &options.static_lib {
+      let alignment = options.alignment.unwrap_or(1);
+      crate::static_lib::compile(&output_path, symbol, alignment)?;
+    }
+
+    Ok(())
+  }
+
+  /// Write the bundle to `OUT_DIR` like [`build`](Self::build), but store the file data in
+  /// `shared` so identical files in multiple bundles are only embedded once.
+  ///
+  /// See [`SharedData`] for how to include these bundles.
+  pub fn build_shared(
+    self,
+    shared: &mut SharedData,
+    bundle_name: &str,
+  ) -> Result<(), ArchiveError> {
+    let (files, mut options, output_path) = self.prepare(bundle_name)?;
+    options.shared_data = Some(shared.name().to_string());
+    options.alignment = None;
+    options.signature = false;
+
+    let mut out = BufWriter::new(File::create(output_path)?);
+    write_shared_archive(&files, &options, shared, &mut out)
+  }
+
+  /// A hash of everything that goes into the archive, from the metadata of the input files, or
+  /// `None` if the archive can't be reused, see [`incremental`](Self::incremental).
+  fn input_hash(&self, manifest_dir: &Path) -> Result<Option<u128>, ArchiveError> {
+    #[cfg(feature = "encryption")]
+    if self.secret.is_some() {
+      return Ok(None);
+    }
+
+    #[cfg(feature = "static-lib")]
+    if self.static_lib {
+      return Ok(None);
+    }
+
+    if !self.incremental || self.build_timestamp {
+      return Ok(None);
+    }
+
+    let mut config = format!("{self:?}");
+    for var in [
+      "CARGO_PKG_VERSION",
+      "PROFILE",
+      "SOURCE_DATE_EPOCH",
+      STUB_ENV,
+    ] {
+      config.push_str(&format!("\n{var}={:?}", env::var_os(var)));
+    }
+
+    #[cfg(feature = "mime")]
+    config.push_str(&format!("\nmime_types={}", self.mime_types));
+    #[cfg(feature = "unicode-normalization")]
+    config.push_str(&format!("\nnormalize_unicode={}", self.normalize_unicode));
+    #[cfg(feature = "signing")]
+    if let Some(key) = &self.signing_key {
+      config.push_str(&format!("\nsigned={:?}", crate::signing::public_key(key)));
+    }
+
+    // The build script covers the filters and other hooks, which are part of it
+    let mut hasher = InputHasher::new(&config);
+    hasher.add_tree(&env::current_exe()?, false)?;
+
+    let source = match &self.source {
+      Source::Dir(dir) | Source::Archive(dir) => dir,
+    };
+
+    let extra_dirs = self.extra_dirs.iter().map(|(dir, _)| dir);
+    for dir in [source].into_iter().chain(extra_dirs) {
+      let dir = manifest_dir.join(dir);
+      hasher.add_tree(&dir, self.follow_symlinks)?;
+      if self.respect_gitignore {
+        for file in GitignoreFiles::outer_files(&dir) {
+          hasher.add_tree(&file, false)?;
+        }
+      }
+    }
+
+    Ok(Some(hasher.finish()))
+  }
+
+  /// Write the archive, signing it if a key is set.
+  fn write(
+    &self,
+    files: &[FileEntry],
+    options: &ArchiveOptions,
+    out: &mut impl Write,
+  ) -> Result<(), ArchiveError> {
+    #[cfg(feature = "signing")]
+    if let Some(key) = &self.signing_key {
+      let mut archive = Vec::new();
+      write_archive(files, options, &mut archive)?;
+      crate::signing::sign(&mut archive, key);
+      out.write_all(&archive)?;
+      return Ok(());
+    }
+
+    write_archive(files, options, out)
+  }
+
+  /// Collect the files for a build script, returning them along with the archive options and
+  /// the output path.
+  fn prepare(
+    &self,
+    bundle_name: &str,
+  ) -> Result<(Vec<FileEntry>, ArchiveOptions, PathBuf), ArchiveError> {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("no CARGO_MANIFEST_DIR");
+    let out_dir = env::var("OUT_DIR").expect("no OUT_DIR");
+    let output_path = output_path(bundle_name);
+
+    let alignment = match self.alignment {
+      Some(alignment) if !alignment.is_power_of_two() || alignment > MAX_ALIGNMENT => {
+        return Err(ArchiveError::InvalidAlignment {
+          alignment,
+          max: MAX_ALIGNMENT,
+        });
+      }
+      alignment => alignment.map(|alignment| alignment as u32),
+    };
+
+    let mut options = ArchiveOptions {
+      case_insensitive: self.case_insensitive,
+      crate_version: env::var("CARGO_PKG_VERSION").ok(),
+      build_timestamp: self.build_timestamp.then(build_timestamp),
+      build_label: self.build_label.clone(),
+      lookup_table: self.lookup_table,
+      sorted_index: self.sorted_index,
+      alignment,
+      #[cfg(feature = "signing")]
+      signature: self.signing_key.is_some(),
+      source_dir: self
+        .is_passthrough()
+        .then(|| self.passthrough_dir(&manifest_dir)),
+      stub: self.is_stub(),
+      ..Default::default()
+    };
+
+    self.emit(format!("cargo:rerun-if-env-changed={STUB_ENV}"));
+    let mut files = self.collect(
+      Path::new(&manifest_dir),
+      Path::new(&out_dir),
+      &mut options.empty_dirs,
+    )?;
+
+    // Stubs only keep the paths, so `include_fs!(mod ..)` still generates every item, and debug
+    // stubs only read the bundled files from the source directory
+    if options.stub || env::var_os(STUB_ENV).is_some() {
+      for file in &mut files {
+        file.data = Vec::new();
+      }
+
+      return Ok((files, options, output_path));
+    }
+
+    if !self.fingerprint.is_empty() {
+      self.fingerprint_files(&mut files, Path::new(&manifest_dir))?;
+    }
+
+    if self.attributions {
+      options.attributions = attribution_paths(&files);
+    }
+
+    #[cfg(feature = "mime")]
+    if self.mime_types {
+      for file in &mut files {
+        file.mime = crate::mime::guess(&file.path).map(String::from);
+      }
+    }
+
+    if let Some(algorithm) = self.checksum {
+      for file in &mut files {
+        let checksum = algorithm.compute(&file.data);
+        file.checksum = Some(checksum.ok_or(ArchiveError::UnsupportedChecksum(algorithm))?);
+      }
+
+      options.checksum = Some(algorithm);
+    }
+
+    if self.etags {
+      for file in &mut files {
+        file.etag = Some(crate::http::etag(&file.data));
+      }
+    }
+
+    for file in &mut files {
+      precompress_file(file, &self.precompress)?;
+    }
+
+    if let Some(compression) = self.compression {
+      for file in &mut files {
+        compress_file(file, compression)?;
+      }
+    }
+
+    #[cfg(feature = "encryption")]
+    let (files, options) = self.encrypt(files, options);
+
+    Ok((files, options, output_path))
+  }
+
+  /// Fingerprint the files matching the globs set with [`fingerprint`](Self::fingerprint).
+  fn fingerprint_files(
+    &self,
+    files: &mut [FileEntry],
+    manifest_dir: &Path,
+  ) -> Result<(), ArchiveError> {
+    let globs = glob_set(&self.fingerprint)?;
+    let root = match &self.source {
+      Source::Dir(dir) if !self.strips_dir(dir, manifest_dir) => archive_path(dir)?,
+      _ => String::new(),
+    };
+
+    fingerprint(files, &globs, &root);
+    Ok(())
+  }
+
+  /// Encrypt the data of every file, if a passphrase or key is set.
+  #[cfg(feature = "encryption")]
+  fn encrypt(
+    &self,
+    mut files: Vec<FileEntry>,
+    mut options: ArchiveOptions,
+  ) -> (Vec<FileEntry>, ArchiveOptions) {
+    use crate::crypto;
+
+    let key = match &self.secret {
+      Some(Secret::Passphrase(passphrase)) => {
+        let kdf = crypto::random_kdf_params();
+        let key = crypto::derive_key(passphrase, &kdf).expect("invalid key derivation parameters");
+        options.kdf = Some(kdf);
+        key
+      }
+      Some(Secret::Key(key)) => *key,
+      None => return (files, options),
+    };
+
+    for file in &mut files {
+      crypto::encrypt_file(&key, file);
+    }
+
+    options.key_check = Some(crypto::key_check(&key));
+    (files, options)
+  }
+
+  /// Collect the files of the bundle, sorted by path, and the empty directories if enabled.
+  ///
+  /// Source archives are extracted into `out_dir` first.
+  fn collect(
+    &self,
+    manifest_dir: &Path,
+    out_dir: &Path,
+    empty_dirs: &mut Vec<String>,
+  ) -> Result<Vec<FileEntry>, ArchiveError> {
+    let mut files = match &self.source {
+      Source::Dir(dir) => self.collect_dir(dir, None, manifest_dir, empty_dirs)?,
+      Source::Archive(archive) => {
+        let archive = manifest_dir.join(archive);
+        self.emit(format!("cargo:rerun-if-changed={}", archive.display()));
+
+        let file_name = archive.file_name().unwrap_or_default();
+        let extracted = out_dir.join("include-fs-extracted").join(file_name);
+        extract_archive(&archive, &extracted)?;
+
+        let extracted = extracted.canonicalize()?;
+        self.collect_tree(&extracted, &extracted, "", false, empty_dirs)?
+      }
+    };
+
+    for (dir, prefix) in &self.extra_dirs {
+      files.extend(self.collect_dir(dir, prefix.as_deref(), manifest_dir, empty_dirs)?);
+    }
+
+    empty_dirs.sort();
+    empty_dirs.dedup();
+
+    // Sort by path so the archive doesn't depend on the directory iteration order
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    if let Some(pair) = files.windows(2).find(|pair| pair[0].path == pair[1].path) {
+      return Err(ArchiveError::DuplicatePath(pair[0].path.clone()));
+    }
+
+    self.check_sizes(&files)?;
+    if self.breadth_first {
+      files.sort_by_key(|file| file.path.matches('/').count());
+    }
+
+    if let Some(compare) = &self.sort_by {
+      files.sort_by(|a, b| compare(&a.path, &b.path));
+    }
+
+    Ok(files)
+  }
+
+  /// Collect the files of a source directory, mounted under `prefix` if it is set.
+  fn collect_dir(
+    &self,
+    dir: &Path,
+    prefix: Option<&str>,
+    manifest_dir: &Path,
+    empty_dirs: &mut Vec<String>,
+  ) -> Result<Vec<FileEntry>, ArchiveError> {
+    let source_dir = manifest_dir.join(dir);
+    if self.optional && !source_dir.exists() {
+      self.emit(format!("cargo:rerun-if-changed={}", dir.display()));
+      return Ok(Vec::new());
+    }
+
+    let source_dir = source_dir.canonicalize()?;
+
+    // Ensure the source directory is a subdirectory of the manifest directory, unless allowed
+    let external = !source_dir.starts_with(manifest_dir);
+    if external && !self.allow_external {
+      return Err(ArchiveError::InvalidSourceDirectory);
+    }
+
+    // Files are read from disk at runtime, so changes don't need a rebuild. Mounted directories
+    // are not covered by passthrough.
+    let watch = !self.is_passthrough() || prefix.is_some();
+    if watch {
+      // Cargo resolves relative paths against the manifest directory
+      let watched_dir = source_dir.strip_prefix(manifest_dir).unwrap_or(&source_dir);
+      self.emit(format!("cargo:rerun-if-changed={}", watched_dir.display()));
+    }
+
+    match prefix {
+      Some(prefix) => {
+        let prefix = prefix.replace('\\', "/");
+        let prefix = prefix.trim_matches('/');
+        if !prefix.is_empty() && EmbeddedPath::new(prefix).is_err() {
+          return Err(ArchiveError::InvalidPath(prefix.to_string()));
+        }
+
+        self.collect_tree(&source_dir, &source_dir, prefix, watch, empty_dirs)
+      }
+      None if self.strip_source_dir || external => {
+        self.collect_tree(&source_dir, &source_dir, "", watch, empty_dirs)
+      }
+      None => self.collect_tree(&source_dir, manifest_dir, "", watch, empty_dirs),
+    }
+  }
+
+  /// Collect the files in `source_dir`, with paths relative to `base_dir` under `prefix`.
+  ///
+  /// Directories without any entries are added to `empty_dirs` if enabled. With `watch`, Cargo is
+  /// told to rerun the build script when any of the walked files change, and when files are added
+  /// to or removed from any of the walked directories.
+  fn collect_tree(
+    &self,
+    source_dir: &Path,
+    base_dir: &Path,
+    prefix: &str,
+    watch: bool,
+    empty_dirs: &mut Vec<String>,
+  ) -> Result<Vec<FileEntry>, ArchiveError> {
+    let ignore = load_embedignore(source_dir)?;
+    let precompilers = compile_globs(&self.precompilers)?;
+    let validators = compile_globs(&self.validators)?;
+    let include = glob_set(&self.include_globs)?;
+    let exclude = glob_set(&self.exclude_globs)?;
+
+    let mut walk = WalkDir::new(source_dir)
+      .follow_links(self.follow_symlinks)
+      .same_file_system(self.same_file_system);
+    match self.max_depth {
+      Some(depth) => walk = walk.max_depth(depth),
+      None if self.follow_symlinks => walk = walk.max_depth(FOLLOW_SYMLINKS_MAX_DEPTH),
+      None => {}
+    }
+
+    let mut files = Vec::new();
+    let mut original_paths = HashMap::new();
+    let mut gitignore = self
+      .respect_gitignore
+      .then(|| GitignoreFiles::new(source_dir));
+    let walk = walk.into_iter().filter_entry(|entry| {
+      let is_dir = entry.file_type().is_dir();
+      let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+      entry.depth() == 0
+        || !(ignore.matched(entry.path(), is_dir).is_ignore()
+          || self.skip_hidden && is_hidden
+          || gitignore
+            .as_mut()
+            .is_some_and(|gitignore| gitignore.is_ignored(entry.path(), is_dir)))
+    });
+
+    for entry in walk {
+      let entry = match entry {
+        Ok(entry) => entry,
+        Err(err) if err.loop_ancestor().is_some() => {
+          let path = err.path().unwrap_or(source_dir);
+          self.emit(format!(
+            "cargo:warning=Skipping symlink loop {}",
+            path.display()
+          ));
+          continue;
+        }
+        // Followed symlinks to missing files fail to resolve
+        Err(err)
+          if err
+            .path()
+            .is_some_and(|path| path.is_symlink() && !path.exists()) =>
+        {
+          self.broken_symlink(err.path().unwrap())?;
+          continue;
+        }
+        Err(err) => return Err(err.into()),
+      };
+
+      if entry.path_is_symlink() && fs::metadata(entry.path()).is_err() {
+        self.broken_symlink(entry.path())?;
+        continue;
+      }
+
+      let meta = entry.metadata()?;
+      if watch && entry.depth() > 0 && (meta.is_dir() || meta.is_file()) {
+        self.emit(format!("cargo:rerun-if-changed={}", entry.path().display()));
+      }
+
+      if meta.is_dir() && self.empty_dirs && entry.depth() > 0 {
+        let mut path = archive_path(entry.path().strip_prefix(base_dir).unwrap())?;
+        if !prefix.is_empty() {
+          path = format!("{prefix}/{path}");
+        }
+
+        if fs::read_dir(entry.path())?.next().is_none() && !exclude.is_match(&path) {
+          empty_dirs.push(self.normalize_path(path));
+        }
+      }
+
+      if !meta.is_file() {
+        continue;
+      }
+
+      if entry.depth() == 1 && entry.file_name() == EMBEDIGNORE {
+        continue;
+      }
+
+      let mut path = archive_path(entry.path().strip_prefix(base_dir).unwrap())?;
+      if !prefix.is_empty() {
+        path = format!("{prefix}/{path}");
+      }
+
+      if let Some(extensions) = &self.included_extensions
+        && !has_extension(entry.path(), extensions)
+      {
+        self.skip(&path, "extension is not included");
+        continue;
+      }
+
+      if !self.include_globs.is_empty() && !include.is_match(&path) {
+        self.skip(&path, "not matched by an include glob");
+        continue;
+      }
+
+      if exclude.is_match(&path) {
+        self.skip(&path, "matched by an exclude glob");
+        continue;
+      }
+
+      if let Some(max) = self.exclude_larger_than
+        && meta.len() > max
+      {
+        self.emit(format!(
+          "cargo:warning=Skipping {path}: file is too large ({} bytes, max {max} bytes)",
+          meta.len(),
+        ));
+        continue;
+      }
+
+      let mut data = fs::read(entry.path())?;
+      if self.normalize_line_endings {
+        data = normalize_line_endings(data);
+      }
+
+      if self
+        .filters
+        .iter()
+        .any(|filter| filter(&path, &meta, &data) == Decision::Skip)
+      {
+        self.skip(&path, "excluded by filter");
+        continue;
+      }
+
+      for (matcher, precompile) in &precompilers {
+        if matcher.is_match(&path) {
+          data = precompile(&path, &data).map_err(|message| ArchiveError::PrecompileFailed {
+            path: path.clone(),
+            message,
+          })?;
+        }
+      }
+
+      for (matcher, validate) in &validators {
+        if matcher.is_match(&path)
+          && let Err(message) = validate(&data)
+        {
+          return Err(ArchiveError::ValidationFailed { path, message });
+        }
+      }
+
+      let nul_terminated = has_extension(entry.path(), &self.nul_terminated_extensions);
+      if nul_terminated && data.contains(&0) {
+        return Err(ArchiveError::InteriorNul { path });
+      }
+
+      let original_path = path.clone();
+      let path = self.normalize_path(path);
+      if let Some(existing) = original_paths.insert(path.clone(), original_path.clone()) {
+        let (first, second) = if existing < original_path {
+          (existing, original_path)
+        } else {
+          (original_path, existing)
+        };
+
+        return Err(ArchiveError::PathCollision {
+          path,
+          first,
+          second,
+        });
+      }
+
+      let mut file = FileEntry::new(path, data);
+      file.nul_terminated = nul_terminated;
+      if self.file_metadata {
+        let modified = meta
+          .modified()
+          .ok()
+          .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+          .map(|modified| modified.as_secs());
+        file.modified = match source_date_epoch() {
+          Some(epoch) => modified.map(|modified| modified.min(epoch)),
+          None => modified,
+        };
+        file.mode = file_mode(&meta);
+      }
+
+      files.push(file);
+    }
+
+    Ok(files)
+  }
+}
+
+/// The `.gitignore` files that apply to a source directory, see [`Bundle::respect_gitignore`].
+struct GitignoreFiles {
+  /// The root of the repository, or the source directory if it isn't in one.
+  root: PathBuf,
+  /// The patterns of every directory, loaded as the walk enters it.
+  matchers: HashMap<PathBuf, Gitignore>,
+}
+
+impl GitignoreFiles {
+  fn new(source_dir: &Path) -> Self {
+    let root = source_dir
+      .ancestors()
+      .find(|dir| dir.join(".git").exists())
+      .unwrap_or(source_dir);
+
+    Self {
+      root: root.to_path_buf(),
+      matchers: HashMap::new(),
+    }
+  }
+
+  /// The ignore files outside of a source directory that apply to it.
+  fn outer_files(source_dir: &Path) -> Vec<PathBuf> {
+    let root = Self::new(source_dir).root;
+    let mut files: Vec<_> = source_dir
+      .ancestors()
+      .skip(1)
+      .take_while(|dir| dir.starts_with(&root))
+      .map(|dir| dir.join(".gitignore"))
+      .collect();
+
+    files.push(root.join(".git/info/exclude"));
+    files
+  }
+
+  /// Whether a path is ignored, by the `.gitignore` file closest to it that matches it.
+  fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+    for dir in path.ancestors().skip(1) {
+      if !dir.starts_with(&self.root) {
+        break;
+      }
+
+      match self.matcher(dir).matched(path, is_dir) {
+        Match::Ignore(_) => return true,
+        Match::Whitelist(_) => return false,
+        Match::None => {}
+      }
+    }
+
+    false
+  }
+
+  fn matcher(&mut self, dir: &Path) -> &Gitignore {
+    let root = &self.root;
+    self.matchers.entry(dir.to_path_buf()).or_insert_with(|| {
+      let mut builder = GitignoreBuilder::new(dir);
+      let mut files = vec![dir.join(".gitignore")];
+      if dir == root {
+        files.push(dir.join(".git/info/exclude"));
+      }
+
+      // Invalid patterns are skipped, like git does
+      for file in files.into_iter().filter(|file| file.is_file()) {
+        builder.add(file);
+      }
+
+      builder.build().unwrap_or_else(|_| Gitignore::empty())
+    })
+  }
+}
+
+/// The path of the archive of a bundle in `OUT_DIR`.
+fn output_path(bundle_name: &str) -> PathBuf {
+  let out_dir = env::var("OUT_DIR").expect("no OUT_DIR");
+  Path::new(&out_dir).join(format!("{bundle_name}.embed_fs"))
+}
+
+/// Compile the globs of per-glob hooks like validators.
+fn compile_globs<T: ?Sized>(
+  hooks: &[(String, Box<T>)],
+) -> Result<Vec<(GlobMatcher, &T)>, ArchiveError> {
+  hooks
+    .iter()
+    .map(|(glob, hook)| Ok((Glob::new(glob)?.compile_matcher(), hook.as_ref())))
+    .collect()
+}
+
+#[cfg(unix)]
+fn file_mode(meta: &Metadata) -> Option<u32> {
+  use std::os::unix::fs::PermissionsExt;
+  Some(meta.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_meta: &Metadata) -> Option<u32> {
+  None
+}
+
+/// Compile globs into a set that matches if any of them match.
+fn glob_set(globs: &[String]) -> Result<GlobSet, ArchiveError> {
+  let mut builder = GlobSetBuilder::new();
+  for glob in globs {
+    builder.add(Glob::new(glob)?);
+  }
+
+  Ok(builder.build()?)
+}
+
+/// Extract a source archive into `dest`, replacing any previous contents.
+fn extract_archive(archive: &Path, dest: &Path) -> Result<(), ArchiveError> {
+  if dest.exists() {
+    fs::remove_dir_all(dest)?;
+  }
+
+  fs::create_dir_all(dest)?;
+
+  let name = archive
+    .file_name()
+    .and_then(|name| name.to_str())
+    .unwrap_or_default()
+    .to_ascii_lowercase();
+
+  #[cfg(feature = "tar")]
+  if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+    let decoder = flate2::read::GzDecoder::new(File::open(archive)?);
+    tar::Archive::new(decoder).unpack(dest)?;
+    return Ok(());
+  } else if name.ends_with(".tar") {
+    tar::Archive::new(File::open(archive)?).unpack(dest)?;
+    return Ok(());
+  }
+
+  #[cfg(feature = "zip")]
+  if name.ends_with(".zip") {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?).map_err(std::io::Error::from)?;
+    zip.extract(dest).map_err(std::io::Error::from)?;
+    return Ok(());
+  }
+
+  let _ = name;
+  Err(ArchiveError::UnsupportedSourceArchive {
+    path: archive.to_path_buf(),
+  })
+}
+
+/// Paths of the attribution files, sorted by directory.
+fn attribution_paths(files: &[FileEntry]) -> Vec<String> {
+  let mut paths: Vec<_> = files
+    .iter()
+    .map(|file| file.path.as_str())
+    .filter(|path| is_attribution_file(path))
+    .collect();
+
+  paths.sort_by_key(|path| (path.rsplit_once('/').map_or("", |(dir, _)| dir), *path));
+  paths.into_iter().map(String::from).collect()
+}
+
+/// The build time set for reproducible builds, in seconds since the Unix epoch.
+fn source_date_epoch() -> Option<u64> {
+  env::var("SOURCE_DATE_EPOCH").ok()?.parse().ok()
+}
+
+/// The current time in seconds since the Unix epoch, or `SOURCE_DATE_EPOCH` if it is set.
+fn build_timestamp() -> u64 {
+  if let Some(epoch) = source_date_epoch() {
+    return epoch;
+  }
+
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |duration| duration.as_secs())
+}
+
+/// Load the `.embedignore` file from the root of the source directory.
+/// If there is no ignore file, nothing is ignored.
+fn load_embedignore(source_dir: &Path) -> Result<Gitignore, ArchiveError> {
+  let mut builder = GitignoreBuilder::new(source_dir);
+  let ignore_file = source_dir.join(EMBEDIGNORE);
+  if ignore_file.is_file()
+    && let Some(err) = builder.add(&ignore_file)
+  {
+    return Err(ArchiveError::InvalidIgnoreFile(err));
+  }
+
+  builder.build().map_err(ArchiveError::InvalidIgnoreFile)
+}
+
+/// Convert a relative filesystem path into an archive path, using `/` as the separator.
+///
+/// Backslashes are treated as separators on every platform, so directories given as Windows
+/// paths produce the same archive paths when the bundle is built elsewhere.
+fn archive_path(path: &Path) -> Result<String, ArchiveError> {
+  let mut parts = Vec::new();
+  for component in path.components() {
+    let Component::Normal(part) = component else {
+      continue;
+    };
+
+    let Some(part) = part.to_str() else {
+      return Err(ArchiveError::NonUnicodePath {
+        path: path.to_path_buf(),
+      });
+    };
+
+    parts.extend(part.split('\\').filter(|part| !part.is_empty()));
+  }
+
+  Ok(parts.join("/"))
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+  path
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| {
+      extensions
+        .iter()
+        .any(|candidate| candidate.eq_ignore_ascii_case(ext))
+    })
+}
+
+fn normalize_line_endings(data: Vec<u8>) -> Vec<u8> {
+  if data.contains(&0) || std::str::from_utf8(&data).is_err() {
+    return data;
+  }
+
+  let mut normalized = Vec::with_capacity(data.len());
+  let mut bytes = data.iter().peekable();
+  while let Some(&byte) = bytes.next() {
+    if byte == b'\r' && bytes.peek() == Some(&&b'\n') {
+      continue;
+    }
+
+    normalized.push(byte);
+  }
+
+  normalized
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+
+  /// A temporary directory tree, removed when dropped.
+  pub(crate) struct TempTree(pub PathBuf);
+
+  impl TempTree {
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Self {
+      let dir = env::temp_dir().join(format!("include-fs-{name}-{}", std::process::id()));
+      let _ = fs::remove_dir_all(&dir);
+      fs::create_dir_all(&dir).unwrap();
+
+      for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+      }
+
+      Self(dir.canonicalize().unwrap())
+    }
+  }
+
+  impl Drop for TempTree {
+    fn drop(&mut self) {
+      let _ = fs::remove_dir_all(&self.0);
+    }
+  }
+
+  pub(crate) fn collected_paths(bundle: Bundle, tree: &TempTree) -> Vec<String> {
+    let files = bundle.collect(&tree.0, &tree.0, &mut Vec::new()).unwrap();
+    files.into_iter().map(|file| file.path).collect()
+  }
+
+  #[test]
+  fn test_embedignore() {
+    let tree = TempTree::new(
+      "embedignore",
+      &[
+        ("assets/.embedignore", "*.map\nscratch/\n!keep.map\n"),
+        ("assets/app.js", ""),
+        ("assets/app.js.map", ""),
+        ("assets/keep.map", ""),
+        ("assets/scratch/notes.txt", ""),
+      ],
+    );
+
+    assert_eq!(
+      collected_paths(Bundle::new("assets"), &tree),
+      ["assets/app.js", "assets/keep.map"]
+    );
+  }
+
+  #[test]
+  fn test_include_extensions() {
+    let tree = TempTree::new(
+      "include-extensions",
+      &[
+        ("public/index.html", ""),
+        ("public/logo.PNG", ""),
+        ("public/app.js.map", ""),
+        ("public/README", ""),
+      ],
+    );
+
+    let bundle = Bundle::new("public").include_extensions(["html", "png"]);
+    assert_eq!(
+      collected_paths(bundle, &tree),
+      ["public/index.html", "public/logo.PNG"]
+    );
+  }
+
+  #[test]
+  fn test_add_dir() {
+    let tree = TempTree::new(
+      "add-dir",
+      &[
+        ("frontend/dist/index.html", ""),
+        ("shared/assets/logo.png", ""),
+        ("vendor/logo.png", ""),
+      ],
+    );
+
+    let bundle = Bundle::new("frontend/dist")
+      .add_dir("shared/assets")
+      .add_dir_at("vendor", "/static/");
+    assert_eq!(
+      collected_paths(bundle, &tree),
+      [
+        "frontend/dist/index.html",
+        "shared/assets/logo.png",
+        "static/logo.png"
+      ]
+    );
+
+    let bundle = Bundle::new("frontend/dist")
+      .strip_source_dir(true)
+      .add_dir("shared/assets")
+      .add_dir_at("vendor", "");
+    assert!(matches!(
+      bundle.collect(&tree.0, &tree.0, &mut Vec::new()),
+      Err(ArchiveError::DuplicatePath(path)) if path == "logo.png"
+    ));
+  }
+
+  #[test]
+  fn test_file_metadata() {
+    let tree = TempTree::new("file-metadata", &[("assets/run.sh", "#!/bin/sh")]);
+    let path = tree.0.join("assets/run.sh");
+    let modified = UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(modified)
+      .unwrap();
+
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let files = Bundle::new("assets")
+      .file_metadata(true)
+      .collect(&tree.0, &tree.0, &mut Vec::new())
+      .unwrap();
+    assert_eq!(files[0].modified, Some(1_700_000_000));
+    assert_eq!(files[0].mode, cfg!(unix).then_some(0o755));
+
+    let files = Bundle::new("assets")
+      .collect(&tree.0, &tree.0, &mut Vec::new())
+      .unwrap();
+    assert_eq!((files[0].modified, files[0].mode), (None, None));
+  }
+
+  #[test]
+  fn test_strip_source_dir() {
+    let tree = TempTree::new(
+      "strip-source-dir",
+      &[("assets/logo.png", ""), ("assets/css/app.css", "")],
+    );
+
+    let bundle = Bundle::new("./assets")
+      .strip_source_dir(true)
+      .exclude("assets/**");
+    assert_eq!(collected_paths(bundle, &tree), ["css/app.css", "logo.png"]);
+  }
+
+  #[test]
+  fn test_include_exclude() {
+    let tree = TempTree::new(
+      "include-exclude",
+      &[
+        ("source/index.html", ""),
+        ("source/docs/guide.html", ""),
+        ("source/docs/guide.html.map", ""),
+        ("source/app.js", ""),
+        ("source/app.js.map", ""),
+        ("source/.DS_Store", ""),
+      ],
+    );
+
+    let bundle = Bundle::new("source")
+      .include("**/*.html")
+      .include("**/*.js")
+      .exclude("source/docs/**");
+    assert_eq!(
+      collected_paths(bundle, &tree),
+      ["source/app.js", "source/index.html"]
+    );
+
+    let bundle = Bundle::new("source")
+      .exclude("**/*.map")
+      .exclude("**/.DS_Store");
+    assert_eq!(
+      collected_paths(bundle, &tree),
+      [
+        "source/app.js",
+        "source/docs/guide.html",
+        "source/index.html"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_include_if() {
+    let tree = TempTree::new(
+      "include-if",
+      &[("assets/a.txt", ""), ("assets/premium/b.txt", "")],
+    );
+
+    let bundle = Bundle::new("assets").include_if(true, "assets/premium/**");
+    assert_eq!(
+      collected_paths(bundle, &tree),
+      ["assets/a.txt", "assets/premium/b.txt"]
+    );
+
+    let bundle = Bundle::new("assets").include_if(false, "assets/premium/**");
+    assert_eq!(collected_paths(bundle, &tree), ["assets/a.txt"]);
+  }
+
+  #[test]
+  fn test_exclude_larger_than() {
+    let tree = TempTree::new(
+      "exclude-larger-than",
+      &[("assets/small.txt", "1234"), ("assets/large.txt", "12345")],
+    );
+
+    let bundle = Bundle::new("assets").exclude_larger_than(4);
+    assert_eq!(collected_paths(bundle, &tree), ["assets/small.txt"]);
+  }
+
+  #[test]
+  fn test_size_limits() {
+    let tree = TempTree::new(
+      "size-limits",
+      &[
+        ("assets/a.txt", "1234"),
+        ("assets/b.txt", "12345"),
+        ("assets/c.txt", "123456"),
+      ],
+    );
+    let collect = |bundle: Bundle| bundle.collect(&tree.0, &tree.0, &mut Vec::new());
+
+    let err = collect(Bundle::new("assets").max_file_size(4)).unwrap_err();
+    let ArchiveError::FilesTooLarge { max: 4, files } = &err else {
+      panic!("unexpected error: {err}");
+    };
+    assert_eq!(
+      files,
+      &[
+        ("assets/c.txt".to_string(), 6),
+        ("assets/b.txt".to_string(), 5)
+      ]
+    );
+    assert!(err.to_string().ends_with("\n  assets/b.txt (5 bytes)"));
+
+    let err = collect(Bundle::new("assets").max_total_size(14)).unwrap_err();
+    assert!(matches!(
+      err,
+      ArchiveError::BundleTooLarge {
+        total: 15,
+        max: 14,
+        ..
+      }
+    ));
+
+    let bundle = Bundle::new("assets").max_file_size(6).max_total_size(15);
+    assert_eq!(collect(bundle).unwrap().len(), 3);
+  }
+
+  #[test]
+  fn test_filter() {
+    let tree = TempTree::new(
+      "filter",
+      &[
+        ("assets/empty.txt", ""),
+        ("assets/secret.txt", "DO NOT SHIP"),
+        ("assets/ok.txt", "ok"),
+      ],
+    );
+
+    let bundle = Bundle::new("assets").filter(|_, meta, contents| {
+      if meta.len() == 0 || contents.starts_with(b"DO NOT SHIP") {
+        Decision::Skip
+      } else {
+        Decision::Include
+      }
+    });
+
+    assert_eq!(collected_paths(bundle, &tree), ["assets/ok.txt"]);
+  }
+
+  #[test]
+  fn test_validate() {
+    let tree = TempTree::new(
+      "validate",
+      &[
+        ("assets/config.json", "{}"),
+        ("assets/lang/en.json", "{"),
+        ("assets/readme.txt", "{"),
+      ],
+    );
+
+    let bundle = || {
+      Bundle::new("assets").validate("**/*.json", |data| {
+        if data.ends_with(b"}") {
+          Ok(())
+        } else {
+          Err("unclosed object")
+        }
+      })
+    };
+
+    let err = bundle()
+      .collect(&tree.0, &tree.0, &mut Vec::new())
+      .unwrap_err();
+    assert!(matches!(
+      err,
+      ArchiveError::ValidationFailed { path, message }
+        if path == "assets/lang/en.json" && message == "unclosed object"
+    ));
+
+    let bundle = bundle().filter(|path, _, _| {
+      if path.ends_with("en.json") {
+        Decision::Skip
+      } else {
+        Decision::Include
+      }
+    });
+    assert_eq!(
+      collected_paths(bundle, &tree),
+      ["assets/config.json", "assets/readme.txt"]
+    );
+
+    let bundle = Bundle::new("assets").validate("[", |_| Ok::<_, String>(()));
+    assert!(matches!(
+      bundle.collect(&tree.0, &tree.0, &mut Vec::new()),
+      Err(ArchiveError::InvalidGlob(_))
+    ));
+  }
+
+  #[test]
+  fn test_precompile() {
+    let tree = TempTree::new(
+      "precompile",
+      &[
+        ("templates/index.html", "<p>{{ name }}</p>"),
+        ("templates/style.css", "p {}"),
+      ],
+    );
+
+    let bundle = Bundle::new("templates")
+      .precompile("**/*.html", |path, source| {
+        assert_eq!(path, "templates/index.html");
+        Ok::<_, String>(source.to_ascii_uppercase())
+      })
+      .validate("**/*.html", |data| {
+        if data.starts_with(b"<P>") {
+          Ok(())
+        } else {
+          Err("not precompiled")
+        }
+      });
+
+    let files = bundle.collect(&tree.0, &tree.0, &mut Vec::new()).unwrap();
+    assert_eq!(files[0].data, b"<P>{{ NAME }}</P>");
+    assert_eq!(files[1].data, b"p {}");
+
+    let bundle = Bundle::new("templates").precompile("**/*.html", |_, _| Err("unclosed tag"));
+    assert!(matches!(
+      bundle.collect(&tree.0, &tree.0, &mut Vec::new()),
+      Err(ArchiveError::PrecompileFailed { path, message })
+        if path == "templates/index.html" && message == "unclosed tag"
+    ));
+  }
+
+  #[test]
+  fn test_ordering() {
+    let tree = TempTree::new(
+      "ordering",
+      &[
+        ("public/a/b.js", ""),
+        ("public/b.css", ""),
+        ("public/index.html", ""),
+      ],
+    );
+
+    assert_eq!(
+      collected_paths(Bundle::new("public"), &tree),
+      ["public/a/b.js", "public/b.css", "public/index.html"]
+    );
+
+    assert_eq!(
+      collected_paths(Bundle::new("public").breadth_first(true), &tree),
+      ["public/b.css", "public/index.html", "public/a/b.js"]
+    );
+
+    let bundle = Bundle::new("public")
+      .sort_by(|a, b| b.ends_with("index.html").cmp(&a.ends_with("index.html")));
+    assert_eq!(
+      collected_paths(bundle, &tree),
+      ["public/index.html", "public/a/b.js", "public/b.css"]
+    );
+  }
+
+  #[test]
+  fn test_reproducible() {
+    let files = [
+      ("assets/b.txt", "b"),
+      ("assets/a/z.txt", "z"),
+      ("assets/a.txt", "a"),
+      ("assets/c/d.txt", "d"),
+    ];
+
+    // Directory iteration order depends on the order the files were created in
+    let mut reversed = files;
+    reversed.reverse();
+
+    let mut archives = Vec::new();
+    for (name, files) in [("reproducible-a", files), ("reproducible-b", reversed)] {
+      let tree = TempTree::new(name, &files);
+      let files = Bundle::new("assets")
+        .collect(&tree.0, &tree.0, &mut Vec::new())
+        .unwrap();
+
+      let mut archive = Vec::new();
+      write_archive(&files, &ArchiveOptions::default(), &mut archive).unwrap();
+      archives.push(archive);
+    }
+
+    assert_eq!(archives[0], archives[1]);
+  }
+
+  #[test]
+  fn test_skip_hidden() {
+    let tree = TempTree::new(
+      "skip-hidden",
+      &[
+        ("assets/a.txt", ""),
+        ("assets/.DS_Store", ""),
+        ("assets/.cache/b.txt", ""),
+      ],
+    );
+
+    assert_eq!(collected_paths(Bundle::new("assets"), &tree).len(), 3);
+    assert_eq!(
+      collected_paths(Bundle::new("assets").skip_hidden(true), &tree),
+      ["assets/a.txt"]
+    );
+  }
+
+  #[test]
+  fn test_respect_gitignore() {
+    let tree = TempTree::new(
+      "respect-gitignore",
+      &[
+        (".git/info/exclude", "*.local\n"),
+        (".gitignore", "node_modules/\n*.log\n"),
+        ("assets/.gitignore", "cache/\n!keep.log\n"),
+        ("assets/index.html", ""),
+        ("assets/app.log", ""),
+        ("assets/keep.log", ""),
+        ("assets/config.local", ""),
+        ("assets/cache/a.bin", ""),
+        ("assets/node_modules/lib/index.js", ""),
+        ("assets/nested/debug.log", ""),
+      ],
+    );
+
+    let paths = collected_paths(Bundle::new("assets").respect_gitignore(true), &tree);
+    assert_eq!(
+      paths,
+      ["assets/.gitignore", "assets/index.html", "assets/keep.log"]
+    );
+  }
+
+  #[test]
+  fn test_max_depth() {
+    let tree = TempTree::new(
+      "max-depth",
+      &[
+        ("assets/top.txt", ""),
+        ("assets/one/two.txt", ""),
+        ("assets/one/two/three.txt", ""),
+      ],
+    );
+
+    assert_eq!(
+      collected_paths(Bundle::new("assets").max_depth(2), &tree),
+      ["assets/one/two.txt", "assets/top.txt"]
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_broken_symlinks() {
+    let tree = TempTree::new("broken-symlinks", &[("assets/ok.txt", "")]);
+    std::os::unix::fs::symlink(tree.0.join("missing"), tree.0.join("assets/broken")).unwrap();
+
+    assert_eq!(
+      collected_paths(Bundle::new("assets"), &tree),
+      ["assets/ok.txt"]
+    );
+
+    let result = Bundle::new("assets")
+      .broken_symlinks(BrokenSymlinks::Error)
+      .collect(&tree.0, &tree.0, &mut Vec::new());
+    assert!(matches!(result, Err(ArchiveError::BrokenSymlink { .. })));
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_follow_symlinks() {
+    let tree = TempTree::new(
+      "follow-symlinks",
+      &[("assets/ok.txt", ""), ("shared/icons/menu.svg", "")],
+    );
+    std::os::unix::fs::symlink(tree.0.join("shared"), tree.0.join("assets/shared")).unwrap();
+    std::os::unix::fs::symlink(tree.0.join("assets"), tree.0.join("assets/loop")).unwrap();
+    std::os::unix::fs::symlink(tree.0.join("missing"), tree.0.join("assets/broken")).unwrap();
+
+    assert_eq!(
+      collected_paths(Bundle::new("assets"), &tree),
+      ["assets/ok.txt"]
+    );
+    assert_eq!(
+      collected_paths(Bundle::new("assets").follow_symlinks(true), &tree),
+      ["assets/ok.txt", "assets/shared/icons/menu.svg"]
+    );
+
+    let result = Bundle::new("assets")
+      .follow_symlinks(true)
+      .broken_symlinks(BrokenSymlinks::Error)
+      .collect(&tree.0, &tree.0, &mut Vec::new());
+    assert!(matches!(result, Err(ArchiveError::BrokenSymlink { .. })));
+  }
+
+  #[test]
+  fn test_empty_dirs() {
+    let tree = TempTree::new(
+      "empty-dirs",
+      &[("assets/a.txt", ""), ("assets/b/c.txt", "")],
+    );
+    for dir in ["assets/empty", "assets/nested/empty", "assets/excluded"] {
+      fs::create_dir_all(tree.0.join(dir)).unwrap();
+    }
+
+    let mut empty_dirs = Vec::new();
+    Bundle::new("assets")
+      .collect(&tree.0, &tree.0, &mut empty_dirs)
+      .unwrap();
+    assert!(empty_dirs.is_empty());
+
+    Bundle::new("assets")
+      .empty_dirs(true)
+      .exclude("assets/excluded")
+      .collect(&tree.0, &tree.0, &mut empty_dirs)
+      .unwrap();
+    assert_eq!(empty_dirs, ["assets/empty", "assets/nested/empty"]);
+  }
+
+  #[test]
+  fn test_optional() {
+    let tree = TempTree::new("optional", &[("a.txt", "a")]);
+
+    let bundle = Bundle::new("missing").optional(true);
+    assert!(collected_paths(bundle, &tree).is_empty());
+
+    let bundle = Bundle::new("missing");
+    assert!(matches!(
+      bundle.collect(&tree.0, &tree.0, &mut Vec::new()),
+      Err(ArchiveError::Io(_))
+    ));
+  }
+
+  #[test]
+  fn test_allow_external() {
+    let tree = TempTree::new(
+      "allow-external",
+      &[("assets/dir/a.txt", "a"), ("crate/Cargo.toml", "")],
+    );
+    let manifest_dir = tree.0.join("crate");
+
+    let bundle = Bundle::new("../assets");
+    assert!(matches!(
+      bundle.collect(&manifest_dir, &manifest_dir, &mut Vec::new()),
+      Err(ArchiveError::InvalidSourceDirectory)
+    ));
+
+    let bundle = Bundle::new("../assets").allow_external(true);
+    let files = bundle
+      .collect(&manifest_dir, &manifest_dir, &mut Vec::new())
+      .unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "dir/a.txt");
+
+    let watched = format!("cargo:rerun-if-changed={}", tree.0.join("assets").display());
+    assert!(bundle.directives.take().contains(&watched));
+  }
+
+  #[test]
+  fn test_rerun_if_changed() {
+    let tree = TempTree::new(
+      "rerun-if-changed",
+      &[("assets/a.txt", "a"), ("assets/dir/b.txt", "b")],
+    );
+
+    let bundle = Bundle::new("assets");
+    bundle.collect(&tree.0, &tree.0, &mut Vec::new()).unwrap();
+    let assets = tree.0.join("assets");
+    let expected = [
+      "assets".to_string(),
+      assets.join("a.txt").display().to_string(),
+      assets.join("dir").display().to_string(),
+      assets.join("dir/b.txt").display().to_string(),
+    ];
+
+    let mut directives = bundle.directives.take();
+    directives.sort();
+    let mut expected = expected.map(|path| format!("cargo:rerun-if-changed={path}"));
+    expected.sort();
+    assert_eq!(directives, expected);
+  }
+
+  #[test]
+  fn test_input_hash() {
+    let tree = TempTree::new("input-hash", &[("assets/a.txt", "a"), ("other/b.txt", "b")]);
+    let hash = |bundle: Bundle| bundle.input_hash(&tree.0).unwrap();
+
+    let before = hash(Bundle::new("assets"));
+    assert!(before.is_some());
+    assert_eq!(hash(Bundle::new("assets")), before);
+    assert_ne!(hash(Bundle::new("assets").exclude("*.map")), before);
+    assert_eq!(hash(Bundle::new("assets").incremental(false)), None);
+    assert_eq!(hash(Bundle::new("assets").build_timestamp(true)), None);
+
+    // Files outside of the source directories don't matter
+    fs::write(tree.0.join("other/b.txt"), "changed").unwrap();
+    assert_eq!(hash(Bundle::new("assets")), before);
+    assert_ne!(hash(Bundle::new("assets").add_dir("other")), before);
+
+    fs::write(tree.0.join("assets/a.txt"), "changed").unwrap();
+    assert_ne!(hash(Bundle::new("assets")), before);
+  }
+
+  #[test]
+  fn test_attribution_paths() {
+    let files = [
+      FileEntry::new("fonts/LICENSE", ""),
+      FileEntry::new("fonts/Mono/LICENSE", ""),
+      FileEntry::new("fonts/NOTICE", ""),
+      FileEntry::new("fonts/inter.ttf", ""),
+    ];
+
+    assert_eq!(
+      attribution_paths(&files),
+      ["fonts/LICENSE", "fonts/NOTICE", "fonts/Mono/LICENSE"]
+    );
+  }
+
+  #[test]
+  fn test_case_insensitive() {
+    let tree = TempTree::new("case-insensitive", &[("assets/Logo.png", "")]);
+    let bundle = Bundle::new("assets").case_insensitive(true);
+    assert_eq!(collected_paths(bundle, &tree), ["assets/logo.png"]);
+
+    fs::write(tree.0.join("assets/logo.png"), "").unwrap();
+
+    // Skip the collision check on case-insensitive filesystems
+    if fs::read_dir(tree.0.join("assets")).unwrap().count() == 2 {
+      let result =
+        Bundle::new("assets")
+          .case_insensitive(true)
+          .collect(&tree.0, &tree.0, &mut Vec::new());
+      let Err(ArchiveError::PathCollision { first, second, .. }) = result else {
+        panic!("expected a collision");
+      };
+
+      assert_eq!(first, "assets/Logo.png");
+      assert_eq!(second, "assets/logo.png");
+    }
+  }
+
+  #[cfg(feature = "unicode-normalization")]
+  #[test]
+  fn test_normalize_unicode() {
+    let tree = TempTree::new("normalize-unicode", &[("assets/cafe\u{301}.txt", "")]);
+    let bundle = Bundle::new("assets").normalize_unicode(true);
+    assert_eq!(collected_paths(bundle, &tree), ["assets/caf\u{e9}.txt"]);
+  }
+
+  #[cfg(feature = "tar")]
+  #[test]
+  fn test_from_tar_archive() {
+    let tree = TempTree::new("from-tar", &[("dist/index.html", "<html></html>")]);
+
+    let encoder = flate2::write::GzEncoder::new(
+      File::create(tree.0.join("dist.tar.gz")).unwrap(),
+      flate2::Compression::default(),
+    );
+    let mut builder = tar::Builder::new(encoder);
+    builder.append_dir_all("dist", tree.0.join("dist")).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let out_dir = tree.0.join("out");
+    let files = Bundle::from_archive("dist.tar.gz")
+      .collect(&tree.0, &out_dir, &mut Vec::new())
+      .unwrap();
+
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "dist/index.html");
+    assert_eq!(files[0].data, b"<html></html>");
+  }
+
+  #[cfg(feature = "zip")]
+  #[test]
+  fn test_from_zip_archive() {
+    use std::io::Write;
+
+    let tree = TempTree::new("from-zip", &[]);
+    let mut zip = zip::ZipWriter::new(File::create(tree.0.join("dist.zip")).unwrap());
+    zip
+      .start_file("dist/app.js", zip::write::SimpleFileOptions::default())
+      .unwrap();
+    zip.write_all(b"main()").unwrap();
+    zip.finish().unwrap();
+
+    let out_dir = tree.0.join("out");
+    let files = Bundle::from_archive("dist.zip")
+      .collect(&tree.0, &out_dir, &mut Vec::new())
+      .unwrap();
+
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "dist/app.js");
+    assert_eq!(files[0].data, b"main()");
+  }
+
+  #[test]
+  fn test_archive_path() {
+    let path = Path::new("assets").join("icons").join("menu.svg");
+    assert_eq!(archive_path(&path).unwrap(), "assets/icons/menu.svg");
+    assert_eq!(
+      archive_path(Path::new("./src/main.rs")).unwrap(),
+      "src/main.rs"
+    );
+    assert_eq!(
+      archive_path(Path::new("assets\\icons\\menu.svg")).unwrap(),
+      "assets/icons/menu.svg"
+    );
+  }
+
+  #[test]
+  fn test_normalize_line_endings() {
+    assert_eq!(normalize_line_endings(b"a\r\nb\r\n".to_vec()), b"a\nb\n");
+    assert_eq!(normalize_line_endings(b"a\rb".to_vec()), b"a\rb");
+
+    // Binary files are left untouched
+    assert_eq!(normalize_line_endings(b"\0\r\n".to_vec()), b"\0\r\n");
+  }
+}
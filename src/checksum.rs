@@ -0,0 +1,184 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::{FsEntry, FsError, IncludeFsInner};
+
+/// Algorithm used for the per-file checksums stored with [`Bundle::checksums`].
+///
+/// [`Bundle::checksums`]: crate::Bundle::checksums
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgorithm {
+  /// CRC-32 (IEEE), the cheapest to verify. Only detects accidental corruption.
+  Crc32,
+  /// 64-bit xxHash, nearly as cheap with fewer collisions. Only detects accidental corruption.
+  XxHash64,
+  /// 256-bit BLAKE3, a cryptographic hash that also detects deliberate modification. Computing
+  /// it requires the `blake3` feature.
+  Blake3,
+}
+
+impl ChecksumAlgorithm {
+  /// The identifier of the algorithm in the archive.
+  #[cfg(feature = "std")]
+  pub(crate) fn id(self) -> u8 {
+    match self {
+      ChecksumAlgorithm::Crc32 => 1,
+      ChecksumAlgorithm::XxHash64 => 2,
+      ChecksumAlgorithm::Blake3 => 3,
+    }
+  }
+
+  pub(crate) fn from_id(id: u8) -> Option<Self> {
+    match id {
+      1 => Some(ChecksumAlgorithm::Crc32),
+      2 => Some(ChecksumAlgorithm::XxHash64),
+      3 => Some(ChecksumAlgorithm::Blake3),
+      _ => None,
+    }
+  }
+
+  /// Compute the checksum of `data`, or `None` if the algorithm is not enabled.
+  pub fn compute(self, data: &[u8]) -> Option<Vec<u8>> {
+    match self {
+      ChecksumAlgorithm::Crc32 => Some(crc32fast::hash(data).to_le_bytes().to_vec()),
+      ChecksumAlgorithm::XxHash64 => {
+        Some(xxhash_rust::xxh64::xxh64(data, 0).to_le_bytes().to_vec())
+      }
+      #[cfg(feature = "blake3")]
+      ChecksumAlgorithm::Blake3 => Some(blake3::hash(data).as_bytes().to_vec()),
+      #[cfg(not(feature = "blake3"))]
+      ChecksumAlgorithm::Blake3 => None,
+    }
+  }
+}
+
+impl FsEntry {
+  /// Check data against the stored checksum. Files without a checksum always pass.
+  pub(crate) fn verify_checksum(
+    &self,
+    algorithm: Option<ChecksumAlgorithm>,
+    path: &str,
+    data: &[u8],
+  ) -> Result<(), FsError> {
+    let (Some(expected), Some(algorithm)) = (&self.checksum, algorithm) else {
+      return Ok(());
+    };
+
+    let actual = algorithm
+      .compute(data)
+      .ok_or(FsError::UnsupportedChecksum(algorithm))?;
+    if actual != *expected {
+      return Err(FsError::ChecksumMismatch(path.to_string()));
+    }
+
+    Ok(())
+  }
+}
+
+impl IncludeFsInner {
+  /// Check every file against its checksum stored with [`Bundle::checksums`], returning
+  /// [`FsError::ChecksumMismatch`] for the first corrupt file.
+  ///
+  /// Files are also decrypted and decompressed, so this detects corrupt archives without
+  /// checksums as well, as long as the corruption affects encoded data. Use
+  /// [`verify_reads`](Self::verify_reads) to check files as they are read instead.
+  ///
+  /// [`Bundle::checksums`]: crate::Bundle::checksums
+  pub fn verify(&self) -> Result<(), FsError> {
+    for (path, entry) in self.index.files.all(self.archive_bytes).flatten() {
+      let data = self.data(entry)?;
+      self.verify_file(path, entry, data)?;
+    }
+
+    Ok(())
+  }
+
+  /// Check the data of a file, unless it has already been checked.
+  pub(crate) fn verify_file(
+    &self,
+    path: &str,
+    entry: &FsEntry,
+    data: &[u8],
+  ) -> Result<(), FsError> {
+    if entry.verified.get().is_some() {
+      return Ok(());
+    }
+
+    entry.verify_checksum(self.index.checksum_algorithm, path, data)?;
+    entry.verified.get_or_init(|| ());
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::archive::{ArchiveOptions, FileEntry, write_archive};
+
+  #[test]
+  fn test_compute() {
+    assert_eq!(
+      ChecksumAlgorithm::Crc32.compute(b"hello").unwrap(),
+      0x3610a686u32.to_le_bytes()
+    );
+    assert_eq!(
+      ChecksumAlgorithm::XxHash64.compute(b"").unwrap(),
+      0xef46db3751d8e999u64.to_le_bytes()
+    );
+
+    for algorithm in [
+      ChecksumAlgorithm::Crc32,
+      ChecksumAlgorithm::XxHash64,
+      ChecksumAlgorithm::Blake3,
+    ] {
+      assert_eq!(ChecksumAlgorithm::from_id(algorithm.id()), Some(algorithm));
+    }
+
+    assert_eq!(
+      ChecksumAlgorithm::Blake3
+        .compute(b"")
+        .map(|hash| hash.len()),
+      cfg!(feature = "blake3").then_some(32)
+    );
+  }
+
+  #[test]
+  fn test_verify() {
+    let files: Vec<_> = ["a.txt", "b.txt"]
+      .into_iter()
+      .map(|path| {
+        let mut file = FileEntry::new(path, format!("contents of {path}"));
+        file.checksum = ChecksumAlgorithm::XxHash64.compute(&file.data);
+        file
+      })
+      .collect();
+
+    let options = ArchiveOptions {
+      checksum: Some(ChecksumAlgorithm::XxHash64),
+      ..Default::default()
+    };
+
+    let mut archive = Vec::new();
+    write_archive(&files, &options, &mut archive).unwrap();
+    let fs = IncludeFsInner::new(Box::leak(archive.clone().into_boxed_slice())).unwrap();
+    fs.verify().unwrap();
+
+    // Flip the last byte, which belongs to the data of b.txt
+    *archive.last_mut().unwrap() ^= 1;
+    let corrupt = IncludeFsInner::new(Box::leak(archive.into_boxed_slice())).unwrap();
+    assert!(matches!(
+      corrupt.verify(),
+      Err(FsError::ChecksumMismatch(path)) if path == "b.txt"
+    ));
+
+    // Reads are only checked if enabled
+    assert!(corrupt.get("b.txt").is_ok());
+    let corrupt = corrupt.verify_reads(true);
+    assert!(corrupt.get("a.txt").is_ok());
+    assert!(matches!(
+      corrupt.get("b.txt"),
+      Err(FsError::ChecksumMismatch(_))
+    ));
+    assert!(corrupt.entries().any(|entry| entry.is_err()));
+  }
+}
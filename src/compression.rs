@@ -0,0 +1,293 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+#[cfg(feature = "std")]
+use crate::ArchiveError;
+use crate::FsError;
+#[cfg(feature = "std")]
+use crate::archive::FileEntry;
+#[cfg(feature = "std")]
+use crate::http::Encoding;
+
+/// Codec used to compress files with [`Bundle::compress`].
+///
+/// [`Bundle::compress`]: crate::Bundle::compress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+  /// Zstandard, which decompresses quickly with good ratios. Requires the `zstd` feature.
+  Zstd,
+  /// gzip, which is slower and larger but widely supported. Requires the `gzip` feature.
+  Gzip,
+}
+
+impl Compression {
+  /// The identifier of the codec in the archive.
+  #[cfg(feature = "std")]
+  pub(crate) fn id(self) -> u8 {
+    match self {
+      Compression::Zstd => 1,
+      Compression::Gzip => 2,
+    }
+  }
+
+  pub(crate) fn from_id(id: u8) -> Option<Self> {
+    match id {
+      1 => Some(Compression::Zstd),
+      2 => Some(Compression::Gzip),
+      _ => None,
+    }
+  }
+
+  /// Compress `data`, or return `None` if the codec is not enabled.
+  ///
+  /// Files are compressed at the highest regular level, since this only happens at build time
+  /// and doesn't slow down decompression.
+  #[cfg(feature = "std")]
+  #[cfg_attr(not(all(feature = "zstd", feature = "gzip")), allow(unused_variables))]
+  fn compress(self, data: &[u8]) -> Option<Vec<u8>> {
+    match self {
+      #[cfg(feature = "zstd")]
+      Compression::Zstd => Some(zstd::encode_all(data, 19).expect("compressing into memory")),
+      #[cfg(feature = "gzip")]
+      Compression::Gzip => {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(data).expect("compressing into memory");
+        Some(encoder.finish().expect("compressing into memory"))
+      }
+      #[allow(unreachable_patterns)]
+      _ => None,
+    }
+  }
+
+  #[cfg(feature = "std")]
+  #[cfg_attr(not(all(feature = "zstd", feature = "gzip")), allow(unused_variables))]
+  pub(crate) fn decoder(self, data: &[u8]) -> Result<Box<dyn Read + '_>, FsError> {
+    match self {
+      #[cfg(feature = "zstd")]
+      Compression::Zstd => Ok(Box::new(
+        zstd::Decoder::new(data).map_err(|_| FsError::InvalidArchive)?,
+      )),
+      #[cfg(feature = "gzip")]
+      Compression::Gzip => Ok(Box::new(flate2::read::GzDecoder::new(data))),
+      #[allow(unreachable_patterns)]
+      _ => Err(FsError::UnsupportedCompression(self)),
+    }
+  }
+
+  /// Decompress `data`, which has to decompress to exactly `size` bytes.
+  #[cfg(feature = "std")]
+  pub(crate) fn decompress(self, data: &[u8], size: usize) -> Result<Vec<u8>, FsError> {
+    // Reading one byte more than expected detects oversized data without decompressing all of it
+    let mut out = Vec::new();
+    let result = self
+      .decoder(data)?
+      .take(size as u64 + 1)
+      .read_to_end(&mut out);
+
+    if result.is_err() || out.len() != size {
+      return Err(FsError::InvalidArchive);
+    }
+
+    Ok(out)
+  }
+
+  /// The codecs need `std`, so compressed files can't be read without it.
+  #[cfg(not(feature = "std"))]
+  pub(crate) fn decompress(self, _: &[u8], _: usize) -> Result<Vec<u8>, FsError> {
+    Err(FsError::UnsupportedCompression(self))
+  }
+}
+
+/// Extensions of formats that are compressed already, so compressing them again wastes time.
+#[cfg(feature = "std")]
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+  "7z", "apk", "avif", "br", "bz2", "docx", "gif", "gz", "heic", "jar", "jpeg", "jpg", "jxl",
+  "ktx2", "m4a", "mkv", "mov", "mp3", "mp4", "ogg", "opus", "png", "rar", "tgz", "webm", "webp",
+  "woff", "woff2", "xlsx", "xz", "zip", "zst",
+];
+
+/// Whether a file is in a format that is compressed already, going by its extension.
+#[cfg(feature = "std")]
+fn is_compressed_format(path: &str) -> bool {
+  let extension = path.rsplit_once('.').map(|(_, extension)| extension);
+  extension.is_some_and(|extension| {
+    COMPRESSED_EXTENSIONS
+      .iter()
+      .any(|compressed| compressed.eq_ignore_ascii_case(extension))
+  })
+}
+
+/// Compress the data of a file, including its NUL terminator.
+///
+/// Files in compressed formats, and files that don't get smaller, are stored as they are. They
+/// have no compression attribute, so readers borrow them like any other uncompressed file.
+#[cfg(feature = "std")]
+pub(crate) fn compress_file(
+  file: &mut FileEntry,
+  compression: Compression,
+) -> Result<(), ArchiveError> {
+  if is_compressed_format(&file.path) {
+    return Ok(());
+  }
+
+  let size = file.data.len() as u64;
+  if file.nul_terminated {
+    file.data.push(0);
+  }
+
+  let compressed = compression.compress(&file.data);
+  let compressed = compressed.ok_or(ArchiveError::UnsupportedCompression(compression))?;
+  if compressed.len() >= file.data.len() {
+    file.data.truncate(size as usize);
+    return Ok(());
+  }
+
+  file.data = compressed;
+  file.compression = Some((compression, size));
+  Ok(())
+}
+
+#[cfg(feature = "std")]
+impl Encoding {
+  /// Compress `data` for HTTP responses, or return `None` if the codec is not enabled.
+  #[cfg_attr(
+    not(all(feature = "brotli", feature = "gzip")),
+    allow(unused_variables)
+  )]
+  fn compress(self, data: &[u8]) -> Option<Vec<u8>> {
+    match self {
+      #[cfg(feature = "brotli")]
+      Encoding::Brotli => {
+        use std::io::Write;
+
+        // The highest quality, with the largest window browsers are required to support
+        let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+        encoder.write_all(data).expect("compressing into memory");
+        Some(encoder.into_inner())
+      }
+      #[cfg(feature = "gzip")]
+      Encoding::Gzip => Compression::Gzip.compress(data),
+      #[allow(unreachable_patterns)]
+      _ => None,
+    }
+  }
+}
+
+/// Store precompressed variants of the data of a file, which has to be neither compressed nor
+/// encrypted yet.
+///
+/// Like with [`compress_file`], files in compressed formats get no variants, and variants that
+/// are not smaller than the data are dropped.
+#[cfg(feature = "std")]
+pub(crate) fn precompress_file(
+  file: &mut FileEntry,
+  encodings: &[Encoding],
+) -> Result<(), ArchiveError> {
+  if is_compressed_format(&file.path) {
+    return Ok(());
+  }
+
+  for &encoding in encodings {
+    let compressed = encoding.compress(&file.data);
+    let compressed = compressed.ok_or(ArchiveError::UnsupportedEncoding(encoding))?;
+    if compressed.len() < file.data.len() {
+      file.encodings.push((encoding, compressed));
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_round_trip() {
+    let data = "hello ".repeat(100);
+    for compression in [Compression::Zstd, Compression::Gzip] {
+      assert_eq!(Compression::from_id(compression.id()), Some(compression));
+
+      let Some(compressed) = compression.compress(data.as_bytes()) else {
+        assert!(matches!(
+          compression.decompress(b"", 0),
+          Err(FsError::UnsupportedCompression(_))
+        ));
+        continue;
+      };
+
+      assert!(compressed.len() < data.len());
+      assert_eq!(
+        compression.decompress(&compressed, data.len()).unwrap(),
+        data.as_bytes()
+      );
+      assert!(matches!(
+        compression.decompress(&compressed, data.len() - 1),
+        Err(FsError::InvalidArchive)
+      ));
+      assert!(matches!(
+        compression.decompress(&compressed[..10], data.len()),
+        Err(FsError::InvalidArchive)
+      ));
+    }
+  }
+
+  #[cfg(any(feature = "zstd", feature = "gzip"))]
+  #[test]
+  fn test_skips_incompressible_files() {
+    let compression = match cfg!(feature = "zstd") {
+      true => Compression::Zstd,
+      false => Compression::Gzip,
+    };
+
+    let text = "hello ".repeat(100);
+    let mut files = [
+      FileEntry::new("a.txt", text.as_str()),
+      FileEntry::new("b.PNG", text.as_str()),
+      FileEntry::new("c.txt", "tiny"),
+    ];
+    files[2].nul_terminated = true;
+    for file in &mut files {
+      compress_file(file, compression).unwrap();
+    }
+
+    assert!(files[0].compression.is_some());
+    assert!(files[1].compression.is_none());
+    assert!(files[2].compression.is_none());
+    assert_eq!(files[2].data, b"tiny");
+  }
+
+  #[cfg(all(feature = "brotli", feature = "gzip"))]
+  #[test]
+  fn test_precompress() {
+    let text = "hello ".repeat(100);
+    let mut files = [
+      FileEntry::new("a.js", text.as_str()),
+      FileEntry::new("b.woff2", text.as_str()),
+      FileEntry::new("c.js", "tiny"),
+    ];
+    for file in &mut files {
+      precompress_file(file, &[Encoding::Brotli, Encoding::Gzip]).unwrap();
+    }
+
+    assert!(files[1].encodings.is_empty());
+    assert!(files[2].encodings.is_empty());
+
+    let [(Encoding::Brotli, brotli), (Encoding::Gzip, gzip)] = &files[0].encodings[..] else {
+      panic!("unexpected encodings: {:?}", files[0].encodings);
+    };
+
+    let mut decompressed = Vec::new();
+    brotli::Decompressor::new(brotli.as_slice(), 4096)
+      .read_to_end(&mut decompressed)
+      .unwrap();
+    assert_eq!(decompressed, text.as_bytes());
+    assert_eq!(
+      Compression::Gzip.decompress(gzip, text.len()).unwrap(),
+      text.as_bytes()
+    );
+  }
+}
@@ -0,0 +1,100 @@
+//! Encryption of file data with XChaCha20-Poly1305, using keys derived with Argon2id.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::XChaCha20Poly1305;
+use chacha20poly1305::aead::{Aead, KeyInit};
+
+use crate::archive::{FileEntry, KdfParams, NONCE_LEN};
+
+pub(crate) type Key = [u8; 32];
+
+/// Length of the salt used for key derivation.
+const SALT_LEN: usize = 16;
+
+/// Fill `buf` with random bytes for a salt or nonce.
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+fn fill_random(buf: &mut [u8]) {
+  getrandom::fill(buf).expect("failed to generate random bytes");
+}
+
+/// `wasm32-unknown-unknown` has no source of randomness without JavaScript. Encrypted bundles
+/// are built by build scripts, so they can still be read there.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+fn fill_random(_: &mut [u8]) {
+  panic!("bundles can't be encrypted on wasm32-unknown-unknown");
+}
+
+/// Key derivation parameters with a random salt and the default Argon2id costs.
+pub(crate) fn random_kdf_params() -> KdfParams {
+  let mut salt = vec![0; SALT_LEN];
+  fill_random(&mut salt);
+
+  KdfParams {
+    m_cost: Params::DEFAULT_M_COST,
+    t_cost: Params::DEFAULT_T_COST,
+    p_cost: Params::DEFAULT_P_COST,
+    salt,
+  }
+}
+
+/// Derive a key from a passphrase, returning `None` if the parameters are invalid.
+pub(crate) fn derive_key(passphrase: &str, kdf: &KdfParams) -> Option<Key> {
+  let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32)).ok()?;
+  let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+  let mut key = [0; 32];
+  argon2
+    .hash_password_into(passphrase.as_bytes(), &kdf.salt, &mut key)
+    .ok()?;
+
+  Some(key)
+}
+
+/// Encrypt data with a random nonce, returning the nonce and the ciphertext.
+pub(crate) fn encrypt(key: &Key, plaintext: &[u8]) -> ([u8; NONCE_LEN], Vec<u8>) {
+  let mut nonce = [0; NONCE_LEN];
+  fill_random(&mut nonce);
+
+  let ciphertext = XChaCha20Poly1305::new(&(*key).into())
+    .encrypt(&nonce.into(), plaintext)
+    .expect("failed to encrypt");
+
+  (nonce, ciphertext)
+}
+
+/// Decrypt data, returning `None` if the key is wrong or the data was modified.
+pub(crate) fn decrypt(key: &Key, nonce: &[u8; NONCE_LEN], ciphertext: &[u8]) -> Option<Vec<u8>> {
+  XChaCha20Poly1305::new(&(*key).into())
+    .decrypt(&(*nonce).into(), ciphertext)
+    .ok()
+}
+
+/// Encrypt the data of a file, including its NUL terminator.
+pub(crate) fn encrypt_file(key: &Key, file: &mut FileEntry) {
+  // Compressed data already contains the NUL terminator
+  if file.nul_terminated && file.compression.is_none() {
+    file.data.push(0);
+  }
+
+  let (nonce, ciphertext) = encrypt(key, &file.data);
+  file.data = ciphertext;
+  file.nonce = Some(nonce);
+
+  // Precompressed variants would contain the file unencrypted
+  file.encodings.clear();
+}
+
+/// A value that allows checking whether a key is correct before decrypting any files. It is the
+/// nonce followed by the encryption of an empty message.
+pub(crate) fn key_check(key: &Key) -> Vec<u8> {
+  let (nonce, ciphertext) = encrypt(key, &[]);
+  [&nonce[..], &ciphertext].concat()
+}
+
+pub(crate) fn verify_key(key: &Key, key_check: &[u8]) -> bool {
+  let Some((nonce, ciphertext)) = key_check.split_first_chunk::<NONCE_LEN>() else {
+    return false;
+  };
+
+  decrypt(key, nonce, ciphertext).is_some()
+}
@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::IncludeFsInner;
+
+/// Differences between an embedded filesystem and a directory on disk.
+///
+/// All paths are embedded paths, i.e. relative to the compared directory and separated by `/`.
+/// Each list is sorted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FsDiff {
+  /// Files that exist in the directory but are not embedded.
+  pub missing: Vec<String>,
+  /// Files that are embedded but do not exist in the directory.
+  pub extra: Vec<String>,
+  /// Files that exist in both places with different contents.
+  pub changed: Vec<String>,
+}
+
+impl FsDiff {
+  /// Whether the embedded filesystem and the directory have identical contents.
+  pub fn is_empty(&self) -> bool {
+    self.missing.is_empty() && self.extra.is_empty() && self.changed.is_empty()
+  }
+}
+
+/// Compare an embedded filesystem against a directory on disk.
+///
+/// Embedded paths are resolved relative to `dir`, so `dir` should correspond to the directory
+/// the bundle's paths are relative to. This is useful for smoke tests that verify the assets
+/// embedded in a binary match the ones in a release artifacts directory.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// static ASSETS: IncludeFs = include_fs!("assets");
+///
+/// let diff = include_fs::diff_against_dir(&ASSETS, "release")?;
+/// assert!(diff.is_empty(), "embedded assets are out of date: {diff:?}");
+/// ```
+pub fn diff_against_dir<P: AsRef<Path>>(fs: &IncludeFsInner, dir: P) -> io::Result<FsDiff> {
+  let dir = dir.as_ref();
+  let mut diff = FsDiff::default();
+  let mut on_disk = HashSet::new();
+
+  for entry in WalkDir::new(dir).follow_links(false) {
+    let entry = entry?;
+    if !entry.file_type().is_file() {
+      continue;
+    }
+
+    let relative = entry.path().strip_prefix(dir).unwrap();
+    let path = relative
+      .components()
+      .map(|c| c.as_os_str().to_string_lossy())
+      .collect::<Vec<_>>()
+      .join("/");
+
+    match fs.get(&path) {
+      Ok(embedded) => {
+        if fs::read(entry.path())? != embedded {
+          diff.changed.push(path.clone());
+        }
+      }
+      Err(_) => diff.missing.push(path.clone()),
+    }
+
+    on_disk.insert(path);
+  }
+
+  for path in fs.list_paths() {
+    if !on_disk.contains(path.as_str()) {
+      diff.extra.push(path.to_string());
+    }
+  }
+
+  diff.missing.sort();
+  diff.extra.sort();
+  diff.changed.sort();
+
+  Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+  use std::env;
+
+  #[test]
+  fn test_diff_against_dir() {
+    let dir = env::temp_dir().join(format!("include-fs-diff-{}", std::process::id()));
+    fs::create_dir_all(dir.join("assets")).unwrap();
+    fs::write(dir.join("assets/same.txt"), "same").unwrap();
+    fs::write(dir.join("assets/changed.txt"), "new").unwrap();
+    fs::write(dir.join("assets/missing.txt"), "").unwrap();
+
+    let embedded = test_fs(&[
+      ("assets/same.txt", b"same"),
+      ("assets/changed.txt", b"old"),
+      ("assets/extra.txt", b""),
+    ]);
+
+    let diff = diff_against_dir(&embedded, &dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(diff.missing, ["assets/missing.txt"]);
+    assert_eq!(diff.extra, ["assets/extra.txt"]);
+    assert_eq!(diff.changed, ["assets/changed.txt"]);
+    assert!(!diff.is_empty());
+  }
+}
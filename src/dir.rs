@@ -0,0 +1,263 @@
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{EmbeddedPath, FsError, IncludeFsInner};
+
+/// A file or directory inside a directory, returned by [`IncludeFsInner::read_dir`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirEntry<'a> {
+  path: &'a str,
+  is_dir: bool,
+}
+
+impl<'a> DirEntry<'a> {
+  /// The embedded path of the entry.
+  pub fn path(&self) -> &'a str {
+    self.path
+  }
+
+  /// The name of the entry, without its directory.
+  pub fn file_name(&self) -> &'a str {
+    self.path.rsplit('/').next().unwrap_or(self.path)
+  }
+
+  pub fn is_file(&self) -> bool {
+    !self.is_dir
+  }
+
+  pub fn is_dir(&self) -> bool {
+    self.is_dir
+  }
+}
+
+impl IncludeFsInner {
+  /// The files and directories directly inside a directory, sorted by name. The root directory
+  /// is `""`.
+  ///
+  /// Only directories that contain files exist, unless the bundle was built with
+  /// [`Bundle::empty_dirs`](crate::Bundle::empty_dirs). Returns [`FsError::NotADirectory`] if
+  /// the path is a file.
+  pub fn read_dir(&self, path: impl AsRef<str>) -> Result<Vec<DirEntry<'_>>, FsError> {
+    let dir = self.normalize_dir(path.as_ref()).ok_or(FsError::NotFound)?;
+    let dir = &*dir;
+
+    // Map entry names to the entries, which borrow the path of a file inside them
+    let prefix = dir_prefix(dir);
+    let mut entries = BTreeMap::new();
+    let files = self.index.files.with_prefix(self.archive_bytes, &prefix);
+    for (path, _) in files {
+      let Some(rest) = child_path(dir, path) else {
+        continue;
+      };
+
+      match rest.split_once('/') {
+        Some((name, _)) => {
+          let len = path.len() - rest.len() + name.len();
+          entries.insert(
+            name,
+            DirEntry {
+              path: &path[..len],
+              is_dir: true,
+            },
+          );
+        }
+        None => {
+          entries.entry(rest).or_insert(DirEntry {
+            path,
+            is_dir: false,
+          });
+        }
+      }
+    }
+
+    for empty_dir in &self.index.empty_dirs {
+      let Some(rest) = child_path(dir, empty_dir) else {
+        continue;
+      };
+
+      let name = rest.split('/').next().unwrap();
+      let len = empty_dir.len() - rest.len() + name.len();
+      entries.entry(name).or_insert(DirEntry {
+        path: &empty_dir[..len],
+        is_dir: true,
+      });
+    }
+
+    if entries.is_empty() && !dir.is_empty() {
+      if self
+        .index
+        .empty_dirs
+        .iter()
+        .any(|empty_dir| empty_dir == dir)
+      {
+        return Ok(Vec::new());
+      }
+
+      return match self.exists(dir) {
+        true => Err(FsError::NotADirectory),
+        false => Err(FsError::NotFound),
+      };
+    }
+
+    Ok(entries.into_values().collect())
+  }
+
+  /// Whether a path is a directory, which means it contains at least one file or is an empty
+  /// directory stored with [`Bundle::empty_dirs`](crate::Bundle::empty_dirs). The root
+  /// directory `""` always exists.
+  pub fn is_dir(&self, path: impl AsRef<str>) -> bool {
+    let Some(dir) = self.normalize_dir(path.as_ref()) else {
+      return false;
+    };
+    let dir = &*dir;
+    let contains = |path: &str| child_path(dir, path).is_some();
+    dir.is_empty()
+      || self
+        .index
+        .files
+        .with_prefix(self.archive_bytes, &dir_prefix(dir))
+        .any(|(path, _)| contains(path))
+      || self
+        .index
+        .empty_dirs
+        .iter()
+        .any(|empty_dir| empty_dir == dir || contains(empty_dir))
+  }
+
+  /// Paths of the directories without files, if the bundle was built with
+  /// [`Bundle::empty_dirs`](crate::Bundle::empty_dirs). Directories that only contain empty
+  /// directories are not included.
+  pub fn empty_dirs(&self) -> impl ExactSizeIterator<Item = &EmbeddedPath> {
+    let dirs = self.index.empty_dirs.iter();
+    dirs.map(|dir| EmbeddedPath::new_unchecked(dir))
+  }
+
+  /// Normalize a directory path like the paths of files, mapping paths without any components
+  /// like `.` to the root directory. Returns `None` if the path leaves the root.
+  fn normalize_dir<'a>(&self, path: &'a str) -> Option<Cow<'a, str>> {
+    let is_root = path
+      .split(['/', '\\'])
+      .all(|component| component.is_empty() || component == ".");
+    let path = match is_root {
+      true => Cow::Borrowed(""),
+      false => crate::path::normalize(path)?,
+    };
+
+    match self.index.case_insensitive {
+      true => Some(Cow::Owned(path.to_lowercase())),
+      false => Some(path),
+    }
+  }
+}
+
+/// The prefix of the paths of the files inside `dir`.
+fn dir_prefix(dir: &str) -> String {
+  match dir {
+    "" => String::new(),
+    dir => format!("{dir}/"),
+  }
+}
+
+/// The path of a file relative to `dir`, if it is inside it.
+fn child_path<'a>(dir: &str, path: &'a str) -> Option<&'a str> {
+  if dir.is_empty() {
+    return Some(path);
+  }
+
+  path.strip_prefix(dir)?.strip_prefix('/')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_read_dir() {
+    let fs = test_fs(&[
+      ("assets/icons/home.png", b""),
+      ("assets/icons/menu/open.png", b""),
+      ("assets/icons/back.png", b""),
+      ("assets/logo.png", b""),
+      ("readme.md", b""),
+    ]);
+
+    let entries = fs.read_dir("assets/icons").unwrap();
+    let names: Vec<_> = entries
+      .iter()
+      .map(|entry| (entry.path(), entry.file_name(), entry.is_dir()))
+      .collect();
+    assert_eq!(
+      names,
+      [
+        ("assets/icons/back.png", "back.png", false),
+        ("assets/icons/home.png", "home.png", false),
+        ("assets/icons/menu", "menu", true),
+      ]
+    );
+
+    let root: Vec<_> = fs
+      .read_dir("")
+      .unwrap()
+      .iter()
+      .map(DirEntry::path)
+      .collect();
+    assert_eq!(root, ["assets", "readme.md"]);
+    assert_eq!(fs.read_dir("assets/").unwrap().len(), 2);
+
+    // Directories are spelled like the paths of files
+    assert_eq!(fs.read_dir("./assets").unwrap().len(), 2);
+    assert_eq!(fs.read_dir("assets//icons").unwrap().len(), 3);
+    assert_eq!(fs.read_dir("assets\\icons").unwrap().len(), 3);
+    assert_eq!(fs.read_dir("assets/icons/menu/..").unwrap().len(), 3);
+    assert_eq!(fs.read_dir("./").unwrap().len(), 2);
+    assert!(fs.is_dir("assets\\icons\\menu"));
+    assert!(matches!(fs.read_dir("../assets"), Err(FsError::NotFound)));
+    assert!(!fs.is_dir("assets/../.."));
+
+    assert!(fs.is_dir("assets/icons/menu"));
+    assert!(fs.is_dir(""));
+    assert!(!fs.is_dir("assets/icon"));
+    assert!(!fs.is_dir("readme.md"));
+    assert!(matches!(
+      fs.read_dir("readme.md"),
+      Err(FsError::NotADirectory)
+    ));
+    assert!(matches!(fs.read_dir("missing"), Err(FsError::NotFound)));
+  }
+
+  #[cfg(feature = "std")]
+  #[test]
+  fn test_empty_dirs() {
+    use crate::archive::{ArchiveOptions, FileEntry};
+    use crate::tests::test_fs_with;
+
+    let options = ArchiveOptions {
+      empty_dirs: vec!["assets/empty".into(), "templates/new/placeholder".into()],
+      ..Default::default()
+    };
+
+    let fs = test_fs_with(&[FileEntry::new("assets/a.txt", "a")], &options);
+
+    let paths = |dir| -> Vec<_> {
+      fs.read_dir(dir)
+        .unwrap()
+        .iter()
+        .map(DirEntry::path)
+        .collect()
+    };
+    assert_eq!(paths("assets"), ["assets/a.txt", "assets/empty"]);
+    assert_eq!(paths(""), ["assets", "templates"]);
+    assert_eq!(paths("templates/new"), ["templates/new/placeholder"]);
+    assert!(paths("assets/empty").is_empty());
+
+    assert!(fs.is_dir("templates"));
+    assert!(fs.is_dir("templates/new/placeholder"));
+    assert!(!fs.is_dir("templates/new/placeholder/missing"));
+    assert!(!fs.exists("assets/empty"));
+    assert_eq!(fs.empty_dirs().len(), 2);
+  }
+}
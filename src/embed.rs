@@ -0,0 +1,131 @@
+//! Support for the [`rust_embed!`](crate::rust_embed) macro, exposing bundles through
+//! `rust_embed::RustEmbed`.
+
+use std::borrow::Cow;
+
+use sha2::{Digest, Sha256};
+
+pub use ::rust_embed::{EmbeddedFile, RustEmbed};
+
+use crate::IncludeFsInner;
+
+/// Implement `rust_embed::RustEmbed` for a unit struct backed by an embedded filesystem.
+///
+/// This allows passing include-fs bundles to libraries that accept `RustEmbed` types, like
+/// `utoipa-swagger-ui` or `poem`. Since `RustEmbed` only has associated functions, the
+/// filesystem has to be a `static`. Paths are relative to `root` if it is given, like they are
+/// relative to the embedded folder with `#[derive(RustEmbed)]`.
+///
+/// ```rust,ignore
+/// static ASSETS: IncludeFs = include_fs!("assets");
+///
+/// include_fs::rust_embed!(pub struct Assets = ASSETS, root = "assets");
+///
+/// let index = Assets::get("index.html").unwrap();
+/// ```
+///
+/// File data is borrowed from the archive, and the SHA-256 hash rust-embed expects is computed
+/// on every call to `get`. The creation time is always `None`.
+///
+/// rust-embed's `compression` feature adds a required method that can't be implemented for
+/// include-fs bundles, so it must not be enabled anywhere in the dependency graph.
+#[macro_export]
+macro_rules! rust_embed {
+  ($(#[$attr:meta])* $vis:vis struct $name:ident = $fs:path $(, root = $root:expr)? $(,)?) => {
+    $(#[$attr])*
+    $vis struct $name;
+
+    impl $crate::embed::RustEmbed for $name {
+      fn get(file_path: &str) -> ::core::option::Option<$crate::embed::EmbeddedFile> {
+        $crate::embed::__get(&$fs, $crate::rust_embed!(@root $($root)?), file_path)
+      }
+
+      fn iter() -> impl ::core::iter::Iterator<Item = ::std::borrow::Cow<'static, str>> + 'static {
+        $crate::embed::__iter(&$fs, $crate::rust_embed!(@root $($root)?))
+      }
+    }
+  };
+
+  (@root $root:expr) => { $root };
+  (@root) => { "" };
+}
+
+fn join(root: &str, path: &str) -> String {
+  match root.trim_matches('/') {
+    "" => path.to_string(),
+    root => format!("{root}/{path}"),
+  }
+}
+
+#[doc(hidden)]
+pub fn __get(fs: &'static IncludeFsInner, root: &str, path: &str) -> Option<EmbeddedFile> {
+  let path = join(root, path);
+  let data = fs.get(&path).ok()?;
+  let hash = Sha256::digest(data).into();
+  let last_modified = fs.last_modified(&path).ok().flatten();
+
+  Some(EmbeddedFile {
+    data: Cow::Borrowed(data),
+    metadata: ::rust_embed::utils::__rust_embed_metadata!(
+      hash,
+      last_modified,
+      None,
+      // Dropped by the macro unless rust-embed's `mime-guess` feature is enabled
+      fs.mime(&path).unwrap_or(crate::DEFAULT_MIME)
+    ),
+  })
+}
+
+#[doc(hidden)]
+pub fn __iter(
+  fs: &'static IncludeFsInner,
+  root: &str,
+) -> impl Iterator<Item = Cow<'static, str>> + 'static {
+  let prefix = join(root, "");
+  fs.list_paths()
+    .into_iter()
+    .filter_map(move |path| path.as_str().strip_prefix(prefix.as_str()))
+    .map(Cow::Borrowed)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::LazyLock;
+
+  use sha2::Digest;
+
+  use crate::IncludeFs;
+  use crate::tests::test_fs;
+
+  static ASSETS: IncludeFs = LazyLock::new(|| {
+    test_fs(&[
+      ("assets/index.html", b"<html></html>"),
+      ("assets/css/style.css", b"body {}"),
+      ("other.txt", b"other"),
+    ])
+  });
+
+  crate::rust_embed!(struct Assets = ASSETS, root = "assets/");
+  crate::rust_embed!(struct All = ASSETS);
+
+  #[test]
+  fn test_rust_embed() {
+    use ::rust_embed::RustEmbed;
+
+    let index = Assets::get("index.html").unwrap();
+    assert_eq!(index.data, &b"<html></html>"[..]);
+    assert_eq!(
+      index.metadata.sha256_hash(),
+      <[u8; 32]>::from(sha2::Sha256::digest(b"<html></html>"))
+    );
+    assert_eq!(index.metadata.last_modified(), None);
+    assert!(Assets::get("other.txt").is_none());
+    assert!(Assets::get("missing.txt").is_none());
+
+    let mut paths: Vec<_> = Assets::iter().collect();
+    paths.sort();
+    assert_eq!(paths, ["css/style.css", "index.html"]);
+    assert_eq!(All::iter().count(), 3);
+    assert_eq!(All::get("other.txt").unwrap().data, &b"other"[..]);
+  }
+}
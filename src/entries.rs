@@ -0,0 +1,83 @@
+use crate::files::FilesIter;
+use crate::{EmbeddedPath, FsEntry, FsError, IncludeFsInner};
+
+/// Iterator over the files of a filesystem, returned by [`IncludeFsInner::entries`].
+pub struct Entries<'a> {
+  fs: &'a IncludeFsInner,
+  files: FilesIter<'a>,
+}
+
+impl<'a> Iterator for Entries<'a> {
+  type Item = Result<(&'a EmbeddedPath, &'a FsEntry, &'a [u8]), FsError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (path, entry) = match self.files.next()? {
+      Some(file) => file,
+      None => return Some(Err(FsError::InvalidArchive)),
+    };
+
+    let data = match self.fs.passthrough(path) {
+      Ok(Some(data)) => Ok(&data[..data.len() - 1]),
+      Ok(None) => self.fs.data(entry).and_then(|data| {
+        if self.fs.verify_reads {
+          self.fs.verify_file(path, entry, data)?;
+        }
+
+        Ok(data)
+      }),
+      Err(err) => Err(err),
+    };
+
+    Some(data.map(|data| (EmbeddedPath::new_unchecked(path), entry, data)))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.files.size_hint()
+  }
+}
+
+impl ExactSizeIterator for Entries<'_> {}
+
+impl IncludeFsInner {
+  /// All files with their index entries and data, in no particular order.
+  ///
+  /// This walks the index directly, so it is cheaper than looking up every path returned by
+  /// [`list_paths`](Self::list_paths). Files that can't be read, such as corrupt entries, are
+  /// returned as errors instead of being skipped.
+  pub fn entries(&self) -> Entries<'_> {
+    Entries {
+      fs: self,
+      files: self.index.files.all(self.archive_bytes),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_entries() {
+    let fs = test_fs(&[("a.txt", b"a"), ("dir/b.txt", b"bb"), ("c.bin", b"")]);
+
+    let entries = fs.entries();
+    assert_eq!(entries.len(), 3);
+
+    let mut files: Vec<_> = entries
+      .map(|entry| {
+        let (path, entry, data) = entry.unwrap();
+        (path.as_str(), entry.size(), data)
+      })
+      .collect();
+    files.sort();
+
+    assert_eq!(
+      files,
+      [
+        ("a.txt", 1, &b"a"[..]),
+        ("c.bin", 0, &b""[..]),
+        ("dir/b.txt", 2, &b"bb"[..]),
+      ]
+    );
+  }
+}
@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::archive::HEADER_LEN;
+use crate::{
+  EmbeddedPath, FsEntry, FsError, IncludeFsInner, Index, parse_standalone_index, read_index,
+};
+
+/// An archive read from disk on demand, instead of being embedded in the binary.
+///
+/// Only the header is read when the archive is opened. File data is read when it is accessed,
+/// and optionally kept in a cache of bounded size, so archives much larger than the available
+/// memory can be used:
+///
+/// ```rust,ignore
+/// let pack = ArchiveFile::open("content/pack.embed_fs")?.cache_budget(256 * 1024 * 1024);
+/// let level = pack.read("levels/01.bin")?;
+/// ```
+///
+/// Encrypted archives and archives built with shared data are not supported.
+pub struct ArchiveFile {
+  file: Mutex<File>,
+  len: u64,
+  index: Index,
+  cache: Mutex<Cache>,
+  verify_reads: bool,
+}
+
+impl ArchiveFile {
+  /// Open an archive, reading its header.
+  pub fn open(path: impl AsRef<Path>) -> Result<Self, FsError> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let header = read_header(&mut reader).map_err(|err| match err.kind() {
+      io::ErrorKind::UnexpectedEof => FsError::InvalidArchive,
+      _ => FsError::Io(err),
+    })?;
+
+    let index = read_index(&header, true)?;
+    if let Some(name) = index.shared_data {
+      return Err(FsError::MissingSharedData(name));
+    }
+
+    if index.key_check.is_some() {
+      return Err(FsError::Encrypted);
+    }
+
+    Ok(Self {
+      file: Mutex::new(reader.into_inner()),
+      len,
+      index,
+      cache: Mutex::new(Cache::default()),
+      verify_reads: false,
+    })
+  }
+
+  /// Keep recently read files in memory, up to `bytes` in total. Files larger than the budget
+  /// are never cached. Disabled by default.
+  pub fn cache_budget(self, bytes: u64) -> Self {
+    self.cache.lock().unwrap().budget = bytes;
+    self
+  }
+
+  /// Check files against their checksums stored with [`Bundle::checksums`] when they are read
+  /// from disk, returning [`FsError::ChecksumMismatch`] for corrupt files. Disabled by default.
+  ///
+  /// [`Bundle::checksums`]: crate::Bundle::checksums
+  pub fn verify_reads(mut self, verify: bool) -> Self {
+    self.verify_reads = verify;
+    self
+  }
+
+  fn entry(&self, path: &str) -> Result<&FsEntry, FsError> {
+    // The index of an archive file is always a map, which doesn't need the archive bytes
+    self.index.entry(&[], path).ok_or(FsError::NotFound)
+  }
+
+  pub fn exists(&self, path: impl AsRef<str>) -> bool {
+    self.entry(path.as_ref()).is_ok()
+  }
+
+  /// The size of a file in bytes, without reading it.
+  pub fn size(&self, path: impl AsRef<str>) -> Result<u64, FsError> {
+    Ok(self.entry(path.as_ref())?.size)
+  }
+
+  /// Read the contents of a file, from the cache if possible.
+  pub fn read(&self, path: impl AsRef<str>) -> Result<Arc<[u8]>, FsError> {
+    let path = path.as_ref();
+    let entry = self.entry(path)?;
+    if let Some(data) = self.cache.lock().unwrap().get(path) {
+      return Ok(data);
+    }
+
+    // Check the bounds before allocating, in case the header is corrupt
+    let len = match entry.compression {
+      Some((_, stored_size)) => stored_size,
+      None => entry.size,
+    };
+
+    let end = entry.data_offset.checked_add(len);
+    if end.is_none_or(|end| end > self.len) {
+      return Err(FsError::InvalidArchive);
+    }
+
+    let mut data = vec![0; len as usize];
+    {
+      let mut file = self.file.lock().unwrap();
+      file.seek(SeekFrom::Start(entry.data_offset))?;
+      file.read_exact(&mut data)?;
+    }
+
+    if entry.compression.is_some() {
+      data = entry.decompress(&data)?;
+      data.truncate(entry.size as usize);
+    }
+
+    if self.verify_reads {
+      entry.verify_checksum(self.index.checksum_algorithm, path, &data)?;
+    }
+
+    let data: Arc<[u8]> = data.into();
+    self.cache.lock().unwrap().insert(path, data.clone());
+    Ok(data)
+  }
+
+  /// All paths in the archive, in no particular order.
+  pub fn list_paths(&self) -> Vec<&EmbeddedPath> {
+    self
+      .index
+      .files
+      .iter(&[])
+      .map(|(path, _)| EmbeddedPath::new_unchecked(path))
+      .collect()
+  }
+
+  /// Total size of the files currently in the cache.
+  pub fn cached_bytes(&self) -> u64 {
+    self.cache.lock().unwrap().bytes
+  }
+}
+
+impl IncludeFsInner {
+  /// Load an archive from a file instead of embedding it, for example one shipped next to the
+  /// executable because it is too large to embed.
+  ///
+  /// The whole archive is read into memory, where it stays for the rest of the program like an
+  /// embedded archive, so this is meant to be called once, into a `static` or at startup. Use
+  /// an [`ArchiveFile`] for archives that should be read on demand instead.
+  ///
+  /// ```rust,ignore
+  /// static ASSETS: LazyLock<IncludeFsInner> = LazyLock::new(|| {
+  ///   let exe = std::env::current_exe().unwrap();
+  ///   IncludeFsInner::open_path(exe.with_file_name("assets.embed_fs")).unwrap()
+  /// });
+  /// ```
+  ///
+  /// Encrypted archives and archives built with shared data are not supported.
+  pub fn open_path(path: impl AsRef<Path>) -> Result<Self, FsError> {
+    Self::from_reader(File::open(path)?)
+  }
+
+  /// Load an archive from a reader, see [`open_path`](Self::open_path).
+  pub fn from_reader(mut reader: impl Read) -> Result<Self, FsError> {
+    let mut archive_bytes = Vec::new();
+    reader.read_to_end(&mut archive_bytes)?;
+
+    // Only leak the data once it is known to be a readable archive
+    let index = parse_standalone_index(&archive_bytes)?;
+    Ok(Self::with_index(index, archive_bytes.leak()))
+  }
+
+  /// Map an archive file into memory instead of reading it, see [`open_path`](Self::open_path).
+  ///
+  /// Only the pages that are accessed are loaded, by the operating system, so archives larger
+  /// than the available memory can be opened instantly. [`get`](Self::get) returns slices into
+  /// the map. The map stays for the rest of the program.
+  ///
+  /// ```rust,ignore
+  /// // Safety: the archive is installed with the application and never modified
+  /// let assets = unsafe { IncludeFsInner::open_mmap(exe_dir.join("assets.embed_fs"))? };
+  /// ```
+  ///
+  /// # Safety
+  ///
+  /// The file must not be modified or truncated while the program is running, by this or any
+  /// other process. Changes to it would change data that is borrowed as immutable, and
+  /// truncating it makes accessing the missing pages crash the program.
+  #[cfg(feature = "mmap")]
+  pub unsafe fn open_mmap(path: impl AsRef<Path>) -> Result<Self, FsError> {
+    let file = File::open(path)?;
+    // Safety: upheld by the caller
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    let index = parse_standalone_index(&map)?;
+    let map: &'static memmap2::Mmap = Box::leak(Box::new(map));
+    let mut fs = Self::with_index(index, map);
+    fs.mmap = Some(map);
+    Ok(fs)
+  }
+}
+
+/// Read the header of an archive, leaving the reader at the start of the file data.
+fn read_header(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+  let mut header = Vec::new();
+  read_more(reader, &mut header, HEADER_LEN)?;
+
+  let file_count = u32::from_le_bytes(header[5..9].try_into().unwrap());
+  let attrs_len = u16::from_le_bytes([header[9], header[10]]);
+  read_more(reader, &mut header, attrs_len as usize)?;
+
+  for _ in 0..file_count {
+    read_more(reader, &mut header, 2)?;
+    let path_len = u16::from_le_bytes([header[header.len() - 2], header[header.len() - 1]]);
+
+    // Path, size, data offset and attribute length
+    read_more(reader, &mut header, path_len as usize + 8 + 8 + 2)?;
+    let attrs_len = u16::from_le_bytes([header[header.len() - 2], header[header.len() - 1]]);
+    read_more(reader, &mut header, attrs_len as usize)?;
+  }
+
+  Ok(header)
+}
+
+fn read_more(reader: &mut impl Read, buf: &mut Vec<u8>, len: usize) -> io::Result<()> {
+  let start = buf.len();
+  buf.resize(start + len, 0);
+  reader.read_exact(&mut buf[start..])
+}
+
+/// A cache of file contents, evicting the least recently used files to stay within its budget.
+#[derive(Default)]
+struct Cache {
+  budget: u64,
+  bytes: u64,
+  /// Contents by path, with the time they were last used.
+  files: HashMap<String, (Arc<[u8]>, u64)>,
+  clock: u64,
+}
+
+impl Cache {
+  fn get(&mut self, path: &str) -> Option<Arc<[u8]>> {
+    self.clock += 1;
+    let (data, last_used) = self.files.get_mut(path)?;
+    *last_used = self.clock;
+    Some(data.clone())
+  }
+
+  fn insert(&mut self, path: &str, data: Arc<[u8]>) {
+    let size = data.len() as u64;
+    if size > self.budget || self.files.contains_key(path) {
+      return;
+    }
+
+    while self.bytes + size > self.budget {
+      let Some(oldest) = self
+        .files
+        .iter()
+        .min_by_key(|(_, (_, last_used))| *last_used)
+        .map(|(path, _)| path.clone())
+      else {
+        break;
+      };
+
+      let (evicted, _) = self.files.remove(&oldest).unwrap();
+      self.bytes -= evicted.len() as u64;
+    }
+
+    self.clock += 1;
+    self.bytes += size;
+    self.files.insert(path.to_string(), (data, self.clock));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ChecksumAlgorithm;
+  use crate::archive::{ArchiveOptions, FileEntry, write_archive};
+  use std::io::Write;
+
+  #[test]
+  fn test_archive_file() {
+    let files = [
+      FileEntry::new("a.bin", vec![1; 100]),
+      FileEntry::new("b.bin", vec![2; 100]),
+      FileEntry::new("big.bin", vec![3; 1000]),
+      FileEntry::new("c.bin", vec![4; 100]),
+    ]
+    .map(|mut file| {
+      file.checksum = ChecksumAlgorithm::Crc32.compute(&file.data);
+      file
+    });
+
+    let options = ArchiveOptions {
+      lookup_table: true,
+      checksum: Some(ChecksumAlgorithm::Crc32),
+      ..Default::default()
+    };
+
+    let path = std::env::temp_dir().join(format!("include-fs-external-{}", std::process::id()));
+    let mut out = File::create(&path).unwrap();
+    write_archive(&files, &options, &mut out).unwrap();
+    out.flush().unwrap();
+
+    let archive = ArchiveFile::open(&path).unwrap().cache_budget(250);
+    assert_eq!(archive.list_paths().len(), 4);
+    assert_eq!(archive.size("big.bin").unwrap(), 1000);
+    assert!(matches!(archive.read("d.bin"), Err(FsError::NotFound)));
+
+    assert_eq!(&*archive.read("a.bin").unwrap(), &[1; 100]);
+    assert_eq!(&*archive.read("big.bin").unwrap(), &[3; 1000]);
+    assert_eq!(archive.cached_bytes(), 100);
+
+    // Reading a third small file evicts the least recently used one
+    archive.read("b.bin").unwrap();
+    archive.read("a.bin").unwrap();
+    assert_eq!(&*archive.read("c.bin").unwrap(), &[4; 100]);
+    assert_eq!(archive.cached_bytes(), 200);
+    assert!(!archive.cache.lock().unwrap().files.contains_key("b.bin"));
+    assert_eq!(&*archive.read("b.bin").unwrap(), &[2; 100]);
+
+    let archive = ArchiveFile::open(&path).unwrap().verify_reads(true);
+    assert!(archive.read("c.bin").is_ok());
+    let offset = archive.entry("c.bin").unwrap().data_offset;
+    out.seek(SeekFrom::Start(offset)).unwrap();
+    out.write_all(&[0]).unwrap();
+    out.flush().unwrap();
+    assert!(matches!(
+      archive.read("c.bin"),
+      Err(FsError::ChecksumMismatch(path)) if path == "c.bin"
+    ));
+
+    out.set_len(20).unwrap();
+    assert!(matches!(
+      ArchiveFile::open(&path),
+      Err(FsError::InvalidArchive)
+    ));
+
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn test_open_path() {
+    let files = [
+      FileEntry::new("assets/config.toml", "debug = true"),
+      FileEntry::new("assets/logo.png", "png"),
+    ];
+
+    let path = std::env::temp_dir().join(format!("include-fs-sidecar-{}", std::process::id()));
+    let mut out = File::create(&path).unwrap();
+    write_archive(&files, &ArchiveOptions::default(), &mut out).unwrap();
+    out.flush().unwrap();
+
+    let fs = IncludeFsInner::open_path(&path).unwrap();
+    assert_eq!(fs.get("assets/config.toml").unwrap(), b"debug = true");
+    assert_eq!(fs.read_dir("assets").unwrap().len(), 2);
+
+    let fs = IncludeFsInner::from_reader(File::open(&path).unwrap()).unwrap();
+    assert!(fs.exists("assets/logo.png"));
+
+    assert!(matches!(
+      IncludeFsInner::from_reader(&b"not an archive"[..]),
+      Err(FsError::InvalidArchive)
+    ));
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+      IncludeFsInner::open_path(&path),
+      Err(FsError::Io(_))
+    ));
+  }
+
+  #[cfg(feature = "mmap")]
+  #[test]
+  fn test_open_mmap() {
+    let files = [FileEntry::new("big.bin", vec![7; 10_000])];
+    let path = std::env::temp_dir().join(format!("include-fs-mmap-{}", std::process::id()));
+    let mut out = File::create(&path).unwrap();
+    write_archive(&files, &ArchiveOptions::default(), &mut out).unwrap();
+    out.flush().unwrap();
+
+    let fs = unsafe { IncludeFsInner::open_mmap(&path) }.unwrap();
+    fs.warm(["big.bin"]).unwrap();
+    assert!(matches!(fs.warm(["missing"]), Err(FsError::NotFound)));
+    assert_eq!(fs.get("big.bin").unwrap(), &[7; 10_000]);
+
+    let invalid = path.with_extension("invalid");
+    std::fs::write(&invalid, "not an archive").unwrap();
+    assert!(matches!(
+      unsafe { IncludeFsInner::open_mmap(&invalid) },
+      Err(FsError::InvalidArchive)
+    ));
+    std::fs::remove_file(&invalid).unwrap();
+  }
+}
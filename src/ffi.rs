@@ -0,0 +1,135 @@
+//! C interface for reading registered bundles.
+//!
+//! Bundles must be registered with [`register`](crate::register) before they can be opened from
+//! C. The matching header is at `include/include_fs.h` in the crate root.
+//!
+//! ```c
+//! const InfsFs *fs = infs_open("assets");
+//! size_t len;
+//! const uint8_t *data = infs_get(fs, "assets/logo.png", &len);
+//! ```
+
+use std::ffi::{CStr, c_char, c_void};
+
+use crate::{IncludeFsInner, all_bundles};
+
+/// Callback invoked by [`infs_list`] for every path. The path is not NUL-terminated.
+pub type InfsListCallback = extern "C" fn(path: *const u8, len: usize, user_data: *mut c_void);
+
+/// Open a registered bundle by name.
+///
+/// Returns NULL if no bundle with the given name is registered. The returned pointer is valid for
+/// the lifetime of the program and does not need to be freed.
+///
+/// # Safety
+///
+/// `name` must be a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn infs_open(name: *const c_char) -> *const IncludeFsInner {
+  if name.is_null() {
+    return std::ptr::null();
+  }
+
+  let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+    return std::ptr::null();
+  };
+
+  all_bundles()
+    .into_iter()
+    .find(|bundle| bundle.name == name)
+    .map_or(std::ptr::null(), |bundle| &**bundle.fs as *const _)
+}
+
+/// Get the contents of a file, writing its length to `out_len`.
+///
+/// Returns NULL if the file does not exist. The returned data is valid for the lifetime of the
+/// program and does not need to be freed.
+///
+/// # Safety
+///
+/// `fs` must be a pointer returned by [`infs_open`], `path` must be a valid NUL-terminated string
+/// and `out_len` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn infs_get(
+  fs: *const IncludeFsInner,
+  path: *const c_char,
+  out_len: *mut usize,
+) -> *const u8 {
+  if fs.is_null() || path.is_null() || out_len.is_null() {
+    return std::ptr::null();
+  }
+
+  let fs = unsafe { &*fs };
+  let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+    return std::ptr::null();
+  };
+
+  match fs.get(path) {
+    Ok(data) => {
+      unsafe { *out_len = data.len() };
+      data.as_ptr()
+    }
+    Err(_) => std::ptr::null(),
+  }
+}
+
+/// Call `callback` for every path in the bundle, returning the number of paths.
+///
+/// Returns 0 without listing anything if `callback` is NULL.
+///
+/// # Safety
+///
+/// `fs` must be a pointer returned by [`infs_open`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn infs_list(
+  fs: *const IncludeFsInner,
+  callback: Option<InfsListCallback>,
+  user_data: *mut c_void,
+) -> usize {
+  let Some(callback) = callback.filter(|_| !fs.is_null()) else {
+    return 0;
+  };
+
+  let paths = unsafe { &*fs }.list_paths();
+  for path in &paths {
+    callback(path.as_ptr(), path.len(), user_data);
+  }
+
+  paths.len()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+  use crate::{IncludeFs, register};
+  use std::sync::LazyLock;
+
+  static FFI: IncludeFs = LazyLock::new(|| test_fs(&[("ffi/a.txt", b"hello")]));
+
+  extern "C" fn collect(path: *const u8, len: usize, user_data: *mut c_void) {
+    let paths = unsafe { &mut *(user_data as *mut Vec<String>) };
+    let path = unsafe { std::slice::from_raw_parts(path, len) };
+    paths.push(String::from_utf8(path.to_vec()).unwrap());
+  }
+
+  #[test]
+  fn test_ffi() {
+    register("ffi-test", &FFI);
+
+    let fs = unsafe { infs_open(c"ffi-test".as_ptr()) };
+    assert!(!fs.is_null());
+    assert!(unsafe { infs_open(c"ffi-test-missing".as_ptr()) }.is_null());
+
+    let mut len = 0;
+    let data = unsafe { infs_get(fs, c"ffi/a.txt".as_ptr(), &mut len) };
+    assert_eq!(unsafe { std::slice::from_raw_parts(data, len) }, b"hello");
+    assert!(unsafe { infs_get(fs, c"ffi/b.txt".as_ptr(), &mut len) }.is_null());
+
+    let mut paths = Vec::<String>::new();
+    let user_data = &mut paths as *mut _ as *mut c_void;
+    assert_eq!(unsafe { infs_list(fs, Some(collect), user_data) }, 1);
+    assert_eq!(paths, ["ffi/a.txt"]);
+    assert_eq!(unsafe { infs_list(fs, None, user_data) }, 0);
+  }
+}
@@ -0,0 +1,205 @@
+//! Lookup of the file entries of an index, either from a map or sorted list built while parsing
+//! the header, or through the lookup table stored with [`Bundle::lookup_table`].
+//!
+//! [`Bundle::lookup_table`]: crate::Bundle::lookup_table
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+use core::slice;
+#[cfg(not(feature = "std"))]
+use hashbrown::{HashMap, hash_map};
+#[cfg(feature = "std")]
+use std::collections::{HashMap, hash_map};
+
+use crate::archive::{BUCKET_SIZE, Fnv1a};
+use crate::sync::OnceLock;
+use crate::{FsEntry, FsError, parse_entry, read_u32};
+
+pub(crate) enum Files {
+  Map(HashMap<String, FsEntry>),
+  /// Entries sorted by path, see [`Bundle::sorted_index`].
+  ///
+  /// [`Bundle::sorted_index`]: crate::Bundle::sorted_index
+  Sorted(Vec<(String, FsEntry)>),
+  Table(LookupTable),
+}
+
+impl Files {
+  /// Look up the entry for a path. The archive has to be the one the index was read from.
+  pub fn get(&self, archive_bytes: &[u8], path: &str) -> Option<&FsEntry> {
+    match self {
+      Files::Map(files) => files.get(path),
+      Files::Sorted(files) => files
+        .binary_search_by(|(entry_path, _)| entry_path.as_str().cmp(path))
+        .ok()
+        .map(|i| &files[i].1),
+      Files::Table(table) => table.get(archive_bytes, path),
+    }
+  }
+
+  /// All paths with their entries, in no particular order. Invalid entries are skipped.
+  pub fn iter<'a>(
+    &'a self,
+    archive_bytes: &'a [u8],
+  ) -> impl Iterator<Item = (&'a str, &'a FsEntry)> + 'a {
+    self.all(archive_bytes).flatten()
+  }
+
+  /// All paths starting with `prefix` with their entries, in no particular order. Only sorted
+  /// indexes avoid visiting the other paths.
+  pub fn with_prefix<'a>(
+    &'a self,
+    archive_bytes: &'a [u8],
+    prefix: &str,
+  ) -> impl Iterator<Item = (&'a str, &'a FsEntry)> {
+    let files = match self {
+      Files::Sorted(files) => {
+        let start = files.partition_point(|(path, _)| path.as_str() < prefix);
+        let len = files[start..].partition_point(|(path, _)| path.starts_with(prefix));
+        FilesIter::Sorted(files[start..start + len].iter())
+      }
+      _ => self.all(archive_bytes),
+    };
+
+    files
+      .flatten()
+      .filter(move |(path, _)| path.starts_with(prefix))
+  }
+
+  /// All paths with their entries, in no particular order, or `None` for invalid entries of a
+  /// lookup table.
+  pub fn all<'a>(&'a self, archive_bytes: &'a [u8]) -> FilesIter<'a> {
+    match self {
+      Files::Map(files) => FilesIter::Map(files.iter()),
+      Files::Sorted(files) => FilesIter::Sorted(files.iter()),
+      Files::Table(table) => FilesIter::Table {
+        table,
+        archive_bytes,
+        indices: 0..table.entries.len(),
+      },
+    }
+  }
+
+  #[cfg(any(feature = "tracing", feature = "pyo3"))]
+  pub fn len(&self) -> usize {
+    match self {
+      Files::Map(files) => files.len(),
+      Files::Sorted(files) => files.len(),
+      Files::Table(table) => table.entries.len(),
+    }
+  }
+}
+
+/// Iterator returned by [`Files::all`].
+pub(crate) enum FilesIter<'a> {
+  Map(hash_map::Iter<'a, String, FsEntry>),
+  Sorted(slice::Iter<'a, (String, FsEntry)>),
+  Table {
+    table: &'a LookupTable,
+    archive_bytes: &'a [u8],
+    indices: Range<usize>,
+  },
+}
+
+impl<'a> Iterator for FilesIter<'a> {
+  type Item = Option<(&'a str, &'a FsEntry)>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match self {
+      FilesIter::Map(files) => files
+        .next()
+        .map(|(path, entry)| Some((path.as_str(), entry))),
+      FilesIter::Sorted(files) => files
+        .next()
+        .map(|(path, entry)| Some((path.as_str(), entry))),
+      FilesIter::Table {
+        table,
+        archive_bytes,
+        indices,
+      } => indices.next().map(|i| table.entry(archive_bytes, i)),
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    match self {
+      FilesIter::Map(files) => files.size_hint(),
+      FilesIter::Sorted(files) => files.size_hint(),
+      FilesIter::Table { indices, .. } => indices.size_hint(),
+    }
+  }
+}
+
+impl ExactSizeIterator for FilesIter<'_> {}
+
+/// A lookup table stored in the archive. Entries are parsed the first time they are accessed.
+pub(crate) struct LookupTable {
+  buckets_offset: usize,
+  bucket_count: usize,
+  entry_offsets: usize,
+  entries: Vec<OnceLock<Option<(String, FsEntry)>>>,
+}
+
+impl LookupTable {
+  pub fn new(
+    archive_bytes: &[u8],
+    offset: usize,
+    bucket_count: usize,
+    file_count: usize,
+  ) -> Result<Self, FsError> {
+    let entry_offsets = bucket_count
+      .checked_mul(BUCKET_SIZE)
+      .and_then(|size| offset.checked_add(size))
+      .ok_or(FsError::InvalidArchive)?;
+    let end = file_count
+      .checked_mul(4)
+      .and_then(|size| entry_offsets.checked_add(size))
+      .ok_or(FsError::InvalidArchive)?;
+
+    if bucket_count == 0 || end > archive_bytes.len() {
+      return Err(FsError::InvalidArchive);
+    }
+
+    Ok(Self {
+      buckets_offset: offset,
+      bucket_count,
+      entry_offsets,
+      entries: (0..file_count).map(|_| OnceLock::new()).collect(),
+    })
+  }
+
+  fn get(&self, archive_bytes: &[u8], path: &str) -> Option<&FsEntry> {
+    let hash = Fnv1a::hash(path.as_bytes());
+    let mut bucket = (hash % self.bucket_count as u64) as usize;
+
+    for _ in 0..self.bucket_count {
+      let offset = self.buckets_offset + bucket * BUCKET_SIZE;
+      let index = read_u32(archive_bytes, offset + 4)? as usize;
+      if index == 0 {
+        return None;
+      }
+
+      if read_u32(archive_bytes, offset)? == hash as u32 {
+        match self.entry(archive_bytes, index - 1) {
+          Some((entry_path, entry)) if entry_path == path => return Some(entry),
+          _ => {}
+        }
+      }
+
+      bucket = (bucket + 1) % self.bucket_count;
+    }
+
+    None
+  }
+
+  /// The entry at `index` in the header, or `None` if it is out of bounds or invalid.
+  fn entry<'a>(&'a self, archive_bytes: &[u8], index: usize) -> Option<(&'a str, &'a FsEntry)> {
+    let entry = self.entries.get(index)?.get_or_init(|| {
+      let offset = read_u32(archive_bytes, self.entry_offsets + index * 4)? as usize;
+      let (path, entry, _) = parse_entry(archive_bytes, offset).ok()?;
+      Some((path, entry))
+    });
+
+    entry.as_ref().map(|(path, entry)| (path.as_str(), entry))
+  }
+}
@@ -0,0 +1,404 @@
+//! Content-hashed file names, see [`Bundle::fingerprint`].
+//!
+//! [`Bundle::fingerprint`]: crate::Bundle::fingerprint
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use globset::GlobSet;
+
+use crate::archive::FileEntry;
+use crate::{EmbeddedPath, IncludeFsInner};
+
+/// Number of hex digits of the content hash in fingerprinted file names.
+const HASH_LEN: usize = 8;
+
+/// HTML attributes containing URLs.
+const URL_ATTRIBUTES: [&str; 4] = ["src", "href", "poster", "srcset"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+  Html,
+  Css,
+}
+
+impl Kind {
+  fn of(path: &str) -> Option<Self> {
+    let (_, extension) = path.rsplit_once('.')?;
+    match extension.to_ascii_lowercase().as_str() {
+      "html" | "htm" => Some(Kind::Html),
+      "css" => Some(Kind::Css),
+      _ => None,
+    }
+  }
+}
+
+/// Rename the files matching `globs` to fingerprinted names, and rewrite the references to them
+/// in HTML and CSS files. Root-relative URLs are resolved against the directory `root`.
+pub(crate) fn fingerprint(files: &mut [FileEntry], globs: &GlobSet, root: &str) {
+  let targets: HashSet<String> = files
+    .iter()
+    .filter(|file| globs.is_match(&file.path))
+    .map(|file| file.path.clone())
+    .collect();
+
+  let mut renamed = HashMap::new();
+  let mut pending: Vec<usize> = (0..files.len())
+    .filter(|&i| targets.contains(&files[i].path))
+    .collect();
+
+  // Files referencing other fingerprinted files are rewritten before they are hashed, so they
+  // have to wait until everything they reference has been renamed
+  while !pending.is_empty() {
+    let (mut ready, blocked): (Vec<usize>, Vec<usize>) = pending.iter().partition(|&&i| {
+      references_of(&files[i], root)
+        .iter()
+        .all(|path| !targets.contains(path) || renamed.contains_key(path) || *path == files[i].path)
+    });
+
+    // Break reference cycles by renaming the remaining files with what is known so far
+    if ready.is_empty() {
+      ready = blocked;
+      pending = Vec::new();
+    } else {
+      pending = blocked;
+    }
+
+    for i in ready {
+      let file = &mut files[i];
+      rewrite_file(file, root, &renamed);
+
+      let path = fingerprinted_path(&file.path, &file.data);
+      let original = std::mem::replace(&mut file.path, path.clone());
+      renamed.insert(original.clone(), path);
+      file.original_path = Some(original);
+    }
+  }
+
+  for file in files.iter_mut().filter(|file| file.original_path.is_none()) {
+    rewrite_file(file, root, &renamed);
+  }
+}
+
+/// `css/app.css` becomes `css/app.1a2b3c4d.css`.
+fn fingerprinted_path(path: &str, data: &[u8]) -> String {
+  let hash = format!("{:016x}", xxhash_rust::xxh64::xxh64(data, 0));
+  let hash = &hash[..HASH_LEN];
+
+  let name_start = path.rfind('/').map_or(0, |i| i + 1);
+  match path[name_start..].rfind('.') {
+    Some(dot) if dot > 0 => {
+      let dot = name_start + dot;
+      format!("{}.{hash}{}", &path[..dot], &path[dot..])
+    }
+    _ => format!("{path}.{hash}"),
+  }
+}
+
+/// The archive paths referenced by an HTML or CSS file.
+fn references_of(file: &FileEntry, root: &str) -> Vec<String> {
+  let (Some(kind), Ok(source)) = (Kind::of(&file.path), std::str::from_utf8(&file.data)) else {
+    return Vec::new();
+  };
+
+  let dir = parent(&file.path);
+  references(source, kind)
+    .into_iter()
+    .filter_map(|range| resolve(&source[range], dir, root))
+    .collect()
+}
+
+/// Rewrite the references to renamed files in an HTML or CSS file.
+fn rewrite_file(file: &mut FileEntry, root: &str, renamed: &HashMap<String, String>) {
+  let (Some(kind), Ok(source)) = (Kind::of(&file.path), std::str::from_utf8(&file.data)) else {
+    return;
+  };
+
+  let dir = parent(&file.path);
+  let mut rewritten = String::with_capacity(source.len());
+  let mut last = 0;
+  for range in references(source, kind) {
+    let url = &source[range.clone()];
+    let Some(path) = resolve(url, dir, root).and_then(|path| renamed.get(&path)) else {
+      continue;
+    };
+
+    // Only the file name changes, so relative URLs stay relative
+    let path_end = url.find(['?', '#']).unwrap_or(url.len());
+    let name_start = url[..path_end].rfind('/').map_or(0, |i| i + 1);
+    let name = path.rsplit('/').next().unwrap_or(path);
+
+    rewritten.push_str(&source[last..range.start]);
+    rewritten.push_str(&url[..name_start]);
+    rewritten.push_str(name);
+    rewritten.push_str(&url[path_end..]);
+    last = range.end;
+  }
+
+  if last > 0 {
+    rewritten.push_str(&source[last..]);
+    file.data = rewritten.into_bytes();
+  }
+}
+
+fn parent(path: &str) -> &str {
+  path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Resolve a URL referenced from a file in `dir` to an archive path. Returns `None` for
+/// external URLs and URLs leaving the root.
+fn resolve(url: &str, dir: &str, root: &str) -> Option<String> {
+  let path = url.split(['?', '#']).next()?;
+  let has_scheme = path
+    .split_once(':')
+    .is_some_and(|(scheme, _)| !scheme.contains('/'));
+  if path.is_empty() || path.starts_with("//") || has_scheme {
+    return None;
+  }
+
+  let (base, path) = match path.strip_prefix('/') {
+    Some(path) => (root, path),
+    None => (dir, path),
+  };
+
+  let mut segments: Vec<_> = base.split('/').filter(|s| !s.is_empty()).collect();
+  for segment in path.split('/') {
+    match segment {
+      "" | "." => {}
+      ".." => {
+        segments.pop()?;
+      }
+      segment => segments.push(segment),
+    }
+  }
+
+  Some(segments.join("/"))
+}
+
+/// Byte ranges of the URLs in an HTML or CSS source, sorted and non-overlapping.
+fn references(source: &str, kind: Kind) -> Vec<Range<usize>> {
+  let lower = source.to_ascii_lowercase();
+  let mut ranges = Vec::new();
+
+  // CSS can also appear in HTML, in style elements and attributes
+  for (start, _) in lower.match_indices("url(") {
+    ranges.extend(value_at(source, start + 4, &[')']));
+  }
+
+  for (start, _) in lower.match_indices("@import") {
+    let value = value_at(source, start + 7, &[';']);
+    ranges.extend(value.filter(|range| !lower[range.clone()].starts_with("url(")));
+  }
+
+  if kind == Kind::Html {
+    for attribute in URL_ATTRIBUTES {
+      for (start, _) in lower.match_indices(attribute) {
+        let preceded_by_space = lower[..start]
+          .bytes()
+          .next_back()
+          .is_some_and(|c| c.is_ascii_whitespace());
+        let rest = lower[start + attribute.len()..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+          continue;
+        }
+
+        let value_start = lower.len() - rest.len() + 1;
+        let Some(value) = value_at(source, value_start, &['>']) else {
+          continue;
+        };
+
+        if attribute == "srcset" {
+          ranges.extend(srcset_urls(source, value));
+        } else {
+          ranges.push(value);
+        }
+      }
+    }
+  }
+
+  ranges.sort_by_key(|range| range.start);
+  let mut end = 0;
+  ranges.retain(|range| {
+    let keep = range.start >= end;
+    end = end.max(range.end);
+    keep
+  });
+
+  ranges
+}
+
+/// The range of a quoted or unquoted value starting at `start` after optional whitespace. Unquoted
+/// values end at whitespace or any of `terminators`.
+fn value_at(source: &str, start: usize, terminators: &[char]) -> Option<Range<usize>> {
+  let rest = &source[start..];
+  let trimmed = rest.trim_start();
+  let start = start + rest.len() - trimmed.len();
+
+  let range = match trimmed.chars().next()? {
+    quote @ ('"' | '\'') => {
+      let len = trimmed[1..].find(quote)?;
+      start + 1..start + 1 + len
+    }
+    _ => {
+      let len = trimmed
+        .find(|c: char| c.is_whitespace() || terminators.contains(&c))
+        .unwrap_or(trimmed.len());
+      start..start + len
+    }
+  };
+
+  (!range.is_empty()).then_some(range)
+}
+
+/// The URLs of the candidates in a `srcset` value, like `logo.png 1x, logo@2x.png 2x`.
+fn srcset_urls(source: &str, value: Range<usize>) -> Vec<Range<usize>> {
+  let mut urls = Vec::new();
+  let mut offset = value.start;
+  for candidate in source[value].split(',') {
+    let trimmed = candidate.trim_start();
+    let start = offset + candidate.len() - trimmed.len();
+    let len = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    if len > 0 {
+      urls.push(start..start + len);
+    }
+
+    offset += candidate.len() + 1;
+  }
+
+  urls
+}
+
+impl IncludeFsInner {
+  /// The path a file was renamed to by [`Bundle::fingerprint`], given its original path.
+  ///
+  /// This is meant for templates and other places that construct asset URLs at runtime.
+  /// Returns `None` if the file was not fingerprinted.
+  ///
+  /// [`Bundle::fingerprint`]: crate::Bundle::fingerprint
+  pub fn fingerprinted_path(&self, path: impl AsRef<str>) -> Option<&EmbeddedPath> {
+    let fingerprints = self.index.fingerprints.get_or_init(|| {
+      self
+        .index
+        .files
+        .iter(self.archive_bytes)
+        .filter_map(|(path, entry)| Some((entry.original_path.clone()?, path.to_string())))
+        .collect()
+    });
+
+    let path = path.as_ref();
+    let path = match self.index.case_insensitive {
+      true => fingerprints.get(&path.to_lowercase()),
+      false => fingerprints.get(path),
+    };
+
+    path.map(|path| EmbeddedPath::new_unchecked(path))
+  }
+
+  /// Whether a file has a fingerprinted name, so its contents never change and it can be served
+  /// with [`http::IMMUTABLE`](crate::http::IMMUTABLE).
+  pub fn is_fingerprinted(&self, path: impl AsRef<str>) -> bool {
+    self
+      .entry(path.as_ref())
+      .is_ok_and(|entry| entry.original_path.is_some())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::archive::ArchiveOptions;
+  use crate::tests::test_fs_with;
+  use globset::{Glob, GlobSetBuilder};
+
+  fn globs(globs: &[&str]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+      builder.add(Glob::new(glob).unwrap());
+    }
+
+    builder.build().unwrap()
+  }
+
+  #[test]
+  fn test_fingerprint() {
+    let mut files = vec![
+      FileEntry::new(
+        "dist/index.html",
+        r#"<link rel="stylesheet" href="css/app.css?v=1">
+<img data-src="img/logo.png" src=img/logo.png srcset="img/logo.png 1x, /img/logo@2x.png 2x">
+<a href="https://example.com/img/logo.png">"#,
+      ),
+      FileEntry::new(
+        "dist/css/app.css",
+        "@import 'base.css'; body { background: url(\"../img/logo.png\"); }",
+      ),
+      FileEntry::new("dist/css/base.css", "p {}"),
+      FileEntry::new("dist/img/logo.png", "png"),
+      FileEntry::new("dist/img/logo@2x.png", "png2x"),
+    ];
+
+    fingerprint(&mut files, &globs(&["**/*.css", "**/*.png"]), "dist");
+
+    let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+    let logo = fingerprinted_path("logo.png", b"png");
+    let logo2x = fingerprinted_path("logo@2x.png", b"png2x");
+    assert_eq!(paths[0], "dist/index.html");
+    assert_eq!(paths[2], fingerprinted_path("dist/css/base.css", b"p {}"));
+    assert_eq!(paths[3], format!("dist/img/{logo}"));
+    assert_eq!(files[3].original_path.as_deref(), Some("dist/img/logo.png"));
+
+    let base = paths[2].rsplit('/').next().unwrap();
+    let css = format!("@import '{base}'; body {{ background: url(\"../img/{logo}\"); }}");
+    assert_eq!(files[1].data, css.as_bytes());
+    assert_eq!(
+      paths[1],
+      fingerprinted_path("dist/css/app.css", css.as_bytes())
+    );
+
+    let app = paths[1].rsplit('/').next().unwrap();
+    let html = format!(
+      r#"<link rel="stylesheet" href="css/{app}?v=1">
+<img data-src="img/logo.png" src=img/{logo} srcset="img/{logo} 1x, /img/{logo2x} 2x">
+<a href="https://example.com/img/logo.png">"#
+    );
+    assert_eq!(std::str::from_utf8(&files[0].data).unwrap(), html);
+  }
+
+  #[test]
+  fn test_runtime_lookup() {
+    let mut files = vec![
+      FileEntry::new("app.js", "js"),
+      FileEntry::new("index.html", "<script src=\"/app.js\"></script>"),
+    ];
+    fingerprint(&mut files, &globs(&["*.js"]), "");
+
+    let fs = test_fs_with(&files, &ArchiveOptions::default());
+
+    let path = fs.fingerprinted_path("app.js").unwrap();
+    assert_eq!(path.as_str(), fingerprinted_path("app.js", b"js"));
+    assert_eq!(fs.get(path).unwrap(), b"js");
+    assert!(fs.is_fingerprinted(path));
+    assert!(!fs.is_fingerprinted("index.html"));
+    assert_eq!(fs.fingerprinted_path("index.html"), None);
+    assert!(!fs.exists("app.js"));
+  }
+
+  #[test]
+  fn test_fingerprinted_path() {
+    assert_eq!(fingerprinted_path("a/b.min.js", b""), "a/b.min.ef46db37.js");
+    assert_eq!(
+      fingerprinted_path("a.b/LICENSE", b""),
+      "a.b/LICENSE.ef46db37"
+    );
+    assert_eq!(fingerprinted_path(".env", b""), ".env.ef46db37");
+
+    assert_eq!(resolve("../a.png", "x/y", ""), Some("x/a.png".to_string()));
+    assert_eq!(
+      resolve("/a.png#top", "x/y", "root"),
+      Some("root/a.png".to_string())
+    );
+    assert_eq!(resolve("../../../a.png", "x/y", ""), None);
+    assert_eq!(resolve("data:image/png;base64,AAAA", "", ""), None);
+    assert_eq!(resolve("//cdn.example.com/a.png", "", ""), None);
+  }
+}
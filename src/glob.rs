@@ -0,0 +1,67 @@
+use globset::GlobBuilder;
+
+use crate::{EmbeddedPath, FsError, IncludeFsInner};
+
+impl IncludeFsInner {
+  /// All paths matching a glob pattern like `assets/**/*.png`, in no particular order.
+  ///
+  /// `*` and `?` don't match `/`, while `**` matches any number of directories. Patterns are
+  /// matched case-insensitively for case-insensitive archives. Returns [`FsError::InvalidGlob`]
+  /// if the pattern is invalid.
+  pub fn glob(&self, pattern: &str) -> Result<impl Iterator<Item = &EmbeddedPath>, FsError> {
+    let matcher = GlobBuilder::new(pattern)
+      .literal_separator(true)
+      .case_insensitive(self.index.case_insensitive)
+      .build()?
+      .compile_matcher();
+
+    Ok(
+      self
+        .index
+        .files
+        .iter(self.archive_bytes)
+        .filter(move |(path, _)| matcher.is_match(path))
+        .map(|(path, _)| EmbeddedPath::new_unchecked(path)),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_glob() {
+    let fs = test_fs(&[
+      ("assets/logo.png", b""),
+      ("assets/icons/home.png", b""),
+      ("assets/icons/home.svg", b""),
+      ("assets/style.css", b""),
+      ("readme.png", b""),
+    ]);
+
+    let glob = |pattern| {
+      let mut paths: Vec<_> = fs
+        .glob(pattern)
+        .unwrap()
+        .map(EmbeddedPath::as_str)
+        .collect();
+      paths.sort();
+      paths
+    };
+
+    assert_eq!(
+      glob("assets/**/*.png"),
+      ["assets/icons/home.png", "assets/logo.png"]
+    );
+    assert_eq!(glob("assets/*.png"), ["assets/logo.png"]);
+    assert_eq!(
+      glob("**/home.*"),
+      ["assets/icons/home.png", "assets/icons/home.svg"]
+    );
+    assert_eq!(glob("*.{css,png}"), ["readme.png"]);
+    assert!(glob("*.txt").is_empty());
+    assert!(matches!(fs.glob("assets/[a"), Err(FsError::InvalidGlob(_))));
+  }
+}
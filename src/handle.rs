@@ -0,0 +1,400 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Take};
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
+
+use crate::{Compression, FsError, IncludeFsInner};
+
+/// A file opened with [`IncludeFsInner::open`], implementing [`Read`], [`BufRead`] and
+/// [`Seek`] over its data.
+///
+/// This is for libraries that take readers instead of slices, like archive, image or audio
+/// decoders. Reading never copies the data out of the archive unless it is encrypted, in which
+/// case the handle reads the cached decrypted data. Compressed files are decompressed
+/// incrementally while reading, so large files can be streamed without holding all of their
+/// data in memory. Seeking backwards in a compressed file restarts decompression from the
+/// start, and seeking forwards decompresses the skipped data.
+///
+/// With the `tokio` feature, it also implements `tokio`'s `AsyncRead`, `AsyncBufRead` and
+/// `AsyncSeek`, see [`IncludeFsInner::open_async`].
+pub struct EmbeddedFile<'a> {
+  inner: Inner<'a>,
+}
+
+enum Inner<'a> {
+  Slice(Cursor<&'a [u8]>),
+  Stream(Stream<'a>),
+}
+
+/// The decompressed data of a file, decoded as it is read.
+struct Stream<'a> {
+  compression: Compression,
+  stored: &'a [u8],
+  size: u64,
+  pos: u64,
+  decoder: BufReader<Take<Box<dyn Read + 'a>>>,
+}
+
+impl<'a> Stream<'a> {
+  fn new(compression: Compression, stored: &'a [u8], size: u64) -> Result<Self, FsError> {
+    Ok(Self {
+      compression,
+      stored,
+      size,
+      pos: 0,
+      decoder: BufReader::new(compression.decoder(stored)?.take(size)),
+    })
+  }
+
+  /// Continue decoding from `pos`, restarting from the start if it is behind the current
+  /// position.
+  fn seek_to(&mut self, pos: u64) -> io::Result<()> {
+    if pos < self.pos {
+      *self = Stream::new(self.compression, self.stored, self.size).map_err(io::Error::other)?;
+    }
+
+    let skip = pos.min(self.size).saturating_sub(self.pos);
+    if skip > 0 {
+      let skipped = io::copy(&mut (&mut *self).take(skip), &mut io::sink())?;
+      if skipped != skip {
+        return Err(truncated());
+      }
+    }
+
+    self.pos = pos;
+    Ok(())
+  }
+}
+
+impl Read for Stream<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let available = self.fill_buf()?;
+    let len = available.len().min(buf.len());
+    buf[..len].copy_from_slice(&available[..len]);
+    self.consume(len);
+    Ok(len)
+  }
+}
+
+impl BufRead for Stream<'_> {
+  fn fill_buf(&mut self) -> io::Result<&[u8]> {
+    if self.pos >= self.size {
+      return Ok(&[]);
+    }
+
+    let available = self.decoder.fill_buf().map_err(|_| truncated())?;
+    if available.is_empty() {
+      return Err(truncated());
+    }
+
+    Ok(available)
+  }
+
+  fn consume(&mut self, amount: usize) {
+    self.decoder.consume(amount);
+    self.pos += amount as u64;
+  }
+}
+
+/// The error for compressed data that doesn't decompress to the size of the file.
+fn truncated() -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, FsError::InvalidArchive)
+}
+
+impl fmt::Debug for EmbeddedFile<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("EmbeddedFile")
+      .field("len", &self.len())
+      .field("position", &self.position())
+      .finish_non_exhaustive()
+  }
+}
+
+impl<'a> EmbeddedFile<'a> {
+  /// The size of the file in bytes.
+  pub fn len(&self) -> u64 {
+    match &self.inner {
+      Inner::Slice(cursor) => cursor.get_ref().len() as u64,
+      Inner::Stream(stream) => stream.size,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// The whole data of the file, independent of the current position, or `None` if the file is
+  /// compressed and decompressed while reading.
+  pub fn data(&self) -> Option<&'a [u8]> {
+    match &self.inner {
+      Inner::Slice(cursor) => Some(cursor.get_ref()),
+      Inner::Stream(_) => None,
+    }
+  }
+
+  fn position(&self) -> u64 {
+    match &self.inner {
+      Inner::Slice(cursor) => cursor.position(),
+      Inner::Stream(stream) => stream.pos,
+    }
+  }
+}
+
+impl Read for EmbeddedFile<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    match &mut self.inner {
+      Inner::Slice(cursor) => cursor.read(buf),
+      Inner::Stream(stream) => stream.read(buf),
+    }
+  }
+}
+
+impl BufRead for EmbeddedFile<'_> {
+  fn fill_buf(&mut self) -> io::Result<&[u8]> {
+    match &mut self.inner {
+      Inner::Slice(cursor) => cursor.fill_buf(),
+      Inner::Stream(stream) => stream.fill_buf(),
+    }
+  }
+
+  fn consume(&mut self, amount: usize) {
+    match &mut self.inner {
+      Inner::Slice(cursor) => cursor.consume(amount),
+      Inner::Stream(stream) => stream.consume(amount),
+    }
+  }
+}
+
+impl Seek for EmbeddedFile<'_> {
+  fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+    let stream = match &mut self.inner {
+      Inner::Slice(cursor) => return cursor.seek(pos),
+      Inner::Stream(stream) => stream,
+    };
+
+    let target = match pos {
+      SeekFrom::Start(offset) => Some(offset),
+      SeekFrom::End(offset) => stream.size.checked_add_signed(offset),
+      SeekFrom::Current(offset) => stream.pos.checked_add_signed(offset),
+    };
+
+    let target = target.ok_or_else(|| {
+      io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "invalid seek to a negative or overflowing position",
+      )
+    })?;
+
+    stream.seek_to(target)?;
+    Ok(target)
+  }
+
+  fn stream_position(&mut self) -> io::Result<u64> {
+    Ok(self.position())
+  }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncRead for EmbeddedFile<'_> {
+  fn poll_read(
+    self: Pin<&mut Self>,
+    _cx: &mut Context<'_>,
+    buf: &mut tokio::io::ReadBuf<'_>,
+  ) -> Poll<io::Result<()>> {
+    let len = self.get_mut().read(buf.initialize_unfilled())?;
+    buf.advance(len);
+    Poll::Ready(Ok(()))
+  }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncBufRead for EmbeddedFile<'_> {
+  fn poll_fill_buf(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+    Poll::Ready(self.get_mut().fill_buf())
+  }
+
+  fn consume(self: Pin<&mut Self>, amount: usize) {
+    BufRead::consume(self.get_mut(), amount);
+  }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncSeek for EmbeddedFile<'_> {
+  fn start_seek(self: Pin<&mut Self>, pos: SeekFrom) -> io::Result<()> {
+    self.get_mut().seek(pos).map(|_| ())
+  }
+
+  fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+    Poll::Ready(Ok(self.position()))
+  }
+}
+
+impl IncludeFsInner {
+  /// Open a file for reading through [`Read`] and [`Seek`].
+  ///
+  /// Compressed files are decompressed while reading, unless their data was already
+  /// decompressed by [`get`](Self::get) or reads are verified with
+  /// [`verify_reads`](Self::verify_reads), which needs all of the data.
+  ///
+  /// ```rust,ignore
+  /// static ASSETS: IncludeFs = include_fs!("assets");
+  ///
+  /// let image = image::load(
+  ///   ASSETS.open("assets/logo.png")?,
+  ///   image::ImageFormat::Png,
+  /// )?;
+  /// ```
+  pub fn open(&self, path: impl AsRef<str>) -> Result<EmbeddedFile<'_>, FsError> {
+    let path = path.as_ref();
+    if !self.verify_reads && self.passthrough(path)?.is_none() {
+      let entry = self.entry(path)?;
+      if let (Some((compression, _)), None, None) =
+        (entry.compression, &entry.nonce, entry.decoded.get())
+      {
+        let stored = entry
+          .stored(self.data_bytes)
+          .ok_or(FsError::InvalidArchive)?;
+
+        return Ok(EmbeddedFile {
+          inner: Inner::Stream(Stream::new(compression, stored, entry.size)?),
+        });
+      }
+    }
+
+    Ok(EmbeddedFile {
+      inner: Inner::Slice(Cursor::new(self.get(path)?)),
+    })
+  }
+
+  /// Open a file for reading through `tokio`'s `AsyncRead` and `AsyncSeek`.
+  ///
+  /// The data is already in memory, so reads complete immediately without blocking on I/O,
+  /// although reading compressed files decompresses them on the calling thread. This returns
+  /// the same handle as [`open`](Self::open), which implements both the blocking and the async
+  /// traits.
+  ///
+  /// ```rust,ignore
+  /// static ASSETS: IncludeFs = include_fs!("assets");
+  ///
+  /// let stream = ReaderStream::new(ASSETS.open_async("assets/video.mp4")?);
+  /// let body = axum::body::Body::from_stream(stream);
+  /// ```
+  #[cfg(feature = "tokio")]
+  pub fn open_async(&self, path: impl AsRef<str>) -> Result<EmbeddedFile<'_>, FsError> {
+    self.open(path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_open() {
+    let fs = test_fs(&[("data.bin", b"0123456789")]);
+    let mut file = fs.open("data.bin").unwrap();
+    assert_eq!(file.len(), 10);
+
+    let mut buf = [0; 4];
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"0123");
+
+    assert_eq!(file.seek(SeekFrom::End(-3)).unwrap(), 7);
+    let mut rest = String::new();
+    file.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "789");
+
+    file.seek(SeekFrom::Start(2)).unwrap();
+    assert_eq!(file.fill_buf().unwrap(), b"23456789");
+    assert!(file.seek(SeekFrom::Current(-5)).is_err());
+    assert_eq!(file.data(), Some(&b"0123456789"[..]));
+
+    assert!(matches!(fs.open("missing.bin"), Err(FsError::NotFound)));
+  }
+
+  #[cfg(feature = "tokio")]
+  #[test]
+  fn test_open_async() {
+    use std::task::Waker;
+    use tokio::io::{AsyncBufRead, AsyncRead, AsyncSeek, ReadBuf};
+
+    let fs = test_fs(&[("data.bin", b"0123456789")]);
+    let mut file = fs.open_async("data.bin").unwrap();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let mut buf = [0; 4];
+    let mut read_buf = ReadBuf::new(&mut buf);
+    let poll = Pin::new(&mut file).poll_read(&mut cx, &mut read_buf);
+    assert!(matches!(poll, Poll::Ready(Ok(()))));
+    assert_eq!(read_buf.filled(), b"0123");
+
+    Pin::new(&mut file).start_seek(SeekFrom::End(-3)).unwrap();
+    let poll = Pin::new(&mut file).poll_complete(&mut cx);
+    assert!(matches!(poll, Poll::Ready(Ok(7))));
+
+    let Poll::Ready(Ok(rest)) = Pin::new(&mut file).poll_fill_buf(&mut cx) else {
+      panic!("read is not ready");
+    };
+    assert_eq!(rest, b"789");
+  }
+
+  #[cfg(feature = "gzip")]
+  #[test]
+  fn test_open_compressed() {
+    use crate::archive::{ArchiveOptions, FileEntry};
+    use crate::compression::compress_file;
+    use crate::tests::test_fs_with;
+
+    let text: String = (0..2000).map(|i| format!("line {i}\n")).collect();
+    let mut files = vec![
+      FileEntry::new("big.txt", text.as_str()),
+      FileEntry::new("shader.glsl", "void main() {}"),
+    ];
+    files[1].nul_terminated = true;
+    for file in &mut files {
+      compress_file(file, Compression::Gzip).unwrap();
+    }
+
+    let fs = test_fs_with(&files, &ArchiveOptions::default());
+
+    let mut file = fs.open("big.txt").unwrap();
+    assert_eq!(file.len(), text.len() as u64);
+    assert_eq!(file.data(), None);
+
+    let mut line = String::new();
+    file.read_line(&mut line).unwrap();
+    assert_eq!(line, "line 0\n");
+
+    let offset = text.find("line 1500").unwrap() as u64;
+    assert_eq!(file.seek(SeekFrom::Start(offset)).unwrap(), offset);
+    let mut rest = String::new();
+    file.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, text[offset as usize..]);
+
+    // Seeking backwards restarts decompression
+    file.seek(SeekFrom::Current(-(text.len() as i64))).unwrap();
+    let mut all = Vec::new();
+    file.read_to_end(&mut all).unwrap();
+    assert_eq!(all, text.as_bytes());
+    assert_eq!(
+      file.seek(SeekFrom::End(10)).unwrap(),
+      text.len() as u64 + 10
+    );
+    assert_eq!(file.read(&mut [0; 4]).unwrap(), 0);
+
+    // The NUL terminator is not part of the data
+    let mut shader = Vec::new();
+    fs.open("shader.glsl")
+      .unwrap()
+      .read_to_end(&mut shader)
+      .unwrap();
+    assert_eq!(shader, b"void main() {}");
+
+    // Once decompressed by `get`, the cached data is used
+    fs.get("big.txt").unwrap();
+    assert!(fs.open("big.txt").unwrap().data().is_some());
+  }
+}
@@ -0,0 +1,329 @@
+//! Helpers for HTTP cache validation, based on modification times or on `ETag`s.
+//!
+//! These are independent of any HTTP framework, so they can be used by the serving integrations
+//! as well as by hand-written handlers:
+//!
+//! ```rust,ignore
+//! let last_modified = ASSETS.last_modified(path)?;
+//! if let Some(last_modified) = last_modified {
+//!   if include_fs::http::not_modified(req.header("If-Modified-Since"), last_modified) {
+//!     return Response::status(304);
+//!   }
+//!
+//!   response.header("Last-Modified", include_fs::http::format_date(last_modified));
+//! }
+//! ```
+//!
+//! With [`Bundle::etags`], responses can carry an `ETag` as well:
+//!
+//! ```rust,ignore
+//! if let Some(etag) = ASSETS.metadata(path)?.etag() {
+//!   if include_fs::http::etag_matches(req.header("If-None-Match"), etag) {
+//!     return Response::status(304);
+//!   }
+//!
+//!   response.header("ETag", etag);
+//! }
+//! ```
+//!
+//! Files stored with precompressed variants by [`Bundle::precompress`] can be sent in the
+//! encoding the client prefers, without compressing them per request:
+//!
+//! ```rust,ignore
+//! let entry = ASSETS.metadata(path)?;
+//! let available: Vec<_> = entry.encodings().collect();
+//! let accept_encoding = req.header("Accept-Encoding");
+//! if let Some(encoding) = include_fs::http::negotiate_encoding(accept_encoding, &available) {
+//!   response.header("Content-Encoding", encoding.as_str());
+//!   response.body(ASSETS.get_encoded(path, encoding)?.unwrap());
+//! }
+//! ```
+//!
+//! # Serving
+//!
+//! The serving integrations for axum, actix-web and Rocket use these helpers and answer requests
+//! the same way:
+//!
+//! - Request paths are percent-decoded and normalized before they are looked up below the root,
+//!   and paths that would leave the root are not found
+//! - Requests for a directory are answered with its index file, `index.html` by default
+//! - Responses have a `Content-Type`, and an `ETag` and `Last-Modified` header if the archive
+//!   stores them, which are used to answer conditional requests with `304 Not Modified`
+//! - Files with precompressed variants are sent in the encoding the client prefers, with a
+//!   `Content-Encoding` and a `Vary: Accept-Encoding` header
+//! - Files renamed by [`Bundle::fingerprint`] are served with [`IMMUTABLE`]
+//! - Methods other than `GET` and `HEAD` are `405 Method Not Allowed`
+//!
+//! [`Bundle::fingerprint`]: crate::Bundle::fingerprint
+//!
+//! [`Bundle::etags`]: crate::Bundle::etags
+//! [`Bundle::precompress`]: crate::Bundle::precompress
+
+use alloc::format;
+use alloc::string::String;
+
+/// `Cache-Control` value for files whose contents never change, like files renamed by
+/// [`Bundle::fingerprint`].
+///
+/// [`Bundle::fingerprint`]: crate::Bundle::fingerprint
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// A `Content-Encoding` that files can be precompressed with, see [`Bundle::precompress`].
+///
+/// [`Bundle::precompress`]: crate::Bundle::precompress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Encoding {
+  /// Brotli, which compresses text better than gzip. Requires the `brotli` feature to build.
+  Brotli,
+  /// gzip, which every client supports. Requires the `gzip` feature to build.
+  Gzip,
+}
+
+impl Encoding {
+  /// The name of the encoding in `Content-Encoding` and `Accept-Encoding` headers.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Encoding::Brotli => "br",
+      Encoding::Gzip => "gzip",
+    }
+  }
+
+  /// The identifier of the encoding in the archive.
+  #[cfg(feature = "std")]
+  pub(crate) fn id(self) -> u8 {
+    match self {
+      Encoding::Brotli => 1,
+      Encoding::Gzip => 2,
+    }
+  }
+
+  pub(crate) fn from_id(id: u8) -> Option<Self> {
+    match id {
+      1 => Some(Encoding::Brotli),
+      2 => Some(Encoding::Gzip),
+      _ => None,
+    }
+  }
+}
+
+/// The encoding to send a file in, given the value of the `Accept-Encoding` request header and
+/// the encodings the file is available in, or `None` to send it as it is.
+///
+/// Encodings are weighted by their `q` value, and ties go to the one that comes first in
+/// `available`. Encodings with `q=0` are never chosen, and `*` matches everything else.
+pub fn negotiate_encoding(
+  accept_encoding: Option<&str>,
+  available: &[Encoding],
+) -> Option<Encoding> {
+  let accept_encoding = accept_encoding?;
+  let quality = |encoding: Encoding| {
+    let mut wildcard = None;
+    for item in accept_encoding.split(',') {
+      let mut params = item.split(';').map(str::trim);
+      let name = params.next().unwrap_or_default();
+      let q = params
+        .find_map(|param| param.strip_prefix("q="))
+        .map_or(Some(1.0), |q| q.parse::<f32>().ok());
+
+      if name.eq_ignore_ascii_case(encoding.as_str())
+        || (encoding == Encoding::Gzip && name.eq_ignore_ascii_case("x-gzip"))
+      {
+        return q.unwrap_or(0.0);
+      } else if name == "*" {
+        wildcard = q;
+      }
+    }
+
+    wildcard.unwrap_or(0.0)
+  };
+
+  let mut best = None;
+  for &encoding in available {
+    let q = quality(encoding);
+    if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+      best = Some((encoding, q));
+    }
+  }
+
+  best.map(|(encoding, _)| encoding)
+}
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+  "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format seconds since the Unix epoch as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub fn format_date(secs: u64) -> String {
+  let days = secs / 86400;
+  let time = secs % 86400;
+  let (year, month, day) = civil_from_days(days as i64);
+
+  format!(
+    "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+    DAYS[(days % 7) as usize],
+    day,
+    MONTHS[month as usize - 1],
+    year,
+    time / 3600,
+    time % 3600 / 60,
+    time % 60,
+  )
+}
+
+/// Parse an HTTP date in the preferred `Sun, 06 Nov 1994 08:49:37 GMT` format, returning
+/// seconds since the Unix epoch. The obsolete RFC 850 and asctime formats are not supported.
+pub fn parse_date(date: &str) -> Option<u64> {
+  let (_, date) = date.split_once(", ")?;
+  let mut parts = date.split(' ');
+  let day: u32 = parts.next()?.parse().ok()?;
+  let month = parts.next()?;
+  let month = MONTHS.iter().position(|&m| m == month)? as u32 + 1;
+  let year: i64 = parts.next()?.parse().ok()?;
+
+  let mut time = parts.next()?.split(':');
+  let hours: u64 = time.next()?.parse().ok()?;
+  let minutes: u64 = time.next()?.parse().ok()?;
+  let seconds: u64 = time.next()?.parse().ok()?;
+
+  if parts.next()? != "GMT" || parts.next().is_some() || time.next().is_some() {
+    return None;
+  }
+
+  if !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+    return None;
+  }
+
+  let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+  Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Whether a response can be answered with `304 Not Modified`, given the value of the
+/// `If-Modified-Since` request header and the modification time of the file.
+///
+/// Unparseable dates are ignored, as required by RFC 9110.
+pub fn not_modified(if_modified_since: Option<&str>, last_modified: u64) -> bool {
+  if_modified_since
+    .and_then(parse_date)
+    .is_some_and(|since| last_modified <= since)
+}
+
+/// The quoted strong `ETag` of some data, as stored by [`Bundle::etags`].
+///
+/// [`Bundle::etags`]: crate::Bundle::etags
+pub fn etag(data: &[u8]) -> String {
+  format!("\"{:032x}\"", xxhash_rust::xxh3::xxh3_128(data))
+}
+
+/// Whether a response can be answered with `304 Not Modified`, given the value of the
+/// `If-None-Match` request header and the `ETag` of the file.
+///
+/// Tags are compared weakly, as required by RFC 9110, so `W/"abc"` matches `"abc"`.
+pub fn etag_matches(if_none_match: Option<&str>, etag: &str) -> bool {
+  let Some(if_none_match) = if_none_match else {
+    return false;
+  };
+
+  let etag = etag.strip_prefix("W/").unwrap_or(etag);
+  if_none_match
+    .split(',')
+    .map(str::trim)
+    .any(|candidate| candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag)
+}
+
+// Conversions between days since the Unix epoch and dates in the proleptic Gregorian calendar,
+// from http://howardhinnant.github.io/date_algorithms.html
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let z = days + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z.rem_euclid(146097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+  let year = yoe + era * 400 + (month <= 2) as i64;
+
+  (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let yoe = year.rem_euclid(400);
+  let mp = (month as i64 + 9) % 12;
+  let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+  era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_dates() {
+    assert_eq!(format_date(784111777), "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(format_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+    assert_eq!(parse_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784111777));
+    assert_eq!(
+      parse_date("Tue, 29 Feb 2028 23:59:59 GMT"),
+      Some(1835481599)
+    );
+    assert_eq!(format_date(1835481599), "Tue, 29 Feb 2028 23:59:59 GMT");
+
+    assert_eq!(parse_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+    assert_eq!(parse_date("Sun, 06 Nov 1994 08:49:37"), None);
+
+    assert!(not_modified(
+      Some("Sun, 06 Nov 1994 08:49:37 GMT"),
+      784111777
+    ));
+    assert!(!not_modified(
+      Some("Sun, 06 Nov 1994 08:49:36 GMT"),
+      784111777
+    ));
+    assert!(!not_modified(Some("garbage"), 784111777));
+    assert!(!not_modified(None, 784111777));
+  }
+
+  #[test]
+  fn test_etags() {
+    let tag = etag(b"hello");
+    assert_eq!(tag.len(), 34);
+    assert!(tag.starts_with('"') && tag.ends_with('"'));
+    assert_ne!(tag, etag(b"hello!"));
+
+    assert!(etag_matches(Some(&tag), &tag));
+    assert!(etag_matches(Some(&format!("\"other\", W/{tag}")), &tag));
+    assert!(etag_matches(Some("*"), &tag));
+    assert!(!etag_matches(Some("\"other\""), &tag));
+    assert!(!etag_matches(None, &tag));
+  }
+
+  #[test]
+  fn test_negotiate_encoding() {
+    let both = [Encoding::Brotli, Encoding::Gzip];
+    assert_eq!(
+      negotiate_encoding(Some("gzip, deflate, br"), &both),
+      Some(Encoding::Brotli)
+    );
+    assert_eq!(
+      negotiate_encoding(Some("br;q=0.5, gzip;q=0.8"), &both),
+      Some(Encoding::Gzip)
+    );
+    assert_eq!(
+      negotiate_encoding(Some("x-gzip"), &both),
+      Some(Encoding::Gzip)
+    );
+    assert_eq!(
+      negotiate_encoding(Some("*, br;q=0"), &both),
+      Some(Encoding::Gzip)
+    );
+    assert_eq!(negotiate_encoding(Some("br"), &[Encoding::Gzip]), None);
+    assert_eq!(negotiate_encoding(Some("identity"), &both), None);
+    assert_eq!(negotiate_encoding(None, &both), None);
+  }
+}
@@ -0,0 +1,157 @@
+//! Skipping the regeneration of archives whose inputs didn't change, see
+//! [`Bundle::incremental`](crate::Bundle::incremental).
+
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use walkdir::WalkDir;
+use xxhash_rust::xxh3::Xxh3;
+
+/// What the previous run of the build script produced for a bundle, stored next to its archive.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct BuildCache {
+  /// Hash of the configuration of the bundle and the metadata of its input files.
+  pub inputs: u128,
+  /// Hash of the archive that was written.
+  pub archive: u128,
+  /// The `cargo:` directives that were printed, which have to be printed on every run.
+  pub directives: Vec<String>,
+}
+
+impl BuildCache {
+  /// The path of the cache for the archive at `output_path`.
+  pub fn path(output_path: &Path) -> PathBuf {
+    output_path.with_extension("embed_fs.cache")
+  }
+
+  /// Read a cache, or `None` if there is none or it can't be parsed.
+  pub fn read(path: &Path) -> Option<Self> {
+    let contents = fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+    let inputs = u128::from_str_radix(lines.next()?, 16).ok()?;
+    let archive = u128::from_str_radix(lines.next()?, 16).ok()?;
+
+    Some(Self {
+      inputs,
+      archive,
+      directives: lines.map(String::from).collect(),
+    })
+  }
+
+  pub fn write(&self, path: &Path) -> io::Result<()> {
+    let mut contents = format!("{:032x}\n{:032x}\n", self.inputs, self.archive);
+    for directive in &self.directives {
+      contents.push_str(directive);
+      contents.push('\n');
+    }
+
+    fs::write(path, contents)
+  }
+}
+
+/// Hashes what goes into a bundle from the metadata of the input files, without reading them.
+pub(crate) struct InputHasher(Xxh3);
+
+impl InputHasher {
+  pub fn new(config: &str) -> Self {
+    let mut hasher = Xxh3::new();
+    hasher.update(config.as_bytes());
+    Self(hasher)
+  }
+
+  /// Add the size and modification time of `path`, and of everything below it if it is a
+  /// directory. Paths that don't exist are added as missing.
+  pub fn add_tree(&mut self, path: &Path, follow_links: bool) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_err() {
+      self.add(path, None);
+      return Ok(());
+    }
+
+    let walk = WalkDir::new(path)
+      .follow_links(follow_links)
+      .sort_by_file_name();
+    for entry in walk {
+      match entry {
+        Ok(entry) => self.add(entry.path(), Some(&entry.metadata()?)),
+        // Symlink loops and broken links are skipped by the walk of the bundle as well
+        Err(err) => self.add(err.path().unwrap_or(path), None),
+      }
+    }
+
+    Ok(())
+  }
+
+  fn add(&mut self, path: &Path, meta: Option<&Metadata>) {
+    self.0.update(&[0]);
+    self.0.update(path.as_os_str().as_encoded_bytes());
+
+    let Some(meta) = meta else {
+      self.0.update(&[0]);
+      return;
+    };
+
+    let modified = meta
+      .modified()
+      .ok()
+      .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+      .map_or(0, |modified| modified.as_nanos());
+
+    self.0.update(&[1 + meta.is_dir() as u8]);
+    self.0.update(&meta.len().to_le_bytes());
+    self.0.update(&modified.to_le_bytes());
+  }
+
+  pub fn finish(self) -> u128 {
+    self.0.digest128()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bundle::tests::TempTree;
+
+  #[test]
+  fn test_build_cache() {
+    let tree = TempTree::new("build-cache", &[]);
+    let path = BuildCache::path(&tree.0.join("assets.embed_fs"));
+    assert!(path.ends_with("assets.embed_fs.cache"));
+    assert_eq!(BuildCache::read(&path), None);
+
+    let cache = BuildCache {
+      inputs: 1,
+      archive: u128::MAX,
+      directives: vec!["cargo:rerun-if-changed=assets".to_string()],
+    };
+    cache.write(&path).unwrap();
+    assert_eq!(BuildCache::read(&path), Some(cache));
+  }
+
+  #[test]
+  fn test_input_hasher() {
+    let tree = TempTree::new("input-hasher", &[("assets/a.txt", "a")]);
+    let hash = || {
+      let mut hasher = InputHasher::new("config");
+      hasher.add_tree(&tree.0.join("assets"), false).unwrap();
+      hasher.add_tree(&tree.0.join("missing"), false).unwrap();
+      hasher.finish()
+    };
+
+    let before = hash();
+    assert_eq!(hash(), before);
+
+    fs::write(tree.0.join("assets/a.txt"), "changed").unwrap();
+    let changed = hash();
+    assert_ne!(changed, before);
+
+    fs::write(tree.0.join("assets/b.txt"), "").unwrap();
+    assert_ne!(hash(), changed);
+
+    let mut hasher = InputHasher::new("other config");
+    hasher.add_tree(&tree.0.join("assets"), false).unwrap();
+    hasher.add_tree(&tree.0.join("missing"), false).unwrap();
+    assert_ne!(hasher.finish(), hash());
+  }
+}
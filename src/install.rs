@@ -0,0 +1,408 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::IncludeFsInner;
+
+/// Name of the uninstall manifest written into the target directory.
+pub const UNINSTALL_MANIFEST: &str = ".include-fs-uninstall";
+
+type ProgressFn<'a> = dyn FnMut(&str, u64, u64) + 'a;
+
+/// How to handle files that already exist in the target directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overwrite {
+  /// Replace existing files.
+  Replace,
+  /// Keep existing files and don't install the embedded version.
+  Skip,
+  /// Fail the installation before any files are written.
+  #[default]
+  Error,
+}
+
+/// Installs the files of an embedded filesystem into a directory, turning a binary that embeds
+/// a bundle into an installer.
+///
+/// A manifest of the installed files is written to [`UNINSTALL_MANIFEST`] in the target
+/// directory, which [`uninstall`] uses to remove them again.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// static APP: IncludeFs = include_fs!("app");
+///
+/// Installer::new(&APP)
+///   .subtree("app")
+///   .overwrite(Overwrite::Replace)
+///   .progress(|path, done, total| println!("{path} ({done}/{total} bytes)"))
+///   .install_to("/opt/app")?;
+/// ```
+pub struct Installer<'a> {
+  fs: &'a IncludeFsInner,
+  subtree: String,
+  overwrite: Overwrite,
+  progress: Option<Box<ProgressFn<'a>>>,
+}
+
+impl fmt::Debug for Installer<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Installer")
+      .field("subtree", &self.subtree)
+      .field("overwrite", &self.overwrite)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<'a> Installer<'a> {
+  pub fn new(fs: &'a IncludeFsInner) -> Self {
+    Self {
+      fs,
+      subtree: String::new(),
+      overwrite: Overwrite::default(),
+      progress: None,
+    }
+  }
+
+  /// Only install the files below this directory, relative to it.
+  pub fn subtree(mut self, subtree: &str) -> Self {
+    self.subtree = subtree.trim_end_matches('/').to_string();
+    self
+  }
+
+  /// Set how existing files are handled. Defaults to [`Overwrite::Error`].
+  pub fn overwrite(mut self, overwrite: Overwrite) -> Self {
+    self.overwrite = overwrite;
+    self
+  }
+
+  /// Call `progress` after every installed file with its path, the number of bytes installed so
+  /// far and the total number of bytes.
+  pub fn progress<F>(mut self, progress: F) -> Self
+  where
+    F: FnMut(&str, u64, u64) + 'a,
+  {
+    self.progress = Some(Box::new(progress));
+    self
+  }
+
+  /// Install the files into `target_dir`, returning the installed paths relative to it.
+  ///
+  /// Files skipped with [`Overwrite::Skip`] are not returned and not added to the uninstall
+  /// manifest. On Unix, files get the permissions stored with [`Bundle::file_metadata`], and
+  /// the default permissions otherwise.
+  ///
+  /// [`Bundle::file_metadata`]: crate::Bundle::file_metadata
+  pub fn install_to<P: AsRef<Path>>(mut self, target_dir: P) -> io::Result<Vec<String>> {
+    let target_dir = target_dir.as_ref();
+
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("install", target = %target_dir.display()).entered();
+    #[cfg(feature = "tracing")]
+    let start = std::time::Instant::now();
+
+    let mut files: Vec<_> = self
+      .fs
+      .list_paths()
+      .into_iter()
+      .filter_map(|path| {
+        if self.subtree.is_empty() {
+          return Some((path.as_str(), path.as_str()));
+        }
+
+        let relative = path.strip_prefix(&self.subtree)?.strip_prefix('/')?;
+        Some((path.as_str(), relative))
+      })
+      .collect();
+
+    files.sort();
+
+    // Check for existing files first, so a failed installation doesn't leave partial results
+    let mut total = 0;
+    for (path, relative) in &files {
+      let file_path = target_dir.join(relative);
+      if self.overwrite == Overwrite::Error && file_path.exists() {
+        return Err(io::Error::new(
+          io::ErrorKind::AlreadyExists,
+          format!("{} already exists", file_path.display()),
+        ));
+      }
+
+      total += self.fs.get(path).map_err(io::Error::other)?.len() as u64;
+    }
+
+    let mut installed = Vec::new();
+    let mut done = 0;
+    for (path, relative) in files {
+      let data = self.fs.get(path).map_err(io::Error::other)?;
+      let file_path = target_dir.join(relative);
+
+      if self.overwrite != Overwrite::Skip || !file_path.exists() {
+        fs::create_dir_all(file_path.parent().unwrap())?;
+        fs::write(&file_path, data)?;
+        if let Some(mode) = self.fs.metadata(path).ok().and_then(|entry| entry.mode()) {
+          set_mode(&file_path, mode)?;
+        }
+
+        installed.push(relative.to_string());
+      }
+
+      done += data.len() as u64;
+      if let Some(progress) = &mut self.progress {
+        progress(relative, done, total);
+      }
+    }
+
+    let mut manifest = installed.join("\n");
+    manifest.push('\n');
+    fs::write(target_dir.join(UNINSTALL_MANIFEST), manifest)?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+      files = installed.len(),
+      bytes = total,
+      duration = ?start.elapsed(),
+      "installed files",
+    );
+
+    Ok(installed)
+  }
+}
+
+/// Remove the files installed by [`Installer::install_to`], along with directories that are
+/// left empty and the manifest itself.
+pub fn uninstall<P: AsRef<Path>>(target_dir: P) -> io::Result<()> {
+  let target_dir = target_dir.as_ref();
+  let manifest_path = target_dir.join(UNINSTALL_MANIFEST);
+  let manifest = fs::read_to_string(&manifest_path)?;
+
+  for path in manifest.lines().filter(|path| !path.is_empty()) {
+    match fs::remove_file(target_dir.join(path)) {
+      Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+      _ => {}
+    }
+
+    // Remove parent directories until one is not empty
+    let mut dir = Path::new(path).parent();
+    while let Some(parent) = dir.filter(|dir| !dir.as_os_str().is_empty()) {
+      if fs::remove_dir(target_dir.join(parent)).is_err() {
+        break;
+      }
+
+      dir = parent.parent();
+    }
+  }
+
+  fs::remove_file(manifest_path)
+}
+
+impl IncludeFsInner {
+  /// Write all files into `dest`, creating it and the parent directories of the files as
+  /// needed, along with the empty directories stored with
+  /// [`Bundle::empty_dirs`](crate::Bundle::empty_dirs). Existing files are replaced.
+  ///
+  /// This is meant for simple extractions, like writing config templates on first run or
+  /// preparing a directory in tests. Use an [`Installer`] to extract a subtree, keep existing
+  /// files or uninstall the files again, or [`materialize`](crate::materialize) for files that
+  /// only have to exist somewhere on disk.
+  ///
+  /// ```rust,ignore
+  /// static TEMPLATES: IncludeFs = include_fs!("templates");
+  ///
+  /// if !config_dir.exists() {
+  ///   TEMPLATES.unpack(&config_dir)?;
+  /// }
+  /// ```
+  pub fn unpack<P: AsRef<Path>>(&self, dest: P) -> io::Result<()> {
+    self.unpack_files(dest.as_ref(), false)
+  }
+
+  /// Like [`unpack`](Self::unpack), but also restore the modification times and, on Unix, the
+  /// permissions stored with [`Bundle::file_metadata`].
+  ///
+  /// [`Bundle::file_metadata`]: crate::Bundle::file_metadata
+  pub fn unpack_with_metadata<P: AsRef<Path>>(&self, dest: P) -> io::Result<()> {
+    self.unpack_files(dest.as_ref(), true)
+  }
+
+  fn unpack_files(&self, dest: &Path, restore_metadata: bool) -> io::Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("unpack", dest = %dest.display()).entered();
+
+    fs::create_dir_all(dest)?;
+    // Empty directories are checked like paths when the archive is parsed
+    for dir in self.empty_dirs() {
+      fs::create_dir_all(dest.join(dir.as_str()))?;
+    }
+
+    for path in self.list_paths() {
+      // Paths are checked to be relative without `..` components when the archive is parsed, so
+      // they stay inside `dest`
+      let file_path = dest.join(path.as_str());
+      fs::create_dir_all(file_path.parent().unwrap())?;
+      fs::write(&file_path, self.get(path).map_err(io::Error::other)?)?;
+
+      if !restore_metadata {
+        continue;
+      }
+
+      let entry = self.metadata(path).map_err(io::Error::other)?;
+      if let Some(modified) = entry.modified() {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(modified);
+        fs::File::options()
+          .write(true)
+          .open(&file_path)?
+          .set_modified(modified)?;
+      }
+
+      if let Some(mode) = entry.mode() {
+        set_mode(&file_path, mode)?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+  use std::os::unix::fs::PermissionsExt;
+  fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_install_and_uninstall() {
+    let target = std::env::temp_dir().join(format!("include-fs-install-{}", std::process::id()));
+    let fs = test_fs(&[
+      ("app/bin/tool", b"tool"),
+      ("app/config.toml", b"embedded"),
+      ("other.txt", b"other"),
+    ]);
+
+    fs::create_dir_all(&target).unwrap();
+    fs::write(target.join("config.toml"), "local").unwrap();
+    fs::write(target.join("keep.txt"), "keep").unwrap();
+
+    assert_eq!(
+      Installer::new(&fs)
+        .subtree("app")
+        .install_to(&target)
+        .unwrap_err()
+        .kind(),
+      io::ErrorKind::AlreadyExists
+    );
+    assert!(!target.join("bin").exists());
+
+    let mut progress = Vec::new();
+    let installed = Installer::new(&fs)
+      .subtree("app")
+      .overwrite(Overwrite::Skip)
+      .progress(|path, done, total| progress.push((path.to_string(), done, total)))
+      .install_to(&target)
+      .unwrap();
+
+    assert_eq!(installed, ["bin/tool"]);
+    assert_eq!(
+      progress,
+      [
+        ("bin/tool".to_string(), 4, 12),
+        ("config.toml".to_string(), 12, 12)
+      ]
+    );
+    assert_eq!(fs::read(target.join("bin/tool")).unwrap(), b"tool");
+    assert_eq!(fs::read(target.join("config.toml")).unwrap(), b"local");
+
+    uninstall(&target).unwrap();
+    assert!(!target.join("bin").exists());
+    assert!(!target.join(UNINSTALL_MANIFEST).exists());
+    assert!(target.join("config.toml").exists());
+    assert!(target.join("keep.txt").exists());
+
+    fs::remove_dir_all(&target).unwrap();
+  }
+
+  #[test]
+  fn test_unpack() {
+    use crate::archive::{ArchiveOptions, FileEntry};
+    use crate::tests::test_fs_with;
+
+    let mut config = FileEntry::new("config/app.toml", "embedded");
+    config.modified = Some(1_700_000_000);
+
+    let options = ArchiveOptions {
+      empty_dirs: vec!["cache/images".into()],
+      ..Default::default()
+    };
+    let fs = test_fs_with(&[config, FileEntry::new("README.md", "readme")], &options);
+
+    let target = std::env::temp_dir().join(format!("include-fs-unpack-{}", std::process::id()));
+    fs::create_dir_all(target.join("config")).unwrap();
+    fs::write(target.join("config/app.toml"), "local").unwrap();
+
+    fs.unpack(&target).unwrap();
+    assert_eq!(
+      fs::read(target.join("config/app.toml")).unwrap(),
+      b"embedded"
+    );
+    assert_eq!(fs::read(target.join("README.md")).unwrap(), b"readme");
+    assert!(target.join("cache/images").is_dir());
+    let modified = fs::metadata(target.join("config/app.toml"))
+      .unwrap()
+      .modified()
+      .unwrap();
+    assert_ne!(
+      modified,
+      SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    );
+
+    fs.unpack_with_metadata(&target).unwrap();
+    let modified = fs::metadata(target.join("config/app.toml"))
+      .unwrap()
+      .modified()
+      .unwrap();
+    assert_eq!(
+      modified,
+      SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    );
+
+    fs::remove_dir_all(&target).unwrap();
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_install_restores_mode() {
+    use crate::archive::{ArchiveOptions, FileEntry};
+    use crate::tests::test_fs_with;
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut tool = FileEntry::new("bin/tool", "tool");
+    tool.mode = Some(0o750);
+    tool.modified = Some(1_700_000_000);
+
+    let fs = test_fs_with(&[tool], &ArchiveOptions::default());
+    assert_eq!(fs.metadata("bin/tool").unwrap().mode(), Some(0o750));
+    assert_eq!(fs.last_modified("bin/tool").unwrap(), Some(1_700_000_000));
+
+    let target = std::env::temp_dir().join(format!("include-fs-mode-{}", std::process::id()));
+    Installer::new(&fs).install_to(&target).unwrap();
+    let mode = fs::metadata(target.join("bin/tool"))
+      .unwrap()
+      .permissions()
+      .mode();
+    assert_eq!(mode & 0o7777, 0o750);
+
+    fs::remove_dir_all(&target).unwrap();
+  }
+}
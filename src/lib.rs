@@ -1,16 +1,149 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("include-fs requires either the `std` or the `alloc` feature");
+
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ffi::CStr;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::env;
-use std::fs::File;
-use std::io::{self, Write};
-use std::path::{Path, PathBuf};
-use std::sync::LazyLock;
+#[cfg(feature = "std")]
+use std::io::Write;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
 use thiserror::Error;
-use walkdir::WalkDir;
 
-pub use include_fs_macros::include_fs;
+pub use include_fs_macros::{include_fs, include_fs_dir};
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "std")]
+pub mod afs;
+mod archive;
+#[doc(hidden)]
+pub mod assert;
+mod attribution;
+#[cfg(feature = "axum")]
+mod axum;
+#[cfg(feature = "std")]
+mod bundle;
+mod checksum;
+mod compression;
+#[cfg(feature = "encryption")]
+mod crypto;
+#[cfg(feature = "std")]
+mod diff;
+mod dir;
+#[cfg(feature = "rust-embed")]
+#[doc(hidden)]
+pub mod embed;
+mod entries;
+#[cfg(feature = "std")]
+mod external;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod files;
+#[cfg(feature = "std")]
+mod fingerprint;
+#[cfg(feature = "std")]
+mod glob;
+#[cfg(feature = "std")]
+mod handle;
+pub mod http;
+#[cfg(feature = "std")]
+mod incremental;
+#[cfg(feature = "std")]
+mod install;
+#[cfg(feature = "std")]
+mod materialize;
+#[cfg(feature = "mime")]
+mod mime;
+mod overlay;
+#[cfg(feature = "std")]
+mod passthrough;
+mod path;
+#[cfg(feature = "pyo3")]
+pub mod python;
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(feature = "rocket")]
+pub mod rocket;
+#[cfg(any(feature = "axum", feature = "actix", feature = "rocket"))]
+mod serve;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "signing")]
+mod signing;
+mod static_fs;
+#[cfg(feature = "static-lib")]
+mod static_lib;
+#[cfg(feature = "std")]
+mod stats;
+mod sync;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "std")]
+mod writer;
 
-const MAGIC: &[u8; 4] = b"INFS";
+use archive::{
+  ARCHIVE_ATTR_ATTRIBUTIONS, ARCHIVE_ATTR_BUILD_LABEL, ARCHIVE_ATTR_BUILD_TIMESTAMP,
+  ARCHIVE_ATTR_CASE_INSENSITIVE, ARCHIVE_ATTR_CHECKSUM, ARCHIVE_ATTR_CRATE_VERSION,
+  ARCHIVE_ATTR_EMPTY_DIRS, ARCHIVE_ATTR_ENCRYPTED, ARCHIVE_ATTR_KDF, ARCHIVE_ATTR_LOOKUP_TABLE,
+  ARCHIVE_ATTR_SHARED_DATA, ARCHIVE_ATTR_SORTED_INDEX, ARCHIVE_ATTR_SOURCE_DIR, ARCHIVE_ATTR_STUB,
+  ATTR_CHECKSUM, ATTR_COMPRESSION, ATTR_ENCODINGS, ATTR_ETAG, ATTR_MIME, ATTR_MODE, ATTR_MODIFIED,
+  ATTR_NONCE, ATTR_NUL_TERMINATED, ATTR_ORIGINAL_PATH, ENCODING_LEN, FORMAT_VERSION, HEADER_LEN,
+  KdfParams, MAGIC, MIN_FORMAT_VERSION,
+};
+pub use attribution::Attribution;
+#[cfg(feature = "axum")]
+pub use axum::ServeEmbedded;
+#[cfg(feature = "std")]
+pub use bundle::{BrokenSymlinks, Bundle, Decision, bundle};
+pub use checksum::ChecksumAlgorithm;
+pub use compression::Compression;
+#[cfg(feature = "std")]
+pub use diff::{FsDiff, diff_against_dir};
+pub use dir::DirEntry;
+pub use entries::Entries;
+#[cfg(feature = "std")]
+pub use external::ArchiveFile;
+use files::{Files, LookupTable};
+#[cfg(feature = "std")]
+pub use handle::EmbeddedFile;
+#[cfg(feature = "std")]
+pub use install::{Installer, Overwrite, UNINSTALL_MANIFEST, uninstall};
+#[cfg(feature = "std")]
+pub use materialize::materialize;
+#[cfg(feature = "mime")]
+pub use mime::DEFAULT_MIME;
+pub use overlay::OverlayFs;
+#[cfg(feature = "std")]
+use passthrough::Passthrough;
+pub use path::EmbeddedPath;
+#[cfg(feature = "std")]
+pub use registry::{RegisteredBundle, all_bundles, all_paths, find, register, register_under};
+#[cfg(feature = "std")]
+pub use shared::SharedData;
+pub use static_fs::StaticFs;
+#[cfg(feature = "std")]
+pub use stats::ExtensionStats;
+use sync::{LazyLock, OnceLock};
+#[cfg(feature = "std")]
+pub use writer::ArchiveWriter;
 
+/// Size of a header entry with an empty path and no attributes.
+const MIN_ENTRY_SIZE: usize = 2 + 8 + 8 + 2;
+
+/// Stride used when touching file data in [`IncludeFsInner::warm`].
+const PAGE_SIZE: usize = 4096;
+
+#[cfg(feature = "std")]
 #[derive(Error, Debug)]
 pub enum ArchiveError {
   #[error("Path too long: {path} ({len} bytes, max {max} bytes)")]
@@ -20,17 +153,102 @@ pub enum ArchiveError {
     max: usize,
   },
 
+  #[error(
+    "Attributes too long: {len} bytes (max {max} bytes), which is likely caused by too many empty directories or attributions"
+  )]
+  AttributesTooLong { len: usize, max: usize },
+
   #[error("Too many files: {count} (max {max})")]
   TooManyFiles { count: usize, max: usize },
 
   #[error("IO error: {0}")]
   Io(#[from] std::io::Error),
 
-  #[error("Source directory must be a subdirectory of the manifest directory")]
+  #[error(
+    "Source directory must be a subdirectory of the manifest directory, see Bundle::allow_external"
+  )]
   InvalidSourceDirectory,
 
   #[error("Failed to collect files: {0}")]
   WalkDir(#[from] walkdir::Error),
+
+  #[error("Path is not valid unicode: {}", path.display())]
+  NonUnicodePath { path: PathBuf },
+
+  #[error("Paths {first} and {second} both map to {path}")]
+  PathCollision {
+    path: String,
+    first: String,
+    second: String,
+  },
+
+  #[error("Unsupported source archive: {} (tar files require the `tar` feature, zip files the `zip` feature)", path.display())]
+  UnsupportedSourceArchive { path: PathBuf },
+
+  #[error("Broken symlink: {}", path.display())]
+  BrokenSymlink { path: PathBuf },
+
+  #[error("Invalid .embedignore file: {0}")]
+  InvalidIgnoreFile(ignore::Error),
+
+  #[error("File can not be NUL-terminated because it contains a NUL byte: {path}")]
+  InteriorNul { path: String },
+
+  #[error("Invalid glob: {0}")]
+  InvalidGlob(#[from] globset::Error),
+
+  #[error("Validation failed for {path}: {message}")]
+  ValidationFailed { path: String, message: String },
+
+  #[error("Precompiling {path} failed: {message}")]
+  PrecompileFailed { path: String, message: String },
+
+  #[error("Invalid archive path: {0}")]
+  InvalidPath(String),
+
+  #[error("Path was added more than once: {0}")]
+  DuplicatePath(String),
+
+  #[error("Alignment must be a power of two up to {max} bytes, got {alignment}")]
+  InvalidAlignment { alignment: usize, max: usize },
+
+  #[error("Checksum algorithm {0:?} is not enabled")]
+  UnsupportedChecksum(ChecksumAlgorithm),
+
+  #[error("Compression codec {0:?} is not enabled")]
+  UnsupportedCompression(Compression),
+
+  #[error("Content encoding {0:?} is not enabled")]
+  UnsupportedEncoding(http::Encoding),
+
+  #[error(
+    "Files larger than {max} bytes, exclude them or raise Bundle::max_file_size:{}",
+    list_sizes(files)
+  )]
+  FilesTooLarge { max: u64, files: Vec<(String, u64)> },
+
+  #[error(
+    "Bundle is {total} bytes (max {max} bytes), the largest files are:{}",
+    list_sizes(largest)
+  )]
+  BundleTooLarge {
+    total: u64,
+    max: u64,
+    largest: Vec<(String, u64)>,
+  },
+
+  #[cfg(feature = "static-lib")]
+  #[error("Failed to compile static library: {0}")]
+  StaticLib(cc::Error),
+}
+
+/// One indented line with the path and size of every file, for [`ArchiveError`] messages.
+#[cfg(feature = "std")]
+fn list_sizes(files: &[(String, u64)]) -> String {
+  files
+    .iter()
+    .map(|(path, size)| format!("\n  {path} ({size} bytes)"))
+    .collect()
 }
 
 #[derive(Error, Debug)]
@@ -40,144 +258,540 @@ pub enum FsError {
 
   #[error("Invalid archive")]
   InvalidArchive,
+
+  #[error("File is not NUL-terminated")]
+  NotNulTerminated,
+
+  #[error("Not a directory")]
+  NotADirectory,
+
+  #[error("Unsupported archive format version {0}")]
+  UnsupportedVersion(u8),
+
+  #[error("Invalid path: {0}")]
+  InvalidPath(String),
+
+  #[error("Archive stores its data in shared data {0}, which was not provided")]
+  MissingSharedData(String),
+
+  #[error("Bundle is not embedded and its source directory doesn't exist")]
+  SourceDirMissing,
+
+  #[error("Archive is encrypted")]
+  Encrypted,
+
+  #[error("Invalid passphrase")]
+  InvalidPassphrase,
+
+  #[error("Invalid key")]
+  InvalidKey,
+
+  #[error("File is compressed")]
+  Compressed,
+
+  #[error("Compression codec {0:?} is not enabled")]
+  UnsupportedCompression(Compression),
+
+  #[error("Alignment must be a power of two up to {max} bytes, got {alignment}")]
+  InvalidAlignment { alignment: usize, max: usize },
+
+  #[error("Checksum algorithm {0:?} is not enabled")]
+  UnsupportedChecksum(ChecksumAlgorithm),
+
+  #[error("Checksum mismatch: {0}")]
+  ChecksumMismatch(String),
+
+  #[error("Archive is not signed")]
+  Unsigned,
+
+  #[error("Invalid signature")]
+  InvalidSignature,
+
+  #[cfg(feature = "std")]
+  #[error("Invalid glob: {0}")]
+  InvalidGlob(#[from] globset::Error),
+
+  #[cfg(feature = "std")]
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
 }
 
-#[derive(Debug)]
-struct FileEntry {
-  pub path: PathBuf,
-  pub size: u64,
+/// The index entry of an embedded file, returned by [`IncludeFsInner::metadata`] and
+/// [`IncludeFsInner::entries`].
+pub struct FsEntry {
+  size: u64,
+  data_offset: u64,
+  nul_terminated: bool,
+  nonce: Option<[u8; archive::NONCE_LEN]>,
+  checksum: Option<Vec<u8>>,
+  original_path: Option<String>,
+  /// The codec and the size of the stored data, if the file is compressed.
+  compression: Option<(Compression, u64)>,
+  modified: Option<u64>,
+  mode: Option<u32>,
+  mime: Option<String>,
+  etag: Option<String>,
+  /// The encoding, data offset and size of every precompressed variant.
+  encodings: Vec<(http::Encoding, u64, u64)>,
+  /// The decrypted and decompressed data including the NUL terminator, once it has been
+  /// accessed.
+  decoded: OnceLock<Vec<u8>>,
+  /// Set once the data has been checked against the checksum.
+  verified: OnceLock<()>,
 }
 
-impl FileEntry {
-  pub fn new(path: impl Into<PathBuf>, size: u64) -> Self {
+impl FsEntry {
+  fn new(size: u64, data_offset: u64) -> Self {
     Self {
-      path: path.into(),
       size,
+      data_offset,
+      nul_terminated: false,
+      nonce: None,
+      checksum: None,
+      original_path: None,
+      compression: None,
+      modified: None,
+      mode: None,
+      mime: None,
+      etag: None,
+      encodings: Vec::new(),
+      decoded: OnceLock::new(),
+      verified: OnceLock::new(),
     }
   }
-}
 
-fn compute_header(files: &[FileEntry]) -> Result<Vec<u8>, ArchiveError> {
-  // Validate file count fits in u32
-  if files.len() > u32::MAX as usize {
-    return Err(ArchiveError::TooManyFiles {
-      count: files.len(),
-      max: u32::MAX as usize,
-    });
+  /// The size of the file in bytes.
+  pub fn size(&self) -> u64 {
+    self.size
   }
 
-  let mut header_size = 4 + 4; // magic + file count
-  for file in files {
-    let path_str = file.path.to_string_lossy();
-    let path_len = path_str.len();
+  /// Whether the file data is followed by a NUL byte, see [`IncludeFsInner::get_cstr`].
+  pub fn is_nul_terminated(&self) -> bool {
+    self.nul_terminated
+  }
+
+  pub fn is_encrypted(&self) -> bool {
+    self.nonce.is_some()
+  }
+
+  /// The codec the file is compressed with, see [`Bundle::compress`].
+  pub fn compression(&self) -> Option<Compression> {
+    self.compression.map(|(compression, _)| compression)
+  }
+
+  /// The stored checksum of the file, see [`Bundle::checksums`].
+  pub fn checksum(&self) -> Option<&[u8]> {
+    self.checksum.as_deref()
+  }
+
+  /// When the source file was last modified, in seconds since the Unix epoch, if it was stored
+  /// with [`Bundle::file_metadata`].
+  pub fn modified(&self) -> Option<u64> {
+    self.modified
+  }
 
-    if path_len > u16::MAX as usize {
-      return Err(ArchiveError::PathTooLong {
-        path: path_str.to_string(),
-        len: path_str.len(),
-        max: u16::MAX as usize,
-      });
+  /// The Unix permission bits of the source file, if they were stored with
+  /// [`Bundle::file_metadata`].
+  pub fn mode(&self) -> Option<u32> {
+    self.mode
+  }
+
+  /// The quoted strong `ETag` of the file, if it was stored with [`Bundle::etags`]. Check it
+  /// against requests with [`http::etag_matches`].
+  pub fn etag(&self) -> Option<&str> {
+    self.etag.as_deref()
+  }
+
+  /// The encodings the file is precompressed in with [`Bundle::precompress`], to pick one with
+  /// [`http::negotiate_encoding`].
+  pub fn encodings(&self) -> impl ExactSizeIterator<Item = http::Encoding> + '_ {
+    self.encodings.iter().map(|&(encoding, _, _)| encoding)
+  }
+
+  /// The data of a file that is neither encrypted nor compressed.
+  fn data<'a>(&self, data_bytes: &'a [u8]) -> Result<&'a [u8], FsError> {
+    self
+      .slice(data_bytes, self.size)
+      .ok_or(FsError::InvalidArchive)
+  }
+
+  /// Whether the file is compressed or encrypted, so it has to be decoded before use.
+  fn is_encoded(&self) -> bool {
+    self.compression.is_some() || self.nonce.is_some()
+  }
+
+  /// Size of the file data as it is stored, including the NUL terminator and encryption tag.
+  fn stored_len(&self) -> Option<u64> {
+    match (self.compression, self.nonce) {
+      (Some((_, stored_size)), _) => Some(stored_size),
+      (None, Some(_)) => self
+        .size
+        .checked_add(self.nul_terminated as u64 + archive::TAG_LEN as u64),
+      (None, None) => self.size.checked_add(self.nul_terminated as u64),
     }
+  }
 
-    // path_len + path + size + offset
-    header_size += 2 + path_len + 8 + 8;
+  /// The data of the file as it is stored, or `None` if it is out of bounds.
+  fn stored<'a>(&self, data_bytes: &'a [u8]) -> Option<&'a [u8]> {
+    self.slice(data_bytes, self.stored_len()?)
   }
 
-  let mut header = Vec::with_capacity(header_size);
+  /// Decompress the decrypted data of the file, including its NUL terminator.
+  fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, FsError> {
+    let Some((compression, _)) = self.compression else {
+      return Ok(data.to_vec());
+    };
+
+    #[cfg(feature = "tracing")]
+    let start = trace_start();
 
-  header.extend_from_slice(MAGIC);
-  header.extend_from_slice(&(files.len() as u32).to_le_bytes());
+    let size = (self.size as usize).saturating_add(self.nul_terminated as usize);
+    let decompressed = compression.decompress(data, size)?;
 
-  let mut data_offset = header_size as u64;
-  for file in files {
-    let path_str = file.path.to_string_lossy();
-    let path_bytes = path_str.as_bytes();
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+      size,
+      ?compression,
+      duration = ?start.map(|start| start.elapsed()),
+      "decompressed file",
+    );
 
-    header.extend_from_slice(&(path_bytes.len() as u16).to_le_bytes());
-    header.extend_from_slice(path_bytes);
-    header.extend_from_slice(&file.size.to_le_bytes());
-    header.extend_from_slice(&data_offset.to_le_bytes());
+    Ok(decompressed)
+  }
 
-    data_offset += file.size;
+  fn slice<'a>(&self, data_bytes: &'a [u8], len: u64) -> Option<&'a [u8]> {
+    let start = usize::try_from(self.data_offset).ok()?;
+    let end = start.checked_add(usize::try_from(len).ok()?)?;
+    data_bytes.get(start..end)
   }
+}
 
-  Ok(header)
+/// Information about how an archive was built, see [`IncludeFsInner::build_info`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildInfo {
+  /// Version of the archive format.
+  pub format_version: u16,
+  /// Version of the crate that built the bundle, from `CARGO_PKG_VERSION`.
+  pub crate_version: Option<String>,
+  /// Build time in seconds since the Unix epoch, if enabled with
+  /// [`Bundle::build_timestamp`].
+  pub timestamp: Option<u64>,
+  /// Label set with [`Bundle::build_label`], such as a git commit hash.
+  pub label: Option<String>,
 }
 
-fn write_archive(files: &[FileEntry], output_path: &Path) -> Result<(), ArchiveError> {
-  let mut file = File::create(output_path)?;
+/// The parsed header of an archive.
+struct Index {
+  files: Files,
+  case_insensitive: bool,
+  build_info: BuildInfo,
+  shared_data: Option<String>,
+  /// The key check value of encrypted archives.
+  key_check: Option<Vec<u8>>,
+  #[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+  kdf: Option<KdfParams>,
+  /// Paths of the license and attribution files, sorted by directory.
+  attributions: Vec<String>,
+  /// Paths of the directories without files, see [`Bundle::empty_dirs`].
+  empty_dirs: Vec<String>,
+  checksum_algorithm: Option<ChecksumAlgorithm>,
+  /// Fingerprinted paths by original path, built on first use.
+  #[cfg(feature = "std")]
+  fingerprints: OnceLock<HashMap<String, String>>,
+  /// Directory to read files from instead of the archive, see [`Bundle::passthrough`].
+  #[cfg_attr(not(feature = "std"), allow(dead_code))]
+  source_dir: Option<String>,
+  /// The files have no data and are only read from the source directory, see
+  /// [`Bundle::embed_in_debug`].
+  stub: bool,
+}
 
-  // Write header
-  let header = compute_header(files)?;
-  file.write_all(&header)?;
+impl Index {
+  /// Look up the index entry for a path, after normalizing it.
+  fn entry(&self, archive_bytes: &[u8], path: &str) -> Option<&FsEntry> {
+    let path = path::normalize(path)?;
+    if self.case_insensitive {
+      self.files.get(archive_bytes, &path.to_lowercase())
+    } else {
+      self.files.get(archive_bytes, &path)
+    }
+  }
+}
+
+fn parse_string(value: &[u8]) -> String {
+  String::from_utf8_lossy(value).to_string()
+}
 
-  // Write file data
-  for file_entry in files {
-    let mut f = File::open(&file_entry.path)?;
-    io::copy(&mut f, &mut file)?;
+/// Parse a stored path, which has to be a valid [`EmbeddedPath`] since archives may not come
+/// from a bundle built by this crate.
+fn parse_path(value: &[u8]) -> Result<String, FsError> {
+  let path = parse_string(value);
+  match EmbeddedPath::new(&path) {
+    Ok(_) => Ok(path),
+    Err(_) => Err(FsError::InvalidArchive),
   }
+}
 
-  Ok(())
+/// Parse a list of stored paths separated by NUL bytes, see [`parse_path`].
+fn parse_paths(value: &[u8]) -> Result<Vec<String>, FsError> {
+  value.split(|&byte| byte == 0).map(parse_path).collect()
 }
 
-/// Bundle a directory to be embedded in the binary.
-/// This function must be called in a build script.
-///
-/// The directory path must be a subdirectory of the manifest directory. The name of the bundle
-/// must later be used as an argument to the `include_fs!` macro.
-///
-/// # Example
-///
-/// ```rust
-/// // In build.rs
-/// include_fs::bundle("assets", "assets").unwrap();
-/// include_fs::bundle("./static/public", "public").unwrap();
-///
-/// // In main.rs
-/// static ASSETS: IncludeFs = include_fs!("assets");
-/// static PUBLIC: IncludeFs = include_fs!("public");
-/// ```
-pub fn bundle<P: AsRef<Path>>(dir: P, bundle_name: &str) -> Result<(), ArchiveError> {
-  let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("no CARGO_MANIFEST_DIR");
-  let source_dir = Path::new(&manifest_dir).join(dir).canonicalize()?;
+/// The start time of a traced operation. `Instant::now` panics on `wasm32-unknown-unknown`, so
+/// durations are not measured there.
+#[cfg(feature = "tracing")]
+fn trace_start() -> Option<std::time::Instant> {
+  match cfg!(all(target_family = "wasm", target_os = "unknown")) {
+    true => None,
+    false => Some(std::time::Instant::now()),
+  }
+}
 
-  // Ensure the source directory is a subdirectory of the manifest directory
-  if !source_dir.starts_with(&manifest_dir) {
-    return Err(ArchiveError::InvalidSourceDirectory);
+/// Parse the index of an archive that can be read on its own, without shared data or a key.
+fn parse_standalone_index(archive_bytes: &[u8]) -> Result<Index, FsError> {
+  let index = parse_index(archive_bytes)?;
+  if let Some(name) = index.shared_data {
+    return Err(FsError::MissingSharedData(name));
   }
 
-  let relative_source_dir = source_dir.strip_prefix(&manifest_dir).unwrap();
-  println!("cargo:rerun-if-changed={}", relative_source_dir.display());
+  if index.key_check.is_some() {
+    return Err(FsError::Encrypted);
+  }
 
-  let mut files = Vec::new();
-  let walk = WalkDir::new(&source_dir).follow_links(false);
-  for entry in walk {
-    let entry = entry?;
-    let meta = entry.metadata()?;
-    if !meta.is_file() {
-      continue;
-    }
+  Ok(index)
+}
 
-    let path = entry.path().strip_prefix(&manifest_dir).unwrap();
-    files.push(FileEntry::new(path, meta.len()));
+/// Parse the index of an archive.
+fn parse_index(archive_bytes: &[u8]) -> Result<Index, FsError> {
+  #[cfg(feature = "tracing")]
+  let start = trace_start();
+
+  let index = read_index(archive_bytes, false);
+
+  #[cfg(feature = "tracing")]
+  match &index {
+    Ok(index) => tracing::debug!(
+      archive_size = archive_bytes.len(),
+      files = index.files.len(),
+      duration = ?start.map(|start| start.elapsed()),
+      "parsed archive index",
+    ),
+    Err(err) => tracing::warn!(archive_size = archive_bytes.len(), %err, "invalid archive"),
   }
 
-  let out_dir = env::var("OUT_DIR").expect("no OUT_DIR");
-  let output_file = format!("{}.embed_fs", bundle_name);
-  let output_path = Path::new(&out_dir).join(output_file);
+  index
+}
 
-  write_archive(&files, &output_path)
+/// Read the index of an archive. If `header_only` is set, `archive_bytes` only contains the
+/// header, so any lookup table is ignored.
+fn read_index(archive_bytes: &[u8], header_only: bool) -> Result<Index, FsError> {
+  if archive_bytes.get(0..4) != Some(MAGIC) {
+    return Err(FsError::InvalidArchive);
+  }
+
+  // Dispatch on the version here once there are several formats to parse
+  let format_version = *archive_bytes.get(4).ok_or(FsError::InvalidArchive)?;
+  if !(MIN_FORMAT_VERSION..=FORMAT_VERSION).contains(&format_version) {
+    return Err(FsError::UnsupportedVersion(format_version));
+  }
+
+  let file_count = read_u32(archive_bytes, 5).ok_or(FsError::InvalidArchive)? as usize;
+
+  // Check the file count before allocating for it
+  if file_count.saturating_mul(MIN_ENTRY_SIZE) > archive_bytes.len() {
+    return Err(FsError::InvalidArchive);
+  }
+
+  let mut case_insensitive = false;
+  let mut shared_data = None;
+  let mut key_check = None;
+  let mut kdf = None;
+  let mut attributions = Vec::new();
+  let mut empty_dirs = Vec::new();
+  let mut checksum_algorithm = None;
+  let mut lookup_table = None;
+  let mut sorted_index = false;
+  let mut source_dir = None;
+  let mut stub = false;
+  let mut build_info = BuildInfo {
+    format_version: format_version.into(),
+    ..Default::default()
+  };
+
+  let archive_attrs_len = read_u16(archive_bytes, 9).ok_or(FsError::InvalidArchive)? as usize;
+  let mut offset = HEADER_LEN;
+
+  let archive_attrs_end = offset + archive_attrs_len;
+  while offset < archive_attrs_end {
+    let (tag, value) = read_attribute(archive_bytes, offset).ok_or(FsError::InvalidArchive)?;
+    offset += 3 + value.len();
+
+    // Unknown attributes are skipped
+    match tag {
+      ARCHIVE_ATTR_CASE_INSENSITIVE => case_insensitive = true,
+      ARCHIVE_ATTR_CRATE_VERSION => build_info.crate_version = Some(parse_string(value)),
+      ARCHIVE_ATTR_BUILD_TIMESTAMP => {
+        build_info.timestamp = Some(read_u64(value, 0).ok_or(FsError::InvalidArchive)?);
+      }
+      ARCHIVE_ATTR_BUILD_LABEL => build_info.label = Some(parse_string(value)),
+      ARCHIVE_ATTR_SHARED_DATA => shared_data = Some(parse_string(value)),
+      ARCHIVE_ATTR_ENCRYPTED => key_check = Some(value.to_vec()),
+      ARCHIVE_ATTR_KDF => kdf = Some(KdfParams::decode(value).ok_or(FsError::InvalidArchive)?),
+      ARCHIVE_ATTR_ATTRIBUTIONS => attributions = parse_paths(value)?,
+      ARCHIVE_ATTR_CHECKSUM => {
+        checksum_algorithm = value.first().copied().and_then(ChecksumAlgorithm::from_id);
+      }
+      ARCHIVE_ATTR_LOOKUP_TABLE => {
+        let table_offset = read_u64(value, 0).ok_or(FsError::InvalidArchive)?;
+        let bucket_count = read_u32(value, 8).ok_or(FsError::InvalidArchive)?;
+        lookup_table = Some((table_offset as usize, bucket_count as usize));
+      }
+      ARCHIVE_ATTR_SOURCE_DIR => source_dir = Some(parse_string(value)),
+      ARCHIVE_ATTR_SORTED_INDEX => sorted_index = true,
+      ARCHIVE_ATTR_EMPTY_DIRS => empty_dirs = parse_paths(value)?,
+      ARCHIVE_ATTR_STUB => stub = true,
+      _ => {}
+    }
+  }
+
+  let files = match lookup_table.filter(|_| !header_only) {
+    Some((table_offset, bucket_count)) => Files::Table(LookupTable::new(
+      archive_bytes,
+      table_offset,
+      bucket_count,
+      file_count,
+    )?),
+    None if sorted_index => {
+      let mut files = Vec::with_capacity(file_count);
+      for _ in 0..file_count {
+        let (path, entry, next_offset) = parse_entry(archive_bytes, offset)?;
+        files.push((path, entry));
+        offset = next_offset;
+      }
+
+      // Bundles are sorted by path unless they use a custom order
+      if !files.is_sorted_by(|(a, _), (b, _)| a <= b) {
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+      }
+
+      Files::Sorted(files)
+    }
+    None => {
+      let mut files = HashMap::with_capacity(file_count);
+      for _ in 0..file_count {
+        let (path, entry, next_offset) = parse_entry(archive_bytes, offset)?;
+        files.insert(path, entry);
+        offset = next_offset;
+      }
+
+      Files::Map(files)
+    }
+  };
+
+  Ok(Index {
+    files,
+    case_insensitive,
+    build_info,
+    shared_data,
+    key_check,
+    kdf,
+    attributions,
+    empty_dirs,
+    checksum_algorithm,
+    #[cfg(feature = "std")]
+    fingerprints: OnceLock::new(),
+    source_dir,
+    stub,
+  })
 }
 
-struct FsEntry {
-  size: u64,
-  data_offset: u64,
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+  Some(u16::from_le_bytes(
+    bytes.get(offset..offset + 2)?.try_into().ok()?,
+  ))
 }
 
-impl FsEntry {
-  fn new(size: u64, data_offset: u64) -> Self {
-    Self { size, data_offset }
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+  Some(u32::from_le_bytes(
+    bytes.get(offset..offset + 4)?.try_into().ok()?,
+  ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+  Some(u64::from_le_bytes(
+    bytes.get(offset..offset + 8)?.try_into().ok()?,
+  ))
+}
+
+/// Read the tag and value of the attribute record at `offset`.
+fn read_attribute(bytes: &[u8], offset: usize) -> Option<(u8, &[u8])> {
+  let tag = *bytes.get(offset)?;
+  let len = read_u16(bytes, offset + 1)? as usize;
+  Some((tag, bytes.get(offset + 3..offset + 3 + len)?))
+}
+
+/// Parse the header entry at `offset`, returning its path, the entry and the offset of the next
+/// entry.
+fn parse_entry(archive_bytes: &[u8], offset: usize) -> Result<(String, FsEntry, usize), FsError> {
+  let invalid = || FsError::InvalidArchive;
+
+  let path_len = read_u16(archive_bytes, offset).ok_or_else(invalid)? as usize;
+  let mut offset = offset + 2;
+
+  let path = archive_bytes
+    .get(offset..offset + path_len)
+    .ok_or_else(invalid)?;
+  let path = parse_path(path)?;
+  offset += path_len;
+
+  let size = read_u64(archive_bytes, offset).ok_or_else(invalid)?;
+  let data_offset = read_u64(archive_bytes, offset + 8).ok_or_else(invalid)?;
+  offset += 16;
+
+  let mut entry = FsEntry::new(size, data_offset);
+
+  let attrs_len = read_u16(archive_bytes, offset).ok_or_else(invalid)? as usize;
+  offset += 2;
+
+  let attrs_end = offset + attrs_len;
+  while offset < attrs_end {
+    let (tag, value) = read_attribute(archive_bytes, offset).ok_or_else(invalid)?;
+    offset += 3 + value.len();
+
+    // Unknown attributes are skipped
+    match tag {
+      ATTR_NUL_TERMINATED => entry.nul_terminated = true,
+      ATTR_NONCE => entry.nonce = Some(value.try_into().map_err(|_| invalid())?),
+      ATTR_CHECKSUM => entry.checksum = Some(value.to_vec()),
+      ATTR_ORIGINAL_PATH => entry.original_path = Some(parse_path(value)?),
+      ATTR_COMPRESSION => {
+        let compression = value.first().copied().and_then(Compression::from_id);
+        let stored_size = read_u64(value, 1);
+        entry.compression = Some(compression.zip(stored_size).ok_or_else(invalid)?);
+      }
+      ATTR_MODIFIED => entry.modified = Some(read_u64(value, 0).ok_or_else(invalid)?),
+      ATTR_MODE => entry.mode = Some(read_u32(value, 0).ok_or_else(invalid)?),
+      ATTR_MIME => entry.mime = Some(parse_string(value)),
+      ATTR_ETAG => entry.etag = Some(parse_string(value)),
+      ATTR_ENCODINGS => {
+        if value.len() % ENCODING_LEN != 0 {
+          return Err(invalid());
+        }
+
+        // Variants in unknown encodings are skipped like unknown attributes
+        for variant in value.chunks_exact(ENCODING_LEN) {
+          if let Some(encoding) = http::Encoding::from_id(variant[0]) {
+            let offset = read_u64(variant, 1).ok_or_else(invalid)?;
+            let size = read_u64(variant, 9).ok_or_else(invalid)?;
+            entry.encodings.push((encoding, offset, size));
+          }
+        }
+      }
+      _ => {}
+    }
   }
+
+  Ok((path, entry, offset))
 }
 
 /// A lazy-loaded file system embedded in the binary.
@@ -185,7 +799,7 @@ impl FsEntry {
 /// The index will be parsed the first time it is accessed. Since only filenames are read on initialization, this should be very fast.
 /// To make sure the index is not read in a time-critical path, the lock can be manually initialized beforehand:
 ///
-/// ```rust
+/// ```rust,ignore
 /// static ASSETS: IncludeFs = include_fs!("assets");
 ///
 /// // This will block until the index fs is loaded.
@@ -193,130 +807,1049 @@ impl FsEntry {
 /// ```
 pub type IncludeFs = LazyLock<IncludeFsInner>;
 
+/// The parsed index of an embedded archive.
+///
+/// Cloning is cheap, since the index is shared between clones. This allows storing the
+/// filesystem in per-request state or moving it into spawned tasks without requiring a
+/// `&'static` reference:
+///
+/// ```rust,ignore
+/// static ASSETS: IncludeFs = include_fs!("assets");
+///
+/// let assets = IncludeFsInner::clone(&ASSETS);
+/// std::thread::spawn(move || assets.get("assets/logo.png").map(|logo| logo.len()));
+/// ```
+#[derive(Clone)]
 pub struct IncludeFsInner {
-  file_index: HashMap<String, FsEntry>,
+  index: Arc<Index>,
   archive_bytes: &'static [u8],
+  data_bytes: &'static [u8],
+  #[cfg(feature = "encryption")]
+  key: Option<crypto::Key>,
+  #[cfg(feature = "std")]
+  passthrough: Option<Arc<Passthrough>>,
+  verify_reads: bool,
+  /// The map of archives opened with [`open_mmap`](Self::open_mmap), to advise the OS about it.
+  #[cfg(feature = "mmap")]
+  mmap: Option<&'static memmap2::Mmap>,
+  #[cfg(feature = "test-util")]
+  faults: Option<Arc<test_util::Faults>>,
 }
 
 impl IncludeFsInner {
   /// Initialize a new IncludeFs from the given bytes.
   ///
+  /// The archive is borrowed rather than copied, so [`get`](Self::get) returns slices of the
+  /// embedded data and only the index is allocated.
+  ///
   /// This function is only meant to be called by the `include_fs!` macro.
   pub fn new(archive_bytes: &'static [u8]) -> Result<Self, FsError> {
-    if &archive_bytes[0..4] != MAGIC {
+    let index = parse_standalone_index(archive_bytes)?;
+    Ok(Self::with_index(index, archive_bytes))
+  }
+
+  /// An archive that contains its file data and is not encrypted.
+  fn with_index(index: Index, archive_bytes: &'static [u8]) -> Self {
+    IncludeFsInner {
+      #[cfg(feature = "std")]
+      passthrough: index
+        .source_dir
+        .clone()
+        .map(PathBuf::from)
+        .and_then(Passthrough::new)
+        .map(Arc::new),
+      index: Arc::new(index),
+      archive_bytes,
+      data_bytes: archive_bytes,
+      #[cfg(feature = "encryption")]
+      key: None,
+      verify_reads: false,
+      #[cfg(feature = "mmap")]
+      mmap: None,
+      #[cfg(feature = "test-util")]
+      faults: None,
+    }
+  }
+
+  /// Initialize a new IncludeFs from an archive built with [`Bundle::build_shared`] and its
+  /// [`SharedData`].
+  ///
+  /// This function is only meant to be called by the `include_fs!` macro.
+  pub fn with_shared_data(
+    archive_bytes: &'static [u8],
+    data_bytes: &'static [u8],
+  ) -> Result<Self, FsError> {
+    let index = parse_index(archive_bytes)?;
+    if index.shared_data.is_none() {
       return Err(FsError::InvalidArchive);
     }
 
-    let file_count = u32::from_le_bytes([
-      archive_bytes[4],
-      archive_bytes[5],
-      archive_bytes[6],
-      archive_bytes[7],
-    ]) as usize;
-
-    let mut offset = 8;
-    let mut file_index = HashMap::with_capacity(file_count);
-
-    for _ in 0..file_count {
-      let path_len =
-        u16::from_le_bytes([archive_bytes[offset], archive_bytes[offset + 1]]) as usize;
-      offset += 2;
-
-      let path = String::from_utf8_lossy(&archive_bytes[offset..offset + path_len]).to_string();
-      offset += path_len;
-
-      let size = u64::from_le_bytes([
-        archive_bytes[offset],
-        archive_bytes[offset + 1],
-        archive_bytes[offset + 2],
-        archive_bytes[offset + 3],
-        archive_bytes[offset + 4],
-        archive_bytes[offset + 5],
-        archive_bytes[offset + 6],
-        archive_bytes[offset + 7],
-      ]);
-      offset += 8;
-
-      let data_offset = u64::from_le_bytes([
-        archive_bytes[offset],
-        archive_bytes[offset + 1],
-        archive_bytes[offset + 2],
-        archive_bytes[offset + 3],
-        archive_bytes[offset + 4],
-        archive_bytes[offset + 5],
-        archive_bytes[offset + 6],
-        archive_bytes[offset + 7],
-      ]);
-      offset += 8;
-
-      file_index.insert(path, FsEntry::new(size, data_offset));
+    if index.key_check.is_some() {
+      return Err(FsError::Encrypted);
+    }
+
+    Ok(IncludeFsInner {
+      #[cfg(feature = "std")]
+      passthrough: index
+        .source_dir
+        .clone()
+        .map(PathBuf::from)
+        .and_then(Passthrough::new)
+        .map(Arc::new),
+      index: Arc::new(index),
+      archive_bytes,
+      data_bytes,
+      #[cfg(feature = "encryption")]
+      key: None,
+      verify_reads: false,
+      #[cfg(feature = "mmap")]
+      mmap: None,
+      #[cfg(feature = "test-util")]
+      faults: None,
+    })
+  }
+
+  /// Open an archive built with [`Bundle::encrypt_with_passphrase`].
+  ///
+  /// Deriving the key is deliberately slow, and files are decrypted when they are first
+  /// accessed. Returns [`FsError::InvalidPassphrase`] if the passphrase is wrong.
+  #[cfg(feature = "encryption")]
+  pub fn with_passphrase(archive_bytes: &'static [u8], passphrase: &str) -> Result<Self, FsError> {
+    let index = parse_index(archive_bytes)?;
+    let Some(kdf) = &index.kdf else {
+      return Err(FsError::InvalidArchive);
+    };
+
+    let key = crypto::derive_key(passphrase, kdf).ok_or(FsError::InvalidArchive)?;
+    Self::with_index_and_key(index, archive_bytes, key).map_err(|err| match err {
+      FsError::InvalidKey => FsError::InvalidPassphrase,
+      err => err,
+    })
+  }
+
+  /// Open an archive built with [`Bundle::encrypt_with_key`].
+  ///
+  /// Files are decrypted when they are first accessed. Returns [`FsError::InvalidKey`] if the
+  /// key is wrong.
+  #[cfg(feature = "encryption")]
+  pub fn with_key(archive_bytes: &'static [u8], key: &[u8; 32]) -> Result<Self, FsError> {
+    let index = parse_index(archive_bytes)?;
+    Self::with_index_and_key(index, archive_bytes, *key)
+  }
+
+  /// An encrypted archive that contains its file data, after checking the key.
+  #[cfg(feature = "encryption")]
+  fn with_index_and_key(
+    index: Index,
+    archive_bytes: &'static [u8],
+    key: crypto::Key,
+  ) -> Result<Self, FsError> {
+    if let Some(name) = index.shared_data {
+      return Err(FsError::MissingSharedData(name));
+    }
+
+    let Some(key_check) = &index.key_check else {
+      return Err(FsError::InvalidArchive);
+    };
+
+    if !crypto::verify_key(&key, key_check) {
+      return Err(FsError::InvalidKey);
     }
 
     Ok(IncludeFsInner {
-      file_index,
+      index: Arc::new(index),
       archive_bytes,
+      data_bytes: archive_bytes,
+      key: Some(key),
+      #[cfg(feature = "std")]
+      passthrough: None,
+      verify_reads: false,
+      #[cfg(feature = "mmap")]
+      mmap: None,
+      #[cfg(feature = "test-util")]
+      faults: None,
     })
   }
 
-  pub fn exists(&self, path: &str) -> bool {
-    self.file_index.contains_key(path)
+  /// Check files against their checksums when they are read, see [`verify`](Self::verify).
+  ///
+  /// Every file is checked the first time it is read, which is mostly useful for archives that
+  /// are not embedded in the binary. Reading a corrupt file returns
+  /// [`FsError::ChecksumMismatch`]. Disabled by default.
+  pub fn verify_reads(mut self, verify: bool) -> Self {
+    self.verify_reads = verify;
+    self
+  }
+
+  /// Look up the index entry for a path.
+  fn entry(&self, path: &str) -> Result<&FsEntry, FsError> {
+    #[cfg(feature = "test-util")]
+    if let Some(faults) = &self.faults {
+      faults.apply(path)?;
+    }
+
+    // Stubs have no data, so their files can only be read from the source directory
+    if self.is_stub_without_source() {
+      return Err(FsError::SourceDirMissing);
+    }
+
+    let entry = self.index.entry(self.archive_bytes, path);
+
+    #[cfg(feature = "tracing")]
+    tracing::trace!(path, size = entry.map(|entry| entry.size), "lookup");
+
+    entry.ok_or(FsError::NotFound)
+  }
+
+  /// Whether this is a stub without a source directory to read the files from, such as when a
+  /// debug binary was copied to another machine, see [`Bundle::embed_in_debug`].
+  fn is_stub_without_source(&self) -> bool {
+    #[cfg(feature = "std")]
+    let passthrough = self.passthrough.is_some();
+    #[cfg(not(feature = "std"))]
+    let passthrough = false;
+
+    self.index.stub && !passthrough
+  }
+
+  pub fn exists(&self, path: impl AsRef<str>) -> bool {
+    let path = path.as_ref();
+    // Only the files that are on disk exist in stub archives
+    self.entry(path).is_ok() && (!self.index.stub || matches!(self.passthrough(path), Ok(Some(_))))
+  }
+
+  pub fn get(&self, path: impl AsRef<str>) -> Result<&[u8], FsError> {
+    if let Some(data) = self.passthrough(path.as_ref())? {
+      return Ok(&data[..data.len() - 1]);
+    }
+
+    let entry = self.entry(path.as_ref())?;
+    let data = self.data(entry)?;
+    if self.verify_reads {
+      self.verify_file(path.as_ref(), entry, data)?;
+    }
+
+    Ok(data)
+  }
+
+  /// The data of a file, without its NUL terminator.
+  fn data<'a>(&'a self, entry: &'a FsEntry) -> Result<&'a [u8], FsError> {
+    if self.is_stub_without_source() {
+      return Err(FsError::SourceDirMissing);
+    }
+
+    if !entry.is_encoded() {
+      return entry.data(self.data_bytes);
+    }
+
+    let data = self.stored_data(entry)?;
+    data
+      .get(..entry.size as usize)
+      .ok_or(FsError::InvalidArchive)
+  }
+
+  /// The data of a file including its NUL terminator, decrypting and decompressing it if
+  /// necessary.
+  fn stored_data<'a>(&'a self, entry: &'a FsEntry) -> Result<&'a [u8], FsError> {
+    let stored = entry
+      .stored(self.data_bytes)
+      .ok_or(FsError::InvalidArchive)?;
+    if !entry.is_encoded() {
+      return Ok(stored);
+    }
+
+    if let Some(decoded) = entry.decoded.get() {
+      return Ok(decoded);
+    }
+
+    let data = match &entry.nonce {
+      Some(nonce) => {
+        let plaintext = self.decrypt(nonce, stored)?;
+        match entry.compression {
+          Some(_) => entry.decompress(&plaintext)?,
+          None => plaintext,
+        }
+      }
+      None => entry.decompress(stored)?,
+    };
+
+    Ok(entry.decoded.get_or_init(|| data))
+  }
+
+  #[cfg(feature = "encryption")]
+  fn decrypt(
+    &self,
+    nonce: &[u8; archive::NONCE_LEN],
+    ciphertext: &[u8],
+  ) -> Result<Vec<u8>, FsError> {
+    #[cfg(feature = "tracing")]
+    let start = trace_start();
+
+    let key = self.key.as_ref().ok_or(FsError::Encrypted)?;
+    let plaintext = crypto::decrypt(key, nonce, ciphertext).ok_or(FsError::InvalidArchive)?;
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+      size = plaintext.len(),
+      duration = ?start.map(|start| start.elapsed()),
+      "decrypted file",
+    );
+
+    Ok(plaintext)
+  }
+
+  #[cfg(not(feature = "encryption"))]
+  fn decrypt(&self, _: &[u8; archive::NONCE_LEN], _: &[u8]) -> Result<Vec<u8>, FsError> {
+    Err(FsError::Encrypted)
   }
 
-  pub fn get(&self, path: &str) -> Result<&[u8], FsError> {
-    let Some(entry) = self.file_index.get(path) else {
-      return Err(FsError::NotFound);
+  /// Passthrough reads files from disk, so it needs `std`.
+  #[cfg(not(feature = "std"))]
+  fn passthrough(&self, _: &str) -> Result<Option<&'static [u8]>, FsError> {
+    Ok(None)
+  }
+
+  /// Get the offset and length of a file within [`data_bytes`](Self::data_bytes).
+  ///
+  /// This is meant for integrations that need to hand a pointer and length to external APIs
+  /// (GPU uploads, registered IO buffers) without copying the data.
+  ///
+  /// Returns [`FsError::Encrypted`] for encrypted archives, since the stored data is not usable
+  /// directly.
+  pub fn raw_parts(&self, path: impl AsRef<str>) -> Result<(usize, usize), FsError> {
+    let entry = self.entry(path.as_ref())?;
+    if entry.nonce.is_some() {
+      return Err(FsError::Encrypted);
+    }
+
+    if entry.compression.is_some() {
+      return Err(FsError::Compressed);
+    }
+
+    let data = entry.data(self.data_bytes)?;
+    Ok((entry.data_offset as usize, data.len()))
+  }
+
+  /// The complete archive, including the header.
+  ///
+  /// The archive is not guaranteed to have any particular alignment, unless it was built with
+  /// [`Bundle::align_data`] and included with `include_fs!`.
+  pub fn archive_bytes(&self) -> &'static [u8] {
+    self.archive_bytes
+  }
+
+  /// The bytes that contain the file data. This is the archive itself, or the shared data for
+  /// archives built with [`Bundle::build_shared`].
+  pub fn data_bytes(&self) -> &'static [u8] {
+    self.data_bytes
+  }
+
+  /// Get a file as a NUL-terminated C string.
+  ///
+  /// Only files that were marked with [`Bundle::nul_terminate_extensions`] at build time have a
+  /// trailing NUL byte, other files return [`FsError::NotNulTerminated`].
+  pub fn get_cstr(&self, path: impl AsRef<str>) -> Result<&CStr, FsError> {
+    let entry = self.entry(path.as_ref())?;
+
+    if !entry.nul_terminated {
+      return Err(FsError::NotNulTerminated);
+    }
+
+    let data = match self.passthrough(path.as_ref())? {
+      Some(data) => data,
+      None => {
+        let data = self.stored_data(entry)?;
+        if self.verify_reads {
+          self.verify_file(path.as_ref(), entry, &data[..data.len().saturating_sub(1)])?;
+        }
+
+        data
+      }
     };
 
-    let start = entry.data_offset as usize;
-    let end = start + entry.size as usize;
-    Ok(&self.archive_bytes[start..end])
+    CStr::from_bytes_with_nul(data).map_err(|_| FsError::InvalidArchive)
   }
 
-  pub fn list_paths(&self) -> Vec<&str> {
-    self.file_index.keys().map(|s| s.as_str()).collect()
+  /// Get a file as [`bytes::Bytes`], without copying.
+  ///
+  /// The returned value borrows the embedded archive, so cloning and slicing it is cheap. Files
+  /// that are encrypted, compressed or read from the source directory are copied.
+  #[cfg(feature = "bytes")]
+  pub fn get_bytes(&self, path: impl AsRef<str>) -> Result<bytes::Bytes, FsError> {
+    if self.passthrough.is_some() {
+      return self.get(path).map(bytes::Bytes::copy_from_slice);
+    }
+
+    let entry = self.entry(path.as_ref())?;
+    if entry.is_encoded() {
+      return self.get(path).map(bytes::Bytes::copy_from_slice);
+    }
+
+    let data = entry.data(self.data_bytes)?;
+    if self.verify_reads {
+      self.verify_file(path.as_ref(), entry, data)?;
+    }
+
+    Ok(bytes::Bytes::from_static(data))
+  }
+
+  /// Copy a file into the given writer, returning the number of bytes written.
+  ///
+  /// Compressed files are decompressed through [`open`](Self::open) while they're written, so
+  /// large files can be streamed to sockets or disk without keeping the decompressed data.
+  #[cfg(feature = "std")]
+  pub fn write_to<W: Write + ?Sized>(
+    &self,
+    path: impl AsRef<str>,
+    out: &mut W,
+  ) -> Result<u64, FsError> {
+    let mut file = self.open(path)?;
+    Ok(std::io::copy(&mut file, out)?)
+  }
+
+  /// Touch the data of the given files, so that later accesses don't hit cold pages.
+  ///
+  /// Embedded data is paged in lazily by the OS, which can add latency to the first access of
+  /// large files, like the textures of the first frame or the response to the first request.
+  /// For archives opened with [`open_mmap`](Self::open_mmap) on Unix, the OS is asked to read
+  /// the stored files ahead with `madvise` instead, which returns without waiting for the reads.
+  /// Returns [`FsError::NotFound`] if any of the paths don't exist.
+  pub fn warm<I, S>(&self, paths: I) -> Result<(), FsError>
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    for path in paths {
+      #[cfg(all(feature = "mmap", unix))]
+      if let Some(map) = self.mmap
+        && self.passthrough.is_none()
+        && let Ok((offset, len)) = self.raw_parts(path.as_ref())
+      {
+        map.advise_range(memmap2::Advice::WillNeed, offset, len)?;
+        continue;
+      }
+
+      let data = self.get(path.as_ref())?;
+      for offset in (0..data.len()).step_by(PAGE_SIZE) {
+        core::hint::black_box(data[offset]);
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn list_paths(&self) -> Vec<&EmbeddedPath> {
+    self
+      .index
+      .files
+      .iter(self.archive_bytes)
+      .map(|(path, _)| EmbeddedPath::new_unchecked(path))
+      .collect()
+  }
+
+  /// When a file was last modified, in seconds since the Unix epoch.
+  ///
+  /// This is the modification time of the source file if it was stored with
+  /// [`Bundle::file_metadata`], otherwise the build time if it was stored with
+  /// [`Bundle::build_timestamp`], and `None` otherwise. It can be used for `Last-Modified`
+  /// headers, see [`http`].
+  pub fn last_modified(&self, path: impl AsRef<str>) -> Result<Option<u64>, FsError> {
+    let entry = self.entry(path.as_ref())?;
+    Ok(entry.modified.or(self.index.build_info.timestamp))
+  }
+
+  /// The index entry of a file, with its size and metadata.
+  pub fn metadata(&self, path: impl AsRef<str>) -> Result<&FsEntry, FsError> {
+    self.entry(path.as_ref())
+  }
+
+  /// The checksum of a file stored with [`Bundle::checksums`], or `None` if the archive has no
+  /// checksums.
+  pub fn checksum(&self, path: impl AsRef<str>) -> Result<Option<&[u8]>, FsError> {
+    Ok(self.entry(path.as_ref())?.checksum.as_deref())
+  }
+
+  /// The `ETag` of a file stored with [`Bundle::etags`], see [`FsEntry::etag`].
+  pub fn etag(&self, path: impl AsRef<str>) -> Result<Option<&str>, FsError> {
+    Ok(self.entry(path.as_ref())?.etag())
+  }
+
+  /// The precompressed variant of a file in the given encoding, stored with
+  /// [`Bundle::precompress`], or `None` if the file has no such variant.
+  ///
+  /// The variant can be sent as the body of an HTTP response with a matching
+  /// `Content-Encoding`, without compressing anything at runtime. Files read from the source
+  /// directory have no variants, so they are sent as they are in debug builds.
+  pub fn get_encoded(
+    &self,
+    path: impl AsRef<str>,
+    encoding: http::Encoding,
+  ) -> Result<Option<&'static [u8]>, FsError> {
+    if self.passthrough(path.as_ref())?.is_some() {
+      return Ok(None);
+    }
+
+    let entry = self.entry(path.as_ref())?;
+    let Some(&(_, offset, size)) = entry.encodings.iter().find(|(e, _, _)| *e == encoding) else {
+      return Ok(None);
+    };
+
+    let data_bytes = self.data_bytes;
+    let start = usize::try_from(offset).map_err(|_| FsError::InvalidArchive)?;
+    let end = usize::try_from(size)
+      .ok()
+      .and_then(|size| start.checked_add(size))
+      .ok_or(FsError::InvalidArchive)?;
+    data_bytes
+      .get(start..end)
+      .map(Some)
+      .ok_or(FsError::InvalidArchive)
+  }
+
+  /// The algorithm of the checksums stored with [`Bundle::checksums`].
+  pub fn checksum_algorithm(&self) -> Option<ChecksumAlgorithm> {
+    self.index.checksum_algorithm
+  }
+
+  /// Information about how the archive was built.
+  ///
+  /// This can be used to confirm exactly which asset build a binary contains.
+  pub fn build_info(&self) -> &BuildInfo {
+    &self.index.build_info
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use archive::{ArchiveOptions, FileEntry, write_archive, write_shared_archive};
 
+  /// Build an in-memory filesystem from the given files.
+  pub(crate) fn test_fs(files: &[(&str, &[u8])]) -> IncludeFsInner {
+    let entries: Vec<_> = files
+      .iter()
+      .map(|(path, data)| FileEntry::new(*path, *data))
+      .collect();
+
+    test_fs_with(&entries, &ArchiveOptions::default())
+  }
+
+  /// Build an in-memory filesystem from the given entries and archive options.
+  pub(crate) fn test_fs_with(files: &[FileEntry], options: &ArchiveOptions) -> IncludeFsInner {
+    let mut archive = Vec::new();
+    write_archive(files, options, &mut archive).unwrap();
+    IncludeFsInner::new(Box::leak(archive.into_boxed_slice())).unwrap()
+  }
+
+  #[cfg(feature = "bytes")]
   #[test]
-  fn test_compute_header() {
-    let files = vec![
-      FileEntry::new("src/main.rs", 1024),
-      FileEntry::new("assets/image.png", 2048),
+  fn test_get_bytes() {
+    let fs = test_fs(&[("index.html", b"<html></html>")]);
+    let bytes = fs.get_bytes("index.html").unwrap();
+    assert_eq!(bytes, &b"<html></html>"[..]);
+    assert_eq!(bytes.as_ptr(), fs.get("index.html").unwrap().as_ptr());
+  }
+
+  #[test]
+  fn test_clone_shares_index() {
+    let fs = test_fs(&[("a.txt", b"a")]);
+    let clone = fs.clone();
+    assert!(Arc::ptr_eq(&fs.index, &clone.index));
+    assert_eq!(clone.get("a.txt").unwrap(), b"a");
+  }
+
+  #[test]
+  fn test_corrupt_archives() {
+    let mut shader = FileEntry::new("shader.glsl", "void main() {}");
+    shader.nul_terminated = true;
+    let files = [shader, FileEntry::new("dir/a.txt", "a")];
+
+    for (lookup_table, sorted_index) in [(false, false), (true, false), (false, true)] {
+      let options = ArchiveOptions {
+        lookup_table,
+        sorted_index,
+        build_timestamp: Some(1_700_000_000),
+        ..Default::default()
+      };
+
+      let mut archive = Vec::new();
+      write_archive(&files, &options, &mut archive).unwrap();
+
+      let mut corrupted = Vec::new();
+      for len in 0..archive.len() {
+        corrupted.push(archive[..len].to_vec());
+      }
+
+      for i in 0..archive.len() {
+        for byte in [0x00, 0x7f, 0xff] {
+          let mut archive = archive.clone();
+          archive[i] = byte;
+          corrupted.push(archive);
+        }
+      }
+
+      // Corrupt archives have to be rejected or return errors, but never panic
+      for archive in corrupted {
+        let Ok(fs) = IncludeFsInner::new(Box::leak(archive.into_boxed_slice())) else {
+          continue;
+        };
+
+        for path in ["shader.glsl", "dir/a.txt", "missing"] {
+          let _ = fs.get(path);
+          let _ = fs.get_cstr(path);
+          let _ = fs.raw_parts(path);
+          let _ = fs.checksum(path);
+        }
+
+        for path in fs.list_paths() {
+          let _ = fs.get(path);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_rejects_invalid_paths() {
+    /// Write an archive and replace `from` with `to` in it, which have the same length.
+    fn patched(files: &[FileEntry], options: &ArchiveOptions, from: &str, to: &str) -> Vec<u8> {
+      let mut archive = Vec::new();
+      write_archive(files, options, &mut archive).unwrap();
+      let pos = archive
+        .windows(from.len())
+        .position(|window| window == from.as_bytes())
+        .unwrap();
+      archive[pos..pos + to.len()].copy_from_slice(to.as_bytes());
+      archive
+    }
+
+    let files = [
+      FileEntry::new("a.txt", "a"),
+      FileEntry::new("_tmp/x.txt", "x"),
     ];
+    for (from, to) in [
+      ("_tmp/x", "/tmp/x"),
+      ("_tmp/x", "../x.t"),
+      ("_tmp/x", "tmp//x"),
+    ] {
+      for sorted_index in [false, true] {
+        let options = ArchiveOptions {
+          sorted_index,
+          ..Default::default()
+        };
 
-    let header = compute_header(&files).unwrap();
+        let archive = patched(&files, &options, from, to);
+        assert!(matches!(
+          IncludeFsInner::new(Box::leak(archive.into_boxed_slice())),
+          Err(FsError::InvalidArchive)
+        ));
+      }
 
-    // Verify magic
-    assert_eq!(&header[0..4], b"INFS");
+      // Entries of lookup tables are parsed lazily, so invalid entries are skipped
+      let options = ArchiveOptions {
+        lookup_table: true,
+        ..Default::default()
+      };
 
-    // Verify file count
-    let file_count = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
-    assert_eq!(file_count, 2);
+      let archive = patched(&files, &options, from, to);
+      let fs = IncludeFsInner::new(Box::leak(archive.into_boxed_slice())).unwrap();
+      assert_eq!(fs.list_paths(), [EmbeddedPath::new("a.txt").unwrap()]);
+      assert!(matches!(fs.get("tmp/x.txt"), Err(FsError::NotFound)));
+    }
 
-    // Basic size check (exact calculation depends on path lengths)
-    let expected_min_size = 4 + 4 + // magic + count
-      2 + "src/main.rs".len() + 8 + 8 + // first file
-      2 + "assets/image.png".len() + 8 + 8; // second file
+    let options = ArchiveOptions {
+      empty_dirs: vec!["_dir".to_string()],
+      ..Default::default()
+    };
+
+    let archive = patched(&files[..1], &options, "_dir", "/dir");
+    assert!(matches!(
+      IncludeFsInner::new(Box::leak(archive.into_boxed_slice())),
+      Err(FsError::InvalidArchive)
+    ));
+  }
+
+  #[test]
+  fn test_get_is_zero_copy() {
+    let fs = test_fs(&[("a.txt", b"a"), ("b.txt", b"b")]);
+    let archive = fs.archive_bytes().as_ptr_range();
+    for path in ["a.txt", "b.txt"] {
+      assert!(archive.contains(&fs.get(path).unwrap().as_ptr()));
+    }
+  }
+
+  #[test]
+  fn test_get_cstr() {
+    let mut shader = FileEntry::new("shader.glsl", "void main() {}");
+    shader.nul_terminated = true;
+
+    let files = [shader, FileEntry::new("a.txt", "a")];
+    let fs = test_fs_with(&files, &ArchiveOptions::default());
+
+    assert_eq!(fs.get_cstr("shader.glsl").unwrap(), c"void main() {}");
+    assert_eq!(fs.get("shader.glsl").unwrap(), b"void main() {}");
+    assert_eq!(fs.get("a.txt").unwrap(), b"a");
+    assert!(matches!(
+      fs.get_cstr("a.txt"),
+      Err(FsError::NotNulTerminated)
+    ));
+  }
+
+  #[test]
+  fn test_raw_parts() {
+    let fs = test_fs(&[("a.bin", &[1, 2, 3]), ("b.bin", &[4, 5])]);
+    let (offset, len) = fs.raw_parts("b.bin").unwrap();
+    assert_eq!(&fs.archive_bytes()[offset..offset + len], &[4, 5]);
+    assert!(matches!(fs.raw_parts("c.bin"), Err(FsError::NotFound)));
+  }
+
+  #[test]
+  fn test_lookup_normalization() {
+    let fs = test_fs(&[("assets/logo.png", b"png"), ("secret.txt", b"secret")]);
+    assert_eq!(fs.get("assets/./logo.png").unwrap(), b"png");
+    assert_eq!(fs.get("/assets//logo.png").unwrap(), b"png");
+    assert_eq!(fs.get("assets/icons/../logo.png").unwrap(), b"png");
+    assert_eq!(fs.get("assets\\logo.png").unwrap(), b"png");
+    assert!(fs.exists("assets/../secret.txt"));
+    assert!(matches!(fs.get("../secret.txt"), Err(FsError::NotFound)));
+    assert!(!fs.exists("assets/../../secret.txt"));
+  }
+
+  #[test]
+  fn test_case_insensitive_lookup() {
+    let options = ArchiveOptions {
+      case_insensitive: true,
+      ..Default::default()
+    };
+
+    let fs = test_fs_with(&[FileEntry::new("logo.png", "png")], &options);
+
+    assert_eq!(fs.get("Logo.PNG").unwrap(), b"png");
+    assert!(fs.exists("logo.png"));
+  }
+
+  #[test]
+  fn test_unsupported_version() {
+    let mut archive = Vec::new();
+    write_archive(
+      &[FileEntry::new("a.txt", "a")],
+      &ArchiveOptions::default(),
+      &mut archive,
+    )
+    .unwrap();
+
+    archive[4] = FORMAT_VERSION + 1;
+    assert!(matches!(
+      IncludeFsInner::new(Box::leak(archive.into_boxed_slice())),
+      Err(FsError::UnsupportedVersion(version)) if version == FORMAT_VERSION + 1
+    ));
+  }
+
+  #[test]
+  fn test_build_info() {
+    let options = ArchiveOptions {
+      crate_version: Some("1.2.3".to_string()),
+      build_timestamp: Some(1_700_000_000),
+      build_label: Some("abc123".to_string()),
+      ..Default::default()
+    };
+
+    let fs = test_fs_with(&[FileEntry::new("a.txt", "a")], &options);
+
+    let info = fs.build_info();
+    assert_eq!(info.format_version, archive::FORMAT_VERSION as u16);
+    assert_eq!(info.crate_version.as_deref(), Some("1.2.3"));
+    assert_eq!(info.timestamp, Some(1_700_000_000));
+    assert_eq!(info.label.as_deref(), Some("abc123"));
+    assert_eq!(fs.last_modified("a.txt").unwrap(), Some(1_700_000_000));
+    assert!(matches!(fs.last_modified("b.txt"), Err(FsError::NotFound)));
+    assert_eq!(fs.get("a.txt").unwrap(), b"a");
+  }
+
+  #[test]
+  fn test_write_to() {
+    let data = vec![7; 200 * 1024 + 1];
+    let fs = test_fs(&[("big.bin", &data)]);
+
+    let mut out = Vec::new();
+    assert_eq!(fs.write_to("big.bin", &mut out).unwrap(), data.len() as u64);
+    assert_eq!(out, data);
+
+    assert!(matches!(
+      fs.write_to("missing.bin", &mut out),
+      Err(FsError::NotFound)
+    ));
+  }
+
+  #[test]
+  fn test_shared_data() {
+    let options = ArchiveOptions {
+      shared_data: Some("shared".to_string()),
+      ..Default::default()
+    };
+
+    let mut shared = SharedData::new("shared");
+    let mut archives = Vec::new();
+    for files in [
+      [("icon.svg", "icon"), ("dark.css", "dark")],
+      [("icon.svg", "icon"), ("light.css", "light")],
+    ] {
+      let entries: Vec<_> = files
+        .iter()
+        .map(|(path, data)| FileEntry::new(*path, *data))
+        .collect();
+      let mut archive = Vec::new();
+      write_shared_archive(&entries, &options, &mut shared, &mut archive).unwrap();
+      archives.push(&*Box::leak(archive.into_boxed_slice()));
+    }
+
+    let data: &'static [u8] = Box::leak(shared.data().to_vec().into_boxed_slice());
+    assert_eq!(data, b"icondarklight");
+
+    let dark = IncludeFsInner::with_shared_data(archives[0], data).unwrap();
+    let light = IncludeFsInner::with_shared_data(archives[1], data).unwrap();
+    assert_eq!(dark.get("dark.css").unwrap(), b"dark");
+    assert_eq!(light.get("light.css").unwrap(), b"light");
+    assert_eq!(
+      dark.get("icon.svg").unwrap().as_ptr(),
+      light.get("icon.svg").unwrap().as_ptr()
+    );
+
+    assert!(matches!(
+      IncludeFsInner::new(archives[0]),
+      Err(FsError::MissingSharedData(name)) if name == "shared"
+    ));
+  }
+
+  #[test]
+  fn test_checksums() {
+    let mut file = FileEntry::new("a.txt", "hello");
+    file.checksum = ChecksumAlgorithm::Crc32.compute(&file.data);
+    let options = ArchiveOptions {
+      checksum: Some(ChecksumAlgorithm::Crc32),
+      ..Default::default()
+    };
+
+    let fs = test_fs_with(&[file], &options);
+
+    assert_eq!(fs.checksum_algorithm(), Some(ChecksumAlgorithm::Crc32));
+    assert_eq!(
+      fs.checksum("a.txt").unwrap(),
+      Some(&0x3610a686u32.to_le_bytes()[..])
+    );
+    assert_eq!(
+      test_fs(&[("a.txt", b"hello")]).checksum("a.txt").unwrap(),
+      None
+    );
+  }
+
+  #[test]
+  fn test_lookup_table() {
+    let files: Vec<_> = (0..50)
+      .map(|i| FileEntry::new(format!("dir/file{i}.txt"), format!("data {i}")))
+      .collect();
+
+    for case_insensitive in [false, true] {
+      let options = ArchiveOptions {
+        case_insensitive,
+        lookup_table: true,
+        ..Default::default()
+      };
+
+      let fs = test_fs_with(&files, &options);
+      assert!(matches!(fs.index.files, Files::Table(_)));
+
+      assert_eq!(fs.get("dir/file7.txt").unwrap(), b"data 7");
+      assert_eq!(fs.get("dir/file49.txt").unwrap(), b"data 49");
+      assert_eq!(fs.exists("DIR/FILE3.TXT"), case_insensitive);
+      assert!(matches!(fs.get("dir/file50.txt"), Err(FsError::NotFound)));
+      assert_eq!(fs.list_paths().len(), 50);
+    }
+
+    let options = ArchiveOptions {
+      shared_data: Some("shared".to_string()),
+      lookup_table: true,
+      ..Default::default()
+    };
+
+    let mut shared = SharedData::new("shared");
+    let mut archive = Vec::new();
+    write_shared_archive(&files, &options, &mut shared, &mut archive).unwrap();
+    let data: &'static [u8] = Box::leak(shared.data().to_vec().into_boxed_slice());
+    let fs = IncludeFsInner::with_shared_data(Box::leak(archive.into_boxed_slice()), data).unwrap();
+    assert_eq!(fs.get("dir/file12.txt").unwrap(), b"data 12");
+
+    // A table extending past the end of the archive is rejected
+    let options = ArchiveOptions {
+      lookup_table: true,
+      ..Default::default()
+    };
+
+    let mut archive = Vec::new();
+    write_archive(&files, &options, &mut archive).unwrap();
+    archive.truncate(archive.len() - 1);
+    assert!(matches!(
+      IncludeFsInner::new(Box::leak(archive.into_boxed_slice())),
+      Err(FsError::InvalidArchive)
+    ));
+  }
+
+  #[test]
+  fn test_sorted_index() {
+    // Written in a custom order, which the reader sorts
+    let files: Vec<_> = ["b/2.txt", "a.txt", "b/1.txt", "b/c/3.txt", "ba.txt"]
+      .into_iter()
+      .map(|path| FileEntry::new(path, path))
+      .collect();
+
+    let options = ArchiveOptions {
+      sorted_index: true,
+      ..Default::default()
+    };
+
+    let fs = test_fs_with(&files, &options);
+    assert!(matches!(fs.index.files, Files::Sorted(_)));
+
+    assert_eq!(fs.get("b/c/3.txt").unwrap(), b"b/c/3.txt");
+    assert!(matches!(fs.get("b/3.txt"), Err(FsError::NotFound)));
+    let paths: Vec<_> = fs.list_paths().iter().map(|path| path.as_str()).collect();
+    assert_eq!(
+      paths,
+      ["a.txt", "b/1.txt", "b/2.txt", "b/c/3.txt", "ba.txt"]
+    );
+
+    let dir: Vec<_> = fs
+      .read_dir("b")
+      .unwrap()
+      .iter()
+      .map(DirEntry::path)
+      .collect();
+    assert_eq!(dir, ["b/1.txt", "b/2.txt", "b/c"]);
+    assert!(fs.is_dir("b/c"));
+    assert!(!fs.is_dir("b/1"));
+    assert_eq!(fs.read_dir("").unwrap().len(), 3);
+  }
 
-    assert_eq!(header.len(), expected_min_size);
+  #[cfg(feature = "encryption")]
+  #[test]
+  fn test_with_passphrase() {
+    let kdf = KdfParams {
+      m_cost: 8,
+      t_cost: 1,
+      p_cost: 1,
+      salt: vec![1; 16],
+    };
+
+    let key = crypto::derive_key("secret", &kdf).unwrap();
+    let mut files = vec![
+      FileEntry::new("a.txt", "licensed"),
+      FileEntry::new("b.glsl", "void"),
+    ];
+    files[1].nul_terminated = true;
+    for file in &mut files {
+      crypto::encrypt_file(&key, file);
+    }
+
+    let options = ArchiveOptions {
+      key_check: Some(crypto::key_check(&key)),
+      kdf: Some(kdf),
+      ..Default::default()
+    };
+
+    let mut archive = Vec::new();
+    write_archive(&files, &options, &mut archive).unwrap();
+    let archive: &'static [u8] = Box::leak(archive.into_boxed_slice());
+
+    assert!(matches!(
+      IncludeFsInner::new(archive),
+      Err(FsError::Encrypted)
+    ));
+    assert!(matches!(
+      IncludeFsInner::with_passphrase(archive, "wrong"),
+      Err(FsError::InvalidPassphrase)
+    ));
+
+    let fs = IncludeFsInner::with_passphrase(archive, "secret").unwrap();
+    assert_eq!(fs.get("a.txt").unwrap(), b"licensed");
+    assert_eq!(fs.get_cstr("b.glsl").unwrap(), c"void");
+    assert!(matches!(fs.raw_parts("a.txt"), Err(FsError::Encrypted)));
+    assert!(!archive.windows(8).any(|window| window == b"licensed"));
   }
 
+  #[cfg(feature = "encryption")]
   #[test]
-  fn test_path_too_long() {
-    let long_path = "a".repeat(u16::MAX as usize + 1);
-    let files = vec![FileEntry::new(long_path.clone(), 100)];
+  fn test_with_key() {
+    let key = [7; 32];
+    let mut file = FileEntry::new("seed.json", "credentials");
+    crypto::encrypt_file(&key, &mut file);
+
+    let options = ArchiveOptions {
+      key_check: Some(crypto::key_check(&key)),
+      ..Default::default()
+    };
+
+    let mut archive = Vec::new();
+    write_archive(&[file], &options, &mut archive).unwrap();
+    let archive: &'static [u8] = Box::leak(archive.into_boxed_slice());
 
-    let result = compute_header(&files);
-    assert!(matches!(result, Err(ArchiveError::PathTooLong { .. })));
+    assert!(matches!(
+      IncludeFsInner::with_key(archive, &[8; 32]),
+      Err(FsError::InvalidKey)
+    ));
+    assert!(matches!(
+      IncludeFsInner::with_passphrase(archive, "secret"),
+      Err(FsError::InvalidArchive)
+    ));
 
-    if let Err(ArchiveError::PathTooLong { path, len, max }) = result {
-      assert_eq!(path, long_path);
-      assert_eq!(len, u16::MAX as usize + 1);
-      assert_eq!(max, u16::MAX as usize);
+    let fs = IncludeFsInner::with_key(archive, &key).unwrap();
+    assert_eq!(fs.get("seed.json").unwrap(), b"credentials");
+    assert!(!archive.windows(11).any(|window| window == b"credentials"));
+  }
+
+  #[cfg(feature = "zstd")]
+  #[test]
+  fn test_compression() {
+    let text = "compressible ".repeat(100);
+    let mut files = vec![
+      FileEntry::new("a.txt", text.as_str()),
+      FileEntry::new("b.glsl", "void main() {}"),
+    ];
+    files[1].nul_terminated = true;
+    for file in &mut files {
+      compression::compress_file(file, Compression::Zstd).unwrap();
     }
+
+    let mut archive = Vec::new();
+    write_archive(&files, &ArchiveOptions::default(), &mut archive).unwrap();
+    assert!(archive.len() < text.len());
+
+    let fs = IncludeFsInner::new(Box::leak(archive.into_boxed_slice())).unwrap();
+    let mut out = Vec::new();
+    assert_eq!(fs.write_to("a.txt", &mut out).unwrap(), text.len() as u64);
+    assert_eq!(out, text.as_bytes());
+    // Streaming doesn't keep the decompressed data
+    assert!(fs.entry("a.txt").unwrap().decoded.get().is_none());
+
+    assert_eq!(fs.get("a.txt").unwrap(), text.as_bytes());
+    assert_eq!(fs.get_cstr("b.glsl").unwrap(), c"void main() {}");
+    assert_eq!(fs.get("b.glsl").unwrap(), b"void main() {}");
+
+    // Decompressed data is kept, so later reads return the same buffer
+    assert_eq!(
+      fs.get("a.txt").unwrap().as_ptr(),
+      fs.get("a.txt").unwrap().as_ptr()
+    );
+    assert!(matches!(fs.raw_parts("a.txt"), Err(FsError::Compressed)));
+  }
+
+  #[test]
+  fn test_warm() {
+    let data = vec![1; PAGE_SIZE * 3];
+    let fs = test_fs(&[("big.bin", &data), ("small.txt", b"a")]);
+
+    fs.warm(["big.bin", "small.txt"]).unwrap();
+    assert!(matches!(
+      fs.warm(["small.txt", "missing.txt"]),
+      Err(FsError::NotFound)
+    ));
   }
 }
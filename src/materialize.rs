@@ -0,0 +1,204 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::IncludeFsInner;
+use crate::archive::Fnv1a;
+
+/// Number of extractions started by this process, which keeps their temporary directories apart.
+static EXTRACTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Extract a subtree of an embedded filesystem to the platform cache directory, returning the
+/// path of the extracted subtree.
+///
+/// This is meant for files that have to exist on disk, like helper binaries that are executed or
+/// plugins that are loaded with `dlopen`. The files are extracted into a directory named after
+/// their content hash, so they are only extracted once, and a new directory is used whenever
+/// the contents change. Extraction happens in a temporary directory that is renamed when
+/// complete, so concurrent processes and threads never observe partially written files.
+///
+/// On Unix, extracted files are made executable. An empty `subtree` extracts all files.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// static TOOLS: IncludeFs = include_fs!("tools");
+///
+/// let tools = include_fs::materialize(&TOOLS, "tools/bin")?;
+/// std::process::Command::new(tools.join("helper")).status()?;
+/// ```
+pub fn materialize(fs: &IncludeFsInner, subtree: &str) -> io::Result<PathBuf> {
+  materialize_in(fs, subtree, &cache_dir().join("include-fs"))
+}
+
+fn materialize_in(fs: &IncludeFsInner, subtree: &str, cache_dir: &Path) -> io::Result<PathBuf> {
+  let subtree = subtree.trim_end_matches('/');
+
+  #[cfg(feature = "tracing")]
+  let _span = tracing::info_span!("materialize", subtree).entered();
+  #[cfg(feature = "tracing")]
+  let start = std::time::Instant::now();
+  let mut paths: Vec<_> = fs
+    .list_paths()
+    .into_iter()
+    .filter(|path| {
+      subtree.is_empty()
+        || path.as_str() == subtree
+        || path
+          .strip_prefix(subtree)
+          .is_some_and(|rest| rest.starts_with('/'))
+    })
+    .collect();
+
+  if paths.is_empty() {
+    return Err(io::Error::new(
+      io::ErrorKind::NotFound,
+      format!("No embedded files in {subtree}"),
+    ));
+  }
+
+  paths.sort();
+
+  let mut hash = Fnv1a::default();
+  for path in &paths {
+    let data = fs.get(path).map_err(io::Error::other)?;
+    hash.write(path.as_bytes());
+    hash.write(&(data.len() as u64).to_le_bytes());
+    hash.write(data);
+  }
+
+  let target = cache_dir.join(format!("{:016x}", hash.0));
+  if !target.exists() {
+    let tmp = cache_dir.join(format!(
+      "{:016x}.{}.{}.tmp",
+      hash.0,
+      std::process::id(),
+      EXTRACTIONS.fetch_add(1, Ordering::Relaxed),
+    ));
+    let _ = fs::remove_dir_all(&tmp);
+
+    for path in &paths {
+      let file_path = tmp.join(path.as_str());
+      fs::create_dir_all(file_path.parent().unwrap())?;
+      fs::write(&file_path, fs.get(path).map_err(io::Error::other)?)?;
+      make_executable(&file_path)?;
+    }
+
+    // Another process may have finished extracting the same files first
+    if let Err(err) = fs::rename(&tmp, &target) {
+      let _ = fs::remove_dir_all(&tmp);
+      if !target.exists() {
+        return Err(err);
+      }
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+      files = paths.len(),
+      target = %target.display(),
+      duration = ?start.elapsed(),
+      "extracted files",
+    );
+  }
+
+  Ok(target.join(subtree))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> io::Result<()> {
+  use std::os::unix::fs::PermissionsExt;
+  fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> io::Result<()> {
+  Ok(())
+}
+
+/// The platform cache directory, falling back to the temporary directory.
+fn cache_dir() -> PathBuf {
+  let dir = if cfg!(windows) {
+    env::var_os("LOCALAPPDATA").map(PathBuf::from)
+  } else if cfg!(target_os = "macos") {
+    env::var_os("HOME").map(|home| Path::new(&home).join("Library/Caches"))
+  } else {
+    env::var_os("XDG_CACHE_HOME")
+      .map(PathBuf::from)
+      .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+  };
+
+  dir.unwrap_or_else(env::temp_dir)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_materialize() {
+    let cache_dir = env::temp_dir().join(format!("include-fs-materialize-{}", std::process::id()));
+    let fs = test_fs(&[
+      ("tools/bin/helper", b"#!/bin/sh"),
+      ("tools/lib/plugin.so", b"plugin"),
+      ("tools/binary", b"other"),
+    ]);
+
+    let bin = materialize_in(&fs, "tools/bin", &cache_dir).unwrap();
+    assert!(bin.ends_with("tools/bin"));
+    assert_eq!(fs::read(bin.join("helper")).unwrap(), b"#!/bin/sh");
+    assert!(!bin.parent().unwrap().join("binary").exists());
+
+    // The same contents are not extracted again
+    fs::write(bin.join("helper"), "modified").unwrap();
+    assert_eq!(materialize_in(&fs, "tools/bin/", &cache_dir).unwrap(), bin);
+    assert_eq!(fs::read(bin.join("helper")).unwrap(), b"modified");
+
+    let all = materialize_in(&fs, "", &cache_dir).unwrap();
+    assert_ne!(all, bin.parent().unwrap().parent().unwrap());
+    assert!(all.join("tools/lib/plugin.so").exists());
+
+    assert!(materialize_in(&fs, "missing", &cache_dir).is_err());
+    fs::remove_dir_all(&cache_dir).unwrap();
+  }
+
+  #[test]
+  fn test_materialize_concurrently() {
+    let cache_dir = env::temp_dir().join(format!(
+      "include-fs-materialize-threads-{}",
+      std::process::id()
+    ));
+    let data = vec![7; 256 * 1024];
+    let files: Vec<_> = (0..16).map(|i| format!("tools/{i}.bin")).collect();
+    let entries: Vec<_> = files
+      .iter()
+      .map(|path| (path.as_str(), &data[..]))
+      .collect();
+    let fs = test_fs(&entries);
+
+    // Threads extracting the same files never see each other's partial trees
+    let dirs: Vec<_> = std::thread::scope(|scope| {
+      let threads: Vec<_> = (0..8)
+        .map(|_| scope.spawn(|| materialize_in(&fs, "tools", &cache_dir).unwrap()))
+        .collect();
+      threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .collect()
+    });
+
+    for dir in &dirs {
+      assert_eq!(dir, &dirs[0]);
+    }
+    for path in &files {
+      assert_eq!(
+        fs::read(dirs[0].join(&path["tools/".len()..])).unwrap(),
+        data
+      );
+    }
+
+    fs::remove_dir_all(&cache_dir).unwrap();
+  }
+}
@@ -0,0 +1,73 @@
+use crate::{FsEntry, FsError, IncludeFsInner};
+
+/// MIME type of files whose type can't be guessed from their extension.
+pub const DEFAULT_MIME: &str = "application/octet-stream";
+
+/// Guess the MIME type of a path from its extension.
+pub(crate) fn guess(path: &str) -> Option<&'static str> {
+  mime_guess::from_path(path).first_raw()
+}
+
+impl FsEntry {
+  /// The MIME type of the file, if it was stored with [`Bundle::mime_types`].
+  ///
+  /// [`Bundle::mime_types`]: crate::Bundle::mime_types
+  pub fn mime(&self) -> Option<&str> {
+    self.mime.as_deref()
+  }
+}
+
+impl IncludeFsInner {
+  /// The MIME type of a file, like `text/html` for `index.html`.
+  ///
+  /// This is the type stored with [`Bundle::mime_types`] if there is one, and otherwise guessed
+  /// from the extension. Files with unknown extensions are [`DEFAULT_MIME`].
+  ///
+  /// [`Bundle::mime_types`]: crate::Bundle::mime_types
+  pub fn mime(&self, path: impl AsRef<str>) -> Result<&str, FsError> {
+    let path = path.as_ref();
+    let entry = self.entry(path)?;
+    Ok(entry.mime().or_else(|| guess(path)).unwrap_or(DEFAULT_MIME))
+  }
+
+  /// Get a file along with its MIME type, see [`mime`](Self::mime).
+  pub fn get_with_mime(&self, path: impl AsRef<str>) -> Result<(&[u8], &str), FsError> {
+    let path = path.as_ref();
+    Ok((self.get(path)?, self.mime(path)?))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::archive::{ArchiveOptions, FileEntry};
+  use crate::tests::test_fs_with;
+
+  #[test]
+  fn test_mime() {
+    let mut stored = FileEntry::new("data.bin", "{}");
+    stored.mime = Some("application/json".to_string());
+    let files = [
+      stored,
+      FileEntry::new("index.html", "<html></html>"),
+      FileEntry::new("style.CSS", ""),
+      FileEntry::new("LICENSE", ""),
+    ];
+
+    let fs = test_fs_with(&files, &ArchiveOptions::default());
+
+    assert_eq!(fs.mime("data.bin").unwrap(), "application/json");
+    assert_eq!(
+      fs.metadata("data.bin").unwrap().mime(),
+      Some("application/json")
+    );
+    assert_eq!(
+      fs.get_with_mime("index.html").unwrap(),
+      (&b"<html></html>"[..], "text/html")
+    );
+    assert_eq!(fs.metadata("index.html").unwrap().mime(), None);
+    assert_eq!(fs.mime("style.CSS").unwrap(), "text/css");
+    assert_eq!(fs.mime("LICENSE").unwrap(), DEFAULT_MIME);
+    assert!(matches!(fs.mime("missing.txt"), Err(FsError::NotFound)));
+  }
+}
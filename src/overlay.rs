@@ -0,0 +1,250 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::{DirEntry, EmbeddedPath, FsEntry, FsError, IncludeFsInner};
+
+/// Several bundles layered on top of each other, where files in later bundles replace files with
+/// the same path in earlier ones.
+///
+/// This keeps default assets in one bundle and lets optional bundles override some of them, like
+/// themes, plugins or locales:
+///
+/// ```rust,ignore
+/// static BASE: IncludeFs = include_fs!("base");
+/// static THEME: IncludeFs = include_fs!("theme");
+///
+/// let assets = OverlayFs::new([&BASE, &THEME]);
+///
+/// // From the theme if it has a stylesheet, otherwise from the base bundle
+/// let style = assets.get("style.css")?;
+/// ```
+///
+/// Directories are merged, so [`read_dir`](Self::read_dir) lists the entries of a directory in
+/// every layer. A file shadows the directories with the same path in the layers below it, along
+/// with everything inside them.
+#[derive(Clone, Default)]
+pub struct OverlayFs<'a> {
+  /// The layers, from the lowest to the highest precedence.
+  layers: Vec<&'a IncludeFsInner>,
+}
+
+impl fmt::Debug for OverlayFs<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("OverlayFs")
+      .field("layers", &self.layers.len())
+      .finish_non_exhaustive()
+  }
+}
+
+impl<'a> OverlayFs<'a> {
+  /// Layer bundles in order of increasing precedence, so the last bundle that contains a file
+  /// is the one it is read from.
+  pub fn new<L>(layers: impl IntoIterator<Item = &'a L>) -> Self
+  where
+    L: Deref<Target = IncludeFsInner> + 'a,
+  {
+    Self {
+      layers: layers.into_iter().map(|layer| &**layer).collect(),
+    }
+  }
+
+  /// Add a layer on top of the existing ones.
+  pub fn with_layer(mut self, layer: &'a IncludeFsInner) -> Self {
+    self.layers.push(layer);
+    self
+  }
+
+  /// The layers, from the lowest to the highest precedence.
+  pub fn layers(&self) -> &[&'a IncludeFsInner] {
+    &self.layers
+  }
+
+  /// The layer a file is read from, which is the highest one that contains it.
+  ///
+  /// Returns `None` if a layer above the ones containing the file has one of its parent
+  /// directories as a file.
+  pub fn resolve(&self, path: impl AsRef<str>) -> Option<&'a IncludeFsInner> {
+    let path = path.as_ref();
+    for layer in self.layers.iter().rev() {
+      if layer.exists(path) {
+        return Some(layer);
+      }
+
+      if shadows(layer, path) {
+        return None;
+      }
+    }
+
+    None
+  }
+
+  pub fn exists(&self, path: impl AsRef<str>) -> bool {
+    self.resolve(path).is_some()
+  }
+
+  /// Read a file from the highest layer that contains it.
+  ///
+  /// Errors reading the file from that layer are returned as they are, instead of falling back
+  /// to the layers below it.
+  pub fn get(&self, path: impl AsRef<str>) -> Result<&'a [u8], FsError> {
+    let path = path.as_ref();
+    self.resolve(path).ok_or(FsError::NotFound)?.get(path)
+  }
+
+  /// The index entry of a file in the highest layer that contains it.
+  pub fn metadata(&self, path: impl AsRef<str>) -> Result<&'a FsEntry, FsError> {
+    let path = path.as_ref();
+    self.resolve(path).ok_or(FsError::NotFound)?.metadata(path)
+  }
+
+  /// The paths of the files in every layer, sorted and without duplicates.
+  ///
+  /// Files inside directories that are shadowed by a file in a higher layer are left out.
+  pub fn list_paths(&self) -> Vec<&'a EmbeddedPath> {
+    let mut paths: Vec<_> = self
+      .layers
+      .iter()
+      .enumerate()
+      .flat_map(|(index, layer)| {
+        let above = &self.layers[index + 1..];
+        layer
+          .list_paths()
+          .into_iter()
+          .filter(move |path| !above.iter().any(|layer| shadows(layer, path.as_str())))
+      })
+      .collect();
+
+    paths.sort();
+    paths.dedup();
+    paths
+  }
+
+  /// The files and directories directly inside a directory in any layer, sorted by name.
+  ///
+  /// Entries of higher layers replace those with the same name in lower layers. Returns
+  /// [`FsError::NotADirectory`] if the highest layer that contains the path has it as a file.
+  pub fn read_dir(&self, path: impl AsRef<str>) -> Result<Vec<DirEntry<'a>>, FsError> {
+    let path = path.as_ref();
+    let mut entries = BTreeMap::new();
+    let mut found = false;
+
+    for layer in self.layers.iter().rev() {
+      match layer.read_dir(path) {
+        Ok(layer_entries) => {
+          found = true;
+          for entry in layer_entries {
+            entries.entry(entry.file_name()).or_insert(entry);
+          }
+        }
+        Err(FsError::NotADirectory) if !found => return Err(FsError::NotADirectory),
+        // Files in lower layers are shadowed by the directories above them
+        Err(FsError::NotADirectory | FsError::NotFound) => {}
+        Err(err) => return Err(err),
+      }
+    }
+
+    match found {
+      true => Ok(entries.into_values().collect()),
+      false => Err(FsError::NotFound),
+    }
+  }
+
+  /// Whether a path is a directory in any layer that isn't shadowed by a file above it.
+  pub fn is_dir(&self, path: impl AsRef<str>) -> bool {
+    self.read_dir(path).is_ok()
+  }
+}
+
+/// Whether a layer has one of the parent directories of `path` as a file.
+fn shadows(layer: &IncludeFsInner, path: &str) -> bool {
+  path
+    .match_indices('/')
+    .any(|(index, _)| layer.exists(&path[..index]))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::IncludeFs;
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_overlay_fs() {
+    let base = test_fs(&[
+      ("index.html", b"base"),
+      ("style.css", b"base"),
+      ("img/logo.png", b"base"),
+      ("docs/readme.md", b"base"),
+    ]);
+    let theme = test_fs(&[
+      ("style.css", b"theme"),
+      ("img/banner.png", b"theme"),
+      ("docs", b"theme"),
+    ]);
+    let overlay = OverlayFs::default().with_layer(&base).with_layer(&theme);
+
+    assert_eq!(overlay.get("index.html").unwrap(), b"base");
+    assert_eq!(overlay.get("style.css").unwrap(), b"theme");
+    assert!(matches!(overlay.get("missing"), Err(FsError::NotFound)));
+    assert!(matches!(
+      overlay.get("docs/readme.md"),
+      Err(FsError::NotFound)
+    ));
+    assert!(!overlay.exists("docs/readme.md"));
+    assert!(core::ptr::eq(overlay.resolve("style.css").unwrap(), &theme));
+    assert_eq!(overlay.metadata("style.css").unwrap().size(), 5);
+
+    let paths: Vec<_> = overlay
+      .list_paths()
+      .iter()
+      .map(|path| path.as_str())
+      .collect();
+    assert_eq!(
+      paths,
+      [
+        "docs",
+        "img/banner.png",
+        "img/logo.png",
+        "index.html",
+        "style.css"
+      ]
+    );
+
+    let names = |dir: &str| -> Vec<_> {
+      let entries = overlay.read_dir(dir).unwrap();
+      entries.iter().map(|entry| entry.file_name()).collect()
+    };
+    assert_eq!(names("img"), ["banner.png", "logo.png"]);
+    assert_eq!(names(""), ["docs", "img", "index.html", "style.css"]);
+    let root = overlay.read_dir("").unwrap();
+    assert!(
+      root
+        .iter()
+        .any(|entry| entry.path() == "docs" && entry.is_file())
+    );
+
+    // The file in the theme shadows the directory in the base bundle
+    assert!(matches!(
+      overlay.read_dir("docs"),
+      Err(FsError::NotADirectory)
+    ));
+    assert!(!overlay.is_dir("docs"));
+    assert!(matches!(
+      overlay.read_dir("missing"),
+      Err(FsError::NotFound)
+    ));
+  }
+
+  #[test]
+  fn test_overlay_fs_new() {
+    static BASE: IncludeFs = IncludeFs::new(|| test_fs(&[("a.txt", b"base"), ("b.txt", b"base")]));
+    static THEME: IncludeFs = IncludeFs::new(|| test_fs(&[("a.txt", b"theme")]));
+
+    let overlay = OverlayFs::new([&BASE, &THEME]);
+    assert_eq!(overlay.layers().len(), 2);
+    assert_eq!(overlay.get("a.txt").unwrap(), b"theme");
+    assert_eq!(overlay.get("b.txt").unwrap(), b"base");
+  }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{EmbeddedPath, FsError, IncludeFsInner};
+
+/// Files read from the source directory of a bundle built with [`Bundle::passthrough`], or from
+/// the directory set with [`IncludeFsInner::with_override_dir`].
+///
+/// [`Bundle::passthrough`]: crate::Bundle::passthrough
+pub(crate) struct Passthrough {
+  dir: PathBuf,
+  /// How long a lookup is reused before the file is checked on disk again.
+  check_interval: Duration,
+  files: Mutex<HashMap<String, CachedFile>>,
+}
+
+/// How long lookups in an override directory are reused, so reads of embedded files don't hit
+/// the disk every time.
+const OVERRIDE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The contents of a file with a trailing NUL byte, or `None` if it wasn't on disk, and the
+/// modification time they were read at.
+///
+/// Contents that are replaced are leaked, since slices of them may still be borrowed. This is
+/// why passthrough is limited to debug builds, and why override directories are meant for the
+/// occasional hot patch.
+struct CachedFile {
+  checked: Instant,
+  modified: Option<SystemTime>,
+  data: Option<&'static [u8]>,
+}
+
+impl Passthrough {
+  /// Read from `dir`, or return `None` if it doesn't exist, such as when the binary was copied
+  /// to another machine.
+  pub(crate) fn new(dir: PathBuf) -> Option<Self> {
+    dir.is_dir().then(|| Self::with_dir(dir, Duration::ZERO))
+  }
+
+  /// Read from `dir`, even if it doesn't exist yet, checking each file at most once per
+  /// `check_interval`.
+  fn with_dir(dir: PathBuf, check_interval: Duration) -> Self {
+    Self {
+      dir,
+      check_interval,
+      files: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Read a file including a trailing NUL byte, or `None` if it doesn't exist on disk.
+  fn read(&self, path: &str) -> Result<Option<&'static [u8]>, FsError> {
+    let Some(path) = crate::path::normalize(path) else {
+      return Ok(None);
+    };
+    let path = EmbeddedPath::new_unchecked(&path);
+
+    if let Some(cached) = self.files.lock().unwrap().get(path.as_str())
+      && cached.checked.elapsed() < self.check_interval
+    {
+      return Ok(cached.data);
+    }
+
+    let checked = Instant::now();
+    let disk_path = self.dir.join(path.as_str());
+    let metadata = match fs::metadata(&disk_path) {
+      Ok(metadata) if metadata.is_file() => Some(metadata),
+      Ok(_) => None,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+      Err(err) => return Err(err.into()),
+    };
+
+    let mut files = self.files.lock().unwrap();
+    let Some(metadata) = metadata else {
+      files.insert(
+        path.as_str().to_string(),
+        CachedFile {
+          checked,
+          modified: None,
+          data: None,
+        },
+      );
+      return Ok(None);
+    };
+
+    let modified = metadata.modified().ok();
+    if let Some(cached) = files.get_mut(path.as_str())
+      && let Some(data) = cached.data
+      && modified.is_some()
+      && cached.modified == modified
+    {
+      cached.checked = checked;
+      return Ok(Some(data));
+    }
+
+    let mut data = fs::read(&disk_path)?;
+    data.push(0);
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(path = %path, size = data.len() - 1, "read file from source directory");
+
+    let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+    files.insert(
+      path.as_str().to_string(),
+      CachedFile {
+        checked,
+        modified,
+        data: Some(data),
+      },
+    );
+    Ok(Some(data))
+  }
+}
+
+impl IncludeFsInner {
+  /// Read a file from the source directory, including a trailing NUL byte, if passthrough is
+  /// enabled and the file exists there.
+  ///
+  /// Only files in the index are read, so files that the bundle excludes aren't served even
+  /// though they are in the source directory.
+  pub(crate) fn passthrough(&self, path: &str) -> Result<Option<&'static [u8]>, FsError> {
+    let Some(passthrough) = &self.passthrough else {
+      return Ok(None);
+    };
+
+    let Some(entry) = self.index.entry(self.archive_bytes, path) else {
+      return Ok(None);
+    };
+
+    // Fingerprinted files are stored under their original path
+    let data = passthrough.read(entry.original_path.as_deref().unwrap_or(path))?;
+    if data.is_none() && self.index.stub {
+      return Err(FsError::NotFound);
+    }
+
+    Ok(data)
+  }
+
+  /// Whether files are read from disk instead of the archive, see
+  /// [`Bundle::passthrough`](crate::Bundle::passthrough) and
+  /// [`with_override_dir`](Self::with_override_dir).
+  pub fn is_passthrough(&self) -> bool {
+    self.passthrough.is_some()
+  }
+
+  /// Read files from `dir` if they exist there, and from the archive otherwise, so operators can
+  /// replace individual assets of a deployed binary without rebuilding it.
+  ///
+  /// Paths are looked up relative to `dir` the same way as in the archive, and only files that
+  /// are in the archive can be overridden. Each path is checked
+  /// on disk at most once a second, including paths that aren't overridden, and files are read
+  /// again when their modification time changes. The directory doesn't have to exist, so
+  /// the override can be set up unconditionally:
+  ///
+  /// ```rust,ignore
+  /// static ASSETS: IncludeFs = include_fs!("assets");
+  /// static PATCHED: LazyLock<IncludeFsInner> = LazyLock::new(|| {
+  ///   IncludeFsInner::clone(&ASSETS).with_override_dir("/etc/my-app/overrides")
+  /// });
+  ///
+  /// // `/etc/my-app/overrides/assets/index.html` if it exists, the embedded file otherwise
+  /// let index = PATCHED.get("assets/index.html")?;
+  /// ```
+  ///
+  /// This replaces the source directory of bundles built with
+  /// [`Bundle::passthrough`](crate::Bundle::passthrough). Every version of a file that is read
+  /// stays in memory, since slices of it may still be borrowed.
+  pub fn with_override_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.passthrough = Some(Arc::new(Passthrough::with_dir(
+      dir.into(),
+      OVERRIDE_CHECK_INTERVAL,
+    )));
+    self
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::archive::{ArchiveOptions, FileEntry};
+  use crate::tests::test_fs_with;
+  use std::time::Duration;
+
+  #[test]
+  fn test_passthrough() {
+    let dir = std::env::temp_dir().join(format!("include-fs-passthrough-{}", std::process::id()));
+    fs::create_dir_all(dir.join("assets")).unwrap();
+    fs::write(dir.join("assets/a.txt"), "edited").unwrap();
+
+    let mut shader = FileEntry::new("assets/shader.glsl", "void main() {}");
+    shader.nul_terminated = true;
+    let files = [
+      FileEntry::new("assets/a.txt", "embedded"),
+      FileEntry::new("assets/b.txt", "embedded"),
+      shader,
+    ];
+
+    let options = ArchiveOptions {
+      source_dir: Some(dir.to_str().unwrap().to_string()),
+      ..Default::default()
+    };
+
+    let fs = test_fs_with(&files, &options);
+    assert!(fs.is_passthrough());
+
+    // Files on disk take precedence, and the archive is used for the others
+    assert_eq!(fs.get("assets/a.txt").unwrap(), b"edited");
+    assert_eq!(fs.get("assets/b.txt").unwrap(), b"embedded");
+    assert_eq!(
+      fs.get_cstr("assets/shader.glsl").unwrap(),
+      c"void main() {}"
+    );
+
+    // Changes are picked up without reopening the archive
+    let file = fs::File::options()
+      .write(true)
+      .open(dir.join("assets/a.txt"))
+      .unwrap();
+    file.set_len(0).unwrap();
+    io::Write::write_all(&mut &file, b"edited again").unwrap();
+    file
+      .set_modified(SystemTime::now() + Duration::from_secs(10))
+      .unwrap();
+    assert_eq!(fs.get("assets/a.txt").unwrap(), b"edited again");
+
+    // Files that aren't in the bundle aren't served, like excluded files
+    fs::write(dir.join("assets/.env"), "SECRET=1").unwrap();
+    assert!(!fs.exists("assets/.env"));
+    assert!(matches!(fs.get("assets/.env"), Err(FsError::NotFound)));
+    assert!(matches!(fs.get("../escape.txt"), Err(FsError::NotFound)));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_override_dir() {
+    let dir = std::env::temp_dir().join(format!("include-fs-override-{}", std::process::id()));
+    let fs = crate::tests::test_fs(&[("a.txt", b"embedded"), ("b.txt", b"embedded")])
+      .with_override_dir(&dir);
+    assert!(fs.is_passthrough());
+    assert_eq!(fs.get("a.txt").unwrap(), b"embedded");
+
+    // Lookups are reused until the check interval passes
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.txt"), "patched").unwrap();
+    assert_eq!(fs.get("a.txt").unwrap(), b"embedded");
+
+    // The directory is picked up once it exists
+    std::thread::sleep(OVERRIDE_CHECK_INTERVAL);
+    assert_eq!(fs.get("a.txt").unwrap(), b"patched");
+    assert_eq!(fs.get("b.txt").unwrap(), b"embedded");
+    fs::write(dir.join("c.txt"), "new").unwrap();
+    assert!(matches!(fs.get("c.txt"), Err(FsError::NotFound)));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_stub() {
+    let dir = std::env::temp_dir().join(format!("include-fs-stub-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.txt"), "on disk").unwrap();
+    fs::write(dir.join("Cargo.toml"), "[package]").unwrap();
+
+    let options = ArchiveOptions {
+      source_dir: Some(dir.to_str().unwrap().to_string()),
+      stub: true,
+      ..Default::default()
+    };
+    let files = [FileEntry::new("a.txt", ""), FileEntry::new("b.txt", "")];
+
+    let fs = test_fs_with(&files, &options);
+
+    // Files are only read from disk, and only if the bundle includes them
+    assert_eq!(fs.get("a.txt").unwrap(), b"on disk");
+    assert!(!fs.exists("b.txt"));
+    assert!(matches!(fs.get("b.txt"), Err(FsError::NotFound)));
+    assert!(matches!(fs.get("Cargo.toml"), Err(FsError::NotFound)));
+    assert_eq!(fs.list_paths().len(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_stub_without_source_dir() {
+    let dir = std::env::temp_dir().join(format!("include-fs-stub-missing-{}", std::process::id()));
+    let options = ArchiveOptions {
+      source_dir: Some(dir.to_str().unwrap().to_string()),
+      stub: true,
+      ..Default::default()
+    };
+    let fs = test_fs_with(&[FileEntry::new("a.txt", "")], &options);
+    assert!(!fs.is_passthrough());
+
+    // The stub's empty data is never returned in place of the files
+    assert!(!fs.exists("a.txt"));
+    assert!(matches!(fs.get("a.txt"), Err(FsError::SourceDirMissing)));
+    assert!(matches!(fs.open("a.txt"), Err(FsError::SourceDirMissing)));
+    assert!(matches!(
+      fs.write_to("a.txt", &mut Vec::new()),
+      Err(FsError::SourceDirMissing)
+    ));
+    assert!(fs.entries().any(|entry| entry.is_err()));
+    assert!(fs.unpack(dir.join("unpacked")).is_err());
+  }
+}
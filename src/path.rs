@@ -0,0 +1,178 @@
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::FsError;
+
+/// A validated path of a file in a bundle.
+///
+/// Embedded paths are relative, use `/` as the separator and contain no empty, `.` or `..`
+/// components, so they can be compared and looked up directly. Lookup functions accept both
+/// strings and embedded paths, while listings return embedded paths:
+///
+/// ```rust,ignore
+/// let logo = EmbeddedPath::new("assets/logo.png")?;
+/// let data = ASSETS.get(logo)?;
+///
+/// assert!(EmbeddedPath::new("assets/../logo.png").is_err());
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct EmbeddedPath(str);
+
+impl EmbeddedPath {
+  /// Validate a path, returning [`FsError::InvalidPath`] if it is not a valid embedded path.
+  pub fn new(path: &str) -> Result<&EmbeddedPath, FsError> {
+    let valid = !path.is_empty()
+      && !path.contains('\\')
+      && path
+        .split('/')
+        .all(|component| !matches!(component, "" | "." | ".."));
+
+    if !valid {
+      return Err(FsError::InvalidPath(path.to_string()));
+    }
+
+    Ok(Self::new_unchecked(path))
+  }
+
+  /// Wrap a path that is known to be valid, such as a path read from an archive.
+  pub(crate) fn new_unchecked(path: &str) -> &EmbeddedPath {
+    // SAFETY: `EmbeddedPath` is a transparent wrapper around `str`
+    unsafe { &*(path as *const str as *const EmbeddedPath) }
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+}
+
+/// Normalize a path used for a lookup into an embedded path, accepting `\` as a separator,
+/// removing empty and `.` components and resolving `..` components.
+///
+/// Returns `None` if the path is empty or a `..` component would leave the root of the bundle,
+/// so paths taken from requests can't refer to anything outside of it.
+pub(crate) fn normalize(path: &str) -> Option<Cow<'_, str>> {
+  if EmbeddedPath::new(path).is_ok() {
+    return Some(Cow::Borrowed(path));
+  }
+
+  let mut components = Vec::new();
+  for component in path.split(['/', '\\']) {
+    match component {
+      "" | "." => {}
+      ".." => {
+        components.pop()?;
+      }
+      component => components.push(component),
+    }
+  }
+
+  match components.is_empty() {
+    true => None,
+    false => Some(Cow::Owned(components.join("/"))),
+  }
+}
+
+impl Deref for EmbeddedPath {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl AsRef<str> for EmbeddedPath {
+  fn as_ref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl PartialEq<str> for EmbeddedPath {
+  fn eq(&self, other: &str) -> bool {
+    &self.0 == other
+  }
+}
+
+impl PartialEq<&str> for EmbeddedPath {
+  fn eq(&self, other: &&str) -> bool {
+    &self.0 == *other
+  }
+}
+
+impl<'a> TryFrom<&'a str> for &'a EmbeddedPath {
+  type Error = FsError;
+
+  fn try_from(path: &'a str) -> Result<Self, FsError> {
+    EmbeddedPath::new(path)
+  }
+}
+
+impl fmt::Debug for EmbeddedPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Debug::fmt(&self.0, f)
+  }
+}
+
+impl fmt::Display for EmbeddedPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Display::fmt(&self.0, f)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validation() {
+    assert_eq!(
+      EmbeddedPath::new("assets/logo.png").unwrap(),
+      "assets/logo.png"
+    );
+    assert!(EmbeddedPath::new("logo.png").is_ok());
+
+    for path in [
+      "",
+      "/assets/logo.png",
+      "assets//logo.png",
+      "assets/./logo.png",
+      "assets/../logo.png",
+      "assets/",
+      "assets\\logo.png",
+    ] {
+      assert!(
+        matches!(EmbeddedPath::new(path), Err(FsError::InvalidPath(_))),
+        "{path}"
+      );
+    }
+  }
+
+  #[test]
+  fn test_normalize() {
+    assert!(matches!(
+      normalize("assets/logo.png"),
+      Some(Cow::Borrowed(_))
+    ));
+    assert_eq!(normalize("assets/./logo.png").unwrap(), "assets/logo.png");
+    assert_eq!(normalize("/assets//logo.png/").unwrap(), "assets/logo.png");
+    assert_eq!(
+      normalize("assets/icons/../logo.png").unwrap(),
+      "assets/logo.png"
+    );
+
+    for path in [
+      "",
+      "/",
+      ".",
+      "..",
+      "../secret",
+      "assets/../../secret",
+      "assets/..",
+    ] {
+      assert_eq!(normalize(path), None, "{path}");
+    }
+  }
+}
@@ -0,0 +1,111 @@
+//! Python bindings for reading archives.
+//!
+//! This allows Python tooling to inspect the exact `.embed_fs` archives that are shipped in Rust
+//! binaries. The module is exported as `include_fs`, so building a `cdylib` crate named
+//! `include_fs` that re-exports [`InfsArchive`] (e.g. with `maturin`) produces an importable
+//! extension module:
+//!
+//! ```python
+//! from include_fs import InfsArchive
+//!
+//! archive = InfsArchive.open("target/release/build/app-1234/out/assets.embed_fs")
+//! for name in archive.names():
+//!     print(name, len(archive.read(name)))
+//! ```
+
+use pyo3::exceptions::{PyFileNotFoundError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::path::PathBuf;
+
+use crate::{FsError, Index, parse_index};
+
+/// An archive read from a `.embed_fs` file.
+#[pyclass(frozen, module = "include_fs")]
+pub struct InfsArchive {
+  archive_bytes: Vec<u8>,
+  /// The shared data for archives built with `Bundle::build_shared`.
+  data_bytes: Option<Vec<u8>>,
+  index: Index,
+}
+
+#[pymethods]
+impl InfsArchive {
+  /// Read an archive from the given path, along with its shared data if it was built with
+  /// `Bundle::build_shared`.
+  #[staticmethod]
+  #[pyo3(signature = (path, shared_data = None))]
+  fn open(path: PathBuf, shared_data: Option<PathBuf>) -> PyResult<Self> {
+    let archive_bytes = std::fs::read(path)?;
+    let index =
+      parse_index(&archive_bytes).map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    if index.key_check.is_some() {
+      return Err(PyValueError::new_err(
+        "Encrypted archives are not supported",
+      ));
+    }
+
+    let data_bytes = match (&index.shared_data, shared_data) {
+      (None, None) => None,
+      (Some(_), Some(path)) => Some(std::fs::read(path)?),
+      (Some(name), None) => {
+        return Err(PyValueError::new_err(format!(
+          "Archive requires shared data {name}"
+        )));
+      }
+      (None, Some(_)) => return Err(PyValueError::new_err("Archive does not use shared data")),
+    };
+
+    Ok(Self {
+      archive_bytes,
+      data_bytes,
+      index,
+    })
+  }
+
+  /// Read the contents of a file in the archive.
+  fn read<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyBytes>> {
+    let Some(entry) = self.index.entry(&self.archive_bytes, name) else {
+      return Err(PyFileNotFoundError::new_err(name.to_string()));
+    };
+
+    let data_bytes = self.data_bytes.as_ref().unwrap_or(&self.archive_bytes);
+    let to_py_err = |err: FsError| PyValueError::new_err(err.to_string());
+    if entry.compression.is_none() {
+      let data = entry.data(data_bytes).map_err(to_py_err)?;
+      return Ok(PyBytes::new(py, data));
+    }
+
+    let stored = entry.stored(data_bytes).ok_or(FsError::InvalidArchive);
+    let data = entry
+      .decompress(stored.map_err(to_py_err)?)
+      .map_err(to_py_err)?;
+    Ok(PyBytes::new(py, &data[..entry.size as usize]))
+  }
+
+  /// All file names in the archive, sorted.
+  fn names(&self) -> Vec<String> {
+    let mut names: Vec<_> = self
+      .index
+      .files
+      .iter(&self.archive_bytes)
+      .map(|(path, _)| path.to_string())
+      .collect();
+    names.sort();
+    names
+  }
+
+  fn __contains__(&self, name: &str) -> bool {
+    self.index.entry(&self.archive_bytes, name).is_some()
+  }
+
+  fn __len__(&self) -> usize {
+    self.index.files.len()
+  }
+}
+
+#[pymodule]
+fn include_fs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+  m.add_class::<InfsArchive>()
+}
@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use crate::IncludeFs;
+
+static REGISTRY: RwLock<Vec<RegisteredBundle>> = RwLock::new(Vec::new());
+
+/// A bundle registered with [`register`] or [`register_under`].
+#[derive(Clone, Copy)]
+pub struct RegisteredBundle {
+  pub name: &'static str,
+  /// The namespace the bundle's paths are mounted under, without slashes, or an empty string.
+  pub prefix: &'static str,
+  pub fs: &'static IncludeFs,
+}
+
+impl RegisteredBundle {
+  /// The path of a file inside the bundle, if `path` is in the bundle's namespace.
+  fn bundle_path<'p>(&self, path: &'p str) -> Option<&'p str> {
+    if self.prefix.is_empty() {
+      return Some(path);
+    }
+
+    path.strip_prefix(self.prefix)?.strip_prefix('/')
+  }
+
+  /// The path of a file of the bundle in the namespace.
+  fn namespaced_path(&self, path: &str) -> String {
+    if self.prefix.is_empty() {
+      path.to_string()
+    } else {
+      format!("{}/{path}", self.prefix)
+    }
+  }
+}
+
+/// Register a bundle, making it available through [`all_bundles`] and [`find`].
+///
+/// Bundles are searched in the order they were registered, so bundles registered first take
+/// precedence. Registering a name again replaces the previous bundle while keeping its position.
+///
+/// Registering does not initialize the bundle's index.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// static CONFIG: IncludeFs = include_fs!("config");
+/// static ASSETS: IncludeFs = include_fs!("assets");
+///
+/// include_fs::register("config", &CONFIG);
+/// include_fs::register("assets", &ASSETS);
+///
+/// // Somewhere that doesn't know about the bundles
+/// if let Some((bundle, data)) = include_fs::find("config/default.toml") {
+///   println!("found in {bundle}: {} bytes", data.len());
+/// }
+/// ```
+pub fn register(name: &'static str, fs: &'static IncludeFs) {
+  register_under(name, "", fs);
+}
+
+/// Register a bundle like [`register`], mounting its paths under `prefix`.
+///
+/// Namespaces keep independently authored bundles from shadowing each other's paths:
+///
+/// ```rust,ignore
+/// include_fs::register_under("web", "web", &WEB);
+/// include_fs::register_under("locales", "locales", &LOCALES);
+///
+/// // Looks up `en.ftl` in the locales bundle
+/// let en = include_fs::find("locales/en.ftl");
+/// ```
+pub fn register_under(name: &'static str, prefix: &'static str, fs: &'static IncludeFs) {
+  let mut registry = REGISTRY.write().unwrap_or_else(|err| err.into_inner());
+  let prefix = prefix.trim_matches('/');
+  let bundle = RegisteredBundle { name, prefix, fs };
+
+  match registry.iter_mut().find(|existing| existing.name == name) {
+    Some(existing) => *existing = bundle,
+    None => registry.push(bundle),
+  }
+}
+
+/// All registered bundles, in priority order.
+pub fn all_bundles() -> Vec<RegisteredBundle> {
+  REGISTRY
+    .read()
+    .unwrap_or_else(|err| err.into_inner())
+    .clone()
+}
+
+/// Find a file in the registered bundles, returning the name of the first bundle that contains
+/// it along with its contents.
+pub fn find(path: impl AsRef<str>) -> Option<(&'static str, &'static [u8])> {
+  let path = path.as_ref();
+  all_bundles()
+    .into_iter()
+    .find_map(|bundle| Some((bundle.name, bundle.fs.get(bundle.bundle_path(path)?).ok()?)))
+}
+
+/// The paths of all files in the registered bundles, including their namespace prefixes, along
+/// with the name of the bundle that [`find`] returns them from.
+///
+/// Shadowed files are not listed. Files are listed in priority order of their bundles, and
+/// sorted by path within each bundle.
+pub fn all_paths() -> Vec<(&'static str, String)> {
+  let mut seen = HashSet::new();
+  let mut paths = Vec::new();
+
+  for bundle in all_bundles() {
+    let mut bundle_paths = bundle.fs.list_paths();
+    bundle_paths.sort();
+
+    for path in bundle_paths {
+      let path = bundle.namespaced_path(path);
+      if seen.insert(path.clone()) {
+        paths.push((bundle.name, path));
+      }
+    }
+  }
+
+  paths
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+  use std::sync::LazyLock;
+
+  static BASE: IncludeFs = LazyLock::new(|| test_fs(&[("shared.txt", b"base"), ("a.txt", b"a")]));
+  static THEME: IncludeFs = LazyLock::new(|| test_fs(&[("shared.txt", b"theme")]));
+
+  #[test]
+  fn test_find_in_priority_order() {
+    register("registry-test-base", &BASE);
+    register("registry-test-theme", &THEME);
+
+    assert_eq!(
+      find("shared.txt"),
+      Some(("registry-test-base", &b"base"[..]))
+    );
+    assert_eq!(find("a.txt"), Some(("registry-test-base", &b"a"[..])));
+    assert_eq!(find("missing.txt"), None);
+
+    // Other tests register bundles concurrently, so only the relative order is checked
+    let names: Vec<_> = all_bundles().iter().map(|bundle| bundle.name).collect();
+    let position = |name| names.iter().position(|&other| other == name).unwrap();
+    assert!(position("registry-test-base") < position("registry-test-theme"));
+  }
+
+  #[test]
+  fn test_namespaces() {
+    register_under("registry-test-web", "/web/", &THEME);
+
+    assert_eq!(
+      find("web/shared.txt"),
+      Some(("registry-test-web", &b"theme"[..]))
+    );
+    assert_eq!(find("webshared.txt"), None);
+
+    let paths = all_paths();
+    assert!(paths.contains(&("registry-test-web", "web/shared.txt".to_string())));
+    assert_eq!(
+      paths
+        .iter()
+        .filter(|(_, path)| path == "web/shared.txt")
+        .count(),
+      1
+    );
+  }
+}
@@ -0,0 +1,147 @@
+//! Serving embedded files with Rocket, as a drop-in replacement for Rocket's `FileServer`.
+//!
+//! ```rust,ignore
+//! static ASSETS: IncludeFs = include_fs!("assets");
+//!
+//! // Before
+//! rocket::build().mount("/static", rocket::fs::FileServer::from("assets"))
+//!
+//! // After
+//! rocket::build().mount("/static", FileServer::new(&ASSETS).root("assets"))
+//! ```
+
+use std::io::Cursor;
+
+use ::rocket::http::uri::{Segments, fmt::Path};
+use ::rocket::http::{Method, Status};
+use ::rocket::route::{Handler, Outcome, Route};
+use ::rocket::{Data, Request, Response};
+
+use crate::IncludeFsInner;
+use crate::serve::Server;
+
+/// A handler that serves the files of an embedded filesystem at the path it is mounted at.
+///
+/// Requests are answered as described in [`http`](crate::http#serving), except that requests
+/// for missing files are forwarded with a `404 Not Found` status, like Rocket's `FileServer`
+/// does, so other routes can still handle them. The route only matches `GET` and `HEAD`
+/// requests, so other methods never reach it.
+#[derive(Debug, Clone)]
+pub struct FileServer {
+  server: Server,
+  rank: isize,
+}
+
+impl FileServer {
+  /// The default rank of the route, which is the same as for Rocket's `FileServer`.
+  pub const DEFAULT_RANK: isize = 10;
+
+  pub fn new(fs: &IncludeFsInner) -> Self {
+    Self {
+      server: Server::new(fs),
+      rank: Self::DEFAULT_RANK,
+    }
+  }
+
+  /// Serve the files below this directory, so `/static/logo.png` is looked up as
+  /// `{root}/logo.png` when mounted at `/static`.
+  pub fn root(mut self, root: &str) -> Self {
+    self.server.set_root(root);
+    self
+  }
+
+  /// The file served for requests to a directory. Defaults to `index.html`, `None` forwards
+  /// them.
+  pub fn index_file(mut self, index_file: Option<&str>) -> Self {
+    self.server.set_index_file(index_file);
+    self
+  }
+
+  /// Set the rank of the route. Defaults to [`DEFAULT_RANK`](Self::DEFAULT_RANK).
+  pub fn rank(mut self, rank: isize) -> Self {
+    self.rank = rank;
+    self
+  }
+}
+
+impl From<FileServer> for Vec<Route> {
+  fn from(server: FileServer) -> Self {
+    let mut route = Route::ranked(server.rank, Method::Get, "/<path..>", server);
+    route.name = Some("include_fs::rocket::FileServer".into());
+    vec![route]
+  }
+}
+
+#[::rocket::async_trait]
+impl Handler for FileServer {
+  async fn handle<'r>(&self, req: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+    // Rocket has already decoded the segments, so escape them for the shared server
+    let Ok(segments) = req.segments::<Segments<'_, Path>>(0..);
+    let path = segments.collect::<Vec<_>>().join("/").replace('%', "%25");
+
+    // Rocket answers HEAD requests with GET routes and strips the body
+    let served = self
+      .server
+      .respond("GET", &path, |name| req.headers().get_one(name));
+    if served.status == 404 {
+      return Outcome::Forward((data, Status::NotFound));
+    }
+
+    let mut response = Response::build();
+    response.status(Status::new(served.status));
+    for (name, value) in served.headers {
+      response.raw_header(name, value);
+    }
+
+    if served.status == 200 {
+      response.sized_body(served.body.len(), Cursor::new(served.body));
+    }
+
+    Outcome::Success(response.finalize())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::serve::test_site;
+  use ::rocket::local::blocking::Client;
+
+  #[test]
+  fn test_file_server() {
+    let rocket = ::rocket::build()
+      .configure(::rocket::Config::debug_default())
+      .mount("/static", FileServer::new(&test_site()).root("public"));
+    let client = Client::untracked(rocket).unwrap();
+
+    let response = client.get("/static/").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+      response.headers().get_one("Content-Type"),
+      Some("text/html")
+    );
+    let etag = response.headers().get_one("ETag").unwrap().to_string();
+    assert_eq!(response.into_string().as_deref(), Some("<html></html>"));
+
+    let response = client
+      .get("/static/index.html")
+      .header(::rocket::http::Header::new("If-None-Match", etag))
+      .dispatch();
+    assert_eq!(response.status(), Status::NotModified);
+
+    let response = client.get("/static/my%20file.txt").dispatch();
+    assert_eq!(response.into_string().as_deref(), Some("spaces"));
+
+    let response = client.head("/static/app.1234abcd.js").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+      response.headers().get_one("Cache-Control"),
+      Some(crate::http::IMMUTABLE)
+    );
+
+    assert_eq!(
+      client.get("/static/missing.js").dispatch().status(),
+      Status::NotFound
+    );
+  }
+}
@@ -0,0 +1,357 @@
+//! The framework-independent part of the HTTP serving integrations.
+
+use std::fmt;
+
+use bytes::Bytes;
+
+use crate::{IncludeFsInner, http as cache};
+
+/// Resolves requests to embedded files, shared by the integrations.
+#[derive(Clone)]
+pub(crate) struct Server {
+  fs: IncludeFsInner,
+  root: String,
+  index_file: Option<String>,
+}
+
+impl fmt::Debug for Server {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Server")
+      .field("root", &self.root)
+      .field("index_file", &self.index_file)
+      .finish_non_exhaustive()
+  }
+}
+
+/// A response, to be converted into the response type of a framework.
+pub(crate) struct Served {
+  pub status: u16,
+  /// Header names are lowercase.
+  pub headers: Vec<(&'static str, String)>,
+  /// Length of the file, which is also sent for `HEAD` requests without a body.
+  #[cfg_attr(not(feature = "axum"), allow(dead_code))]
+  pub len: usize,
+  pub body: Bytes,
+}
+
+impl Served {
+  fn status(status: u16) -> Self {
+    Self {
+      status,
+      headers: Vec::new(),
+      len: 0,
+      body: Bytes::new(),
+    }
+  }
+}
+
+impl Server {
+  pub fn new(fs: &IncludeFsInner) -> Self {
+    Self {
+      fs: fs.clone(),
+      root: String::new(),
+      index_file: Some("index.html".to_string()),
+    }
+  }
+
+  pub fn set_root(&mut self, root: &str) {
+    self.root = root.trim_matches('/').to_string();
+  }
+
+  pub fn set_index_file(&mut self, index_file: Option<&str>) {
+    self.index_file = index_file.map(String::from);
+  }
+
+  /// The embedded path for a request path, if the file exists.
+  fn resolve(&self, request_path: &str) -> Option<String> {
+    let decoded = percent_decode(request_path.trim_start_matches('/'))?;
+    let is_dir = decoded.is_empty() || decoded.ends_with('/');
+
+    // The request path is normalized on its own, so `..` can't leave the root
+    let path = match decoded
+      .split(['/', '\\'])
+      .all(|component| component.is_empty() || component == ".")
+    {
+      true => String::new(),
+      false => crate::path::normalize(&decoded)?.into_owned(),
+    };
+    let path = match (self.root.is_empty(), path.is_empty()) {
+      (true, _) => path,
+      (false, true) => self.root.clone(),
+      (false, false) => format!("{}/{path}", self.root),
+    };
+
+    if !path.is_empty() && !is_dir && self.fs.exists(&path) {
+      return Some(path);
+    }
+
+    let index_file = self.index_file.as_deref()?;
+    let index = match path.trim_end_matches('/') {
+      "" => index_file.to_string(),
+      dir => format!("{dir}/{index_file}"),
+    };
+
+    self.fs.exists(&index).then_some(index)
+  }
+
+  /// Answer a request, looking up request headers by their lowercase name with `header`.
+  pub fn respond<'a>(
+    &self,
+    method: &str,
+    request_path: &str,
+    header: impl Fn(&'static str) -> Option<&'a str>,
+  ) -> Served {
+    if method != "GET" && method != "HEAD" {
+      let mut served = Served::status(405);
+      served.headers.push(("allow", "GET, HEAD".to_string()));
+      return served;
+    }
+
+    let Some(path) = self.resolve(request_path) else {
+      return Served::status(404);
+    };
+
+    let Ok(entry) = self.fs.metadata(&path) else {
+      return Served::status(404);
+    };
+
+    let etag = entry.etag();
+    let encodings: Vec<_> = entry.encodings().collect();
+    let last_modified = self.fs.last_modified(&path).ok().flatten();
+
+    // If-None-Match takes precedence over If-Modified-Since
+    let not_modified = match header("if-none-match") {
+      Some(if_none_match) => {
+        etag.is_some_and(|etag| cache::etag_matches(Some(if_none_match), etag))
+      }
+      None => last_modified.is_some_and(|last_modified| {
+        cache::not_modified(header("if-modified-since"), last_modified)
+      }),
+    };
+
+    let mut served = match not_modified {
+      true => Served::status(304),
+      false => {
+        // Precompressed variants are sent as they are stored, falling back to the file itself
+        // for files read from the source directory
+        let encoding = cache::negotiate_encoding(header("accept-encoding"), &encodings);
+        let encoded = encoding.and_then(|encoding| {
+          let data = self.fs.get_encoded(&path, encoding).ok().flatten()?;
+          Some((encoding, Bytes::from_static(data)))
+        });
+
+        let (data, encoding) = match encoded {
+          Some((encoding, data)) => (data, Some(encoding)),
+          None => match self.fs.get_bytes(&path) {
+            Ok(data) => (data, None),
+            Err(_) => return Served::status(500),
+          },
+        };
+
+        let mut served = Served {
+          status: 200,
+          headers: Vec::new(),
+          len: data.len(),
+          body: match method {
+            "HEAD" => Bytes::new(),
+            _ => data,
+          },
+        };
+
+        if let Ok(mime) = self.fs.mime(&path) {
+          served.headers.push(("content-type", mime.to_string()));
+        }
+
+        if let Some(encoding) = encoding {
+          served
+            .headers
+            .push(("content-encoding", encoding.as_str().to_string()));
+        }
+
+        served
+      }
+    };
+
+    if let Some(etag) = etag {
+      served.headers.push(("etag", etag.to_string()));
+    }
+
+    if !encodings.is_empty() {
+      served.headers.push(("vary", "accept-encoding".to_string()));
+    }
+
+    if let Some(last_modified) = last_modified {
+      served
+        .headers
+        .push(("last-modified", cache::format_date(last_modified)));
+    }
+
+    if entry.original_path.is_some() {
+      served
+        .headers
+        .push(("cache-control", cache::IMMUTABLE.to_string()));
+    }
+
+    served
+  }
+}
+
+/// Decode `%XX` escapes in a request path, or `None` if the result is not valid UTF-8.
+fn percent_decode(path: &str) -> Option<String> {
+  let bytes = path.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let escaped = bytes
+      .get(i + 1..i + 3)
+      .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+      .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+    match (bytes[i], escaped) {
+      (b'%', Some(byte)) => {
+        decoded.push(byte);
+        i += 3;
+      }
+      (byte, _) => {
+        decoded.push(byte);
+        i += 1;
+      }
+    }
+  }
+
+  String::from_utf8(decoded).ok()
+}
+
+/// A small site below `public/`, with an `ETag` on the index, a fingerprinted script and a
+/// stylesheet with a gzip variant.
+#[cfg(test)]
+pub(crate) fn test_site() -> IncludeFsInner {
+  use crate::archive::{ArchiveOptions, FileEntry};
+  use crate::tests::test_fs_with;
+
+  let mut index = FileEntry::new("public/index.html", "<html></html>");
+  index.etag = Some(cache::etag(&index.data));
+  let mut app = FileEntry::new("public/app.1234abcd.js", "app");
+  app.original_path = Some("public/app.js".to_string());
+  let mut style = FileEntry::new("public/style.css", "body {}");
+  style.encodings = vec![(cache::Encoding::Gzip, b"gzipped".to_vec())];
+  let files = [
+    index,
+    app,
+    FileEntry::new("public/my file.txt", "spaces"),
+    FileEntry::new("public/docs/index.html", "docs"),
+    style,
+  ];
+
+  test_fs_with(&files, &ArchiveOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_respond() {
+    let mut server = Server::new(&test_site());
+    server.set_root("/public/");
+    let respond = |method, path| server.respond(method, path, |_| None);
+
+    let served = respond("GET", "/");
+    assert_eq!(
+      (served.status, &served.body[..]),
+      (200, &b"<html></html>"[..])
+    );
+    assert!(
+      served
+        .headers
+        .contains(&("content-type", "text/html".to_string()))
+    );
+
+    let served = respond("HEAD", "/app.1234abcd.js");
+    assert_eq!((served.status, served.len), (200, 3));
+    assert!(served.body.is_empty());
+    assert!(
+      served
+        .headers
+        .contains(&("cache-control", cache::IMMUTABLE.to_string()))
+    );
+
+    assert_eq!(&respond("GET", "/my%20file.txt").body[..], b"spaces");
+    assert_eq!(&respond("GET", "/docs").body[..], b"docs");
+    assert_eq!(&respond("GET", "/docs/").body[..], b"docs");
+    assert_eq!(respond("GET", "/missing.js").status, 404);
+    assert_eq!(respond("POST", "/index.html").status, 405);
+
+    server.set_index_file(None);
+    assert_eq!(server.respond("GET", "/", |_| None).status, 404);
+  }
+
+  #[test]
+  fn test_traversal() {
+    let mut server = Server::new(&test_site());
+    server.set_root("public/docs");
+    let respond = |path| server.respond("GET", path, |_| None);
+
+    assert_eq!(&respond("/").body[..], b"docs");
+    assert_eq!(&respond("/./index.html").body[..], b"docs");
+    assert_eq!(respond("/../style.css").status, 404);
+    assert_eq!(respond("/%2e%2e/style.css").status, 404);
+    assert_eq!(respond("/..%2Fapp.1234abcd.js").status, 404);
+    assert_eq!(respond("/..\\style.css").status, 404);
+    assert_eq!(respond("/a/../../style.css").status, 404);
+  }
+
+  #[test]
+  fn test_conditional() {
+    let server = Server::new(&test_site());
+    let served = server.respond("GET", "/public/index.html", |_| None);
+    let (_, etag) = served
+      .headers
+      .into_iter()
+      .find(|(name, _)| *name == "etag")
+      .unwrap();
+
+    let served = server.respond("GET", "/public/index.html", |name| {
+      (name == "if-none-match").then_some(etag.as_str())
+    });
+    assert_eq!(served.status, 304);
+    assert!(served.body.is_empty());
+    assert!(served.headers.contains(&("etag", etag.clone())));
+  }
+
+  #[test]
+  fn test_precompressed() {
+    let server = Server::new(&test_site());
+    let served = server.respond("GET", "/public/style.css", |name| {
+      (name == "accept-encoding").then_some("gzip, br")
+    });
+    assert_eq!(&served.body[..], b"gzipped");
+    assert!(
+      served
+        .headers
+        .contains(&("content-encoding", "gzip".to_string()))
+    );
+    assert!(
+      served
+        .headers
+        .contains(&("vary", "accept-encoding".to_string()))
+    );
+
+    let served = server.respond("GET", "/public/style.css", |_| None);
+    assert_eq!(&served.body[..], b"body {}");
+    assert!(
+      !served
+        .headers
+        .iter()
+        .any(|(name, _)| *name == "content-encoding")
+    );
+  }
+
+  #[test]
+  fn test_percent_decode() {
+    assert_eq!(percent_decode("a%20b%2Fc").as_deref(), Some("a b/c"));
+    assert_eq!(percent_decode("100%").as_deref(), Some("100%"));
+    assert_eq!(percent_decode("%zz").as_deref(), Some("%zz"));
+    assert_eq!(percent_decode("%ff"), None);
+  }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use crate::ArchiveError;
+
+/// File data shared between multiple bundles, so identical files are only stored once in the
+/// binary.
+///
+/// Bundles are added with [`Bundle::build_shared`](crate::Bundle::build_shared), and the data is
+/// written to `OUT_DIR/{name}.embed_data` by [`finish`](Self::finish). The data has to be passed
+/// to every `include_fs!` call for these bundles:
+///
+/// ```rust,ignore
+/// // In build.rs
+/// let mut shared = include_fs::SharedData::new("themes");
+/// Bundle::new("themes/dark").build_shared(&mut shared, "dark")?;
+/// Bundle::new("themes/light").build_shared(&mut shared, "light")?;
+/// shared.finish()?;
+///
+/// // In main.rs
+/// static THEME_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/themes.embed_data"));
+/// static DARK: IncludeFs = include_fs!("dark", shared = THEME_DATA);
+/// static LIGHT: IncludeFs = include_fs!("light", shared = THEME_DATA);
+/// ```
+#[derive(Debug)]
+pub struct SharedData {
+  name: String,
+  data: Vec<u8>,
+  /// Offset and length of the stored data, by content hash.
+  stored: HashMap<u64, Vec<(u64, u64)>>,
+}
+
+impl SharedData {
+  pub fn new(name: &str) -> Self {
+    Self {
+      name: name.to_string(),
+      data: Vec::new(),
+      stored: HashMap::new(),
+    }
+  }
+
+  pub(crate) fn name(&self) -> &str {
+    &self.name
+  }
+
+  #[cfg(test)]
+  pub(crate) fn data(&self) -> &[u8] {
+    &self.data
+  }
+
+  /// Add the stored data of a file, followed by a NUL byte if `nul_terminated`, returning its
+  /// offset. Data that was already added is reused.
+  pub(crate) fn insert(&mut self, data: &[u8], nul_terminated: bool) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    nul_terminated.hash(&mut hasher);
+
+    let len = data.len() as u64 + nul_terminated as u64;
+    let candidates = self.stored.entry(hasher.finish()).or_default();
+    for &(offset, stored_len) in candidates.iter() {
+      let start = offset as usize;
+      if stored_len == len && self.data[start..start + data.len()] == *data {
+        return offset;
+      }
+    }
+
+    let offset = self.data.len() as u64;
+    self.data.extend_from_slice(data);
+    if nul_terminated {
+      self.data.push(0);
+    }
+
+    candidates.push((offset, len));
+    offset
+  }
+
+  /// Write the shared data to `OUT_DIR`, after all bundles have been added.
+  pub fn finish(self) -> Result<(), ArchiveError> {
+    let out_dir = env::var("OUT_DIR").expect("no OUT_DIR");
+    let output_path = Path::new(&out_dir).join(format!("{}.embed_data", self.name));
+    fs::write(output_path, &self.data)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_deduplicates_data() {
+    let mut shared = SharedData::new("test");
+    let a = shared.insert(b"icon", false);
+    let b = shared.insert(b"other", false);
+    let c = shared.insert(b"icon", false);
+    let d = shared.insert(b"icon", true);
+
+    assert_eq!(a, c);
+    assert_ne!(a, b);
+    assert_ne!(a, d);
+    assert_eq!(shared.data, b"iconothericon\0");
+  }
+}
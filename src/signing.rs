@@ -0,0 +1,127 @@
+//! Ed25519 signatures over whole archives, made with [`Bundle::sign`](crate::Bundle::sign).
+
+use core::ops::Range;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha512};
+
+use crate::archive::{ARCHIVE_ATTR_SIGNATURE, HEADER_LEN, SIGNATURE_LEN};
+use crate::{FsError, IncludeFsInner, read_attribute, read_u16};
+
+/// The range of the signature attribute value in the archive, if the archive is signed.
+fn signature_range(archive: &[u8]) -> Option<Range<usize>> {
+  let attrs_end = HEADER_LEN + read_u16(archive, 9)? as usize;
+  let mut offset = HEADER_LEN;
+  while offset < attrs_end {
+    let (tag, value) = read_attribute(archive, offset)?;
+    if tag == ARCHIVE_ATTR_SIGNATURE && value.len() == SIGNATURE_LEN {
+      return Some(offset + 3..offset + 3 + SIGNATURE_LEN);
+    }
+
+    offset += 3 + value.len();
+  }
+
+  None
+}
+
+/// The SHA-512 hash of the archive with the signature zeroed, which is what gets signed.
+fn signed_digest(archive: &[u8], signature: Range<usize>) -> [u8; 64] {
+  let mut hasher = Sha512::new();
+  hasher.update(&archive[..signature.start]);
+  hasher.update([0; SIGNATURE_LEN]);
+  hasher.update(&archive[signature.end..]);
+  hasher.finalize().into()
+}
+
+/// Sign an archive written with a zeroed signature attribute, filling in the signature.
+pub(crate) fn sign(archive: &mut [u8], secret_key: &[u8; 32]) {
+  let range = signature_range(archive).expect("archive has no signature attribute");
+  let signature = SigningKey::from_bytes(secret_key).sign(&signed_digest(archive, range.clone()));
+  archive[range].copy_from_slice(&signature.to_bytes());
+}
+
+/// The public key matching `secret_key`.
+pub(crate) fn public_key(secret_key: &[u8; 32]) -> [u8; 32] {
+  SigningKey::from_bytes(secret_key)
+    .verifying_key()
+    .to_bytes()
+}
+
+impl IncludeFsInner {
+  /// Check the signature made with [`Bundle::sign`] against the public key of the signer,
+  /// returning [`FsError::InvalidSignature`] if any byte of the archive was changed since it was
+  /// signed, or [`FsError::Unsigned`] if it has no signature.
+  ///
+  /// This reads the whole archive, so it is meant to be called once after opening archives that
+  /// are not protected by the integrity of the binary, like ones opened with
+  /// [`open_path`](Self::open_path):
+  ///
+  /// ```rust,ignore
+  /// const PUBLIC_KEY: [u8; 32] = *include_bytes!("../keys/assets.pub");
+  ///
+  /// let assets = IncludeFsInner::open_path(exe_dir.join("assets.embed_fs"))?;
+  /// assets.verify_signature(&PUBLIC_KEY)?;
+  /// ```
+  ///
+  /// Shared data is not covered by signatures, and neither are archives read with an
+  /// [`ArchiveFile`](crate::ArchiveFile), which could change after being checked.
+  ///
+  /// [`Bundle::sign`]: crate::Bundle::sign
+  pub fn verify_signature(&self, public_key: &[u8; 32]) -> Result<(), FsError> {
+    let archive = self.archive_bytes;
+    let range = signature_range(archive).ok_or(FsError::Unsigned)?;
+    let signature = Signature::from_bytes(archive[range.clone()].try_into().unwrap());
+    let key = VerifyingKey::from_bytes(public_key).map_err(|_| FsError::InvalidSignature)?;
+    key
+      .verify_strict(&signed_digest(archive, range), &signature)
+      .map_err(|_| FsError::InvalidSignature)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::archive::{ArchiveOptions, FileEntry, write_archive};
+
+  #[test]
+  fn test_signatures() {
+    let secret_key = [1; 32];
+    let public_key = SigningKey::from_bytes(&secret_key)
+      .verifying_key()
+      .to_bytes();
+
+    let files = [FileEntry::new("a.txt", "a"), FileEntry::new("b.txt", "b")];
+    let options = ArchiveOptions {
+      signature: true,
+      lookup_table: true,
+      ..Default::default()
+    };
+    let mut archive = Vec::new();
+    write_archive(&files, &options, &mut archive).unwrap();
+    sign(&mut archive, &secret_key);
+
+    let fs = IncludeFsInner::new(archive.clone().leak()).unwrap();
+    fs.verify_signature(&public_key).unwrap();
+    assert_eq!(fs.get("b.txt").unwrap(), b"b");
+
+    let other_key = SigningKey::from_bytes(&[2; 32]).verifying_key().to_bytes();
+    assert!(matches!(
+      fs.verify_signature(&other_key),
+      Err(FsError::InvalidSignature)
+    ));
+
+    let data_offset = fs.raw_parts("a.txt").unwrap().0;
+    archive[data_offset] = b'x';
+    let tampered = IncludeFsInner::new(archive.leak()).unwrap();
+    assert!(matches!(
+      tampered.verify_signature(&public_key),
+      Err(FsError::InvalidSignature)
+    ));
+
+    let unsigned = crate::tests::test_fs(&[("a.txt", b"a")]);
+    assert!(matches!(
+      unsigned.verify_signature(&public_key),
+      Err(FsError::Unsigned)
+    ));
+  }
+}
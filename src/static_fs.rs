@@ -0,0 +1,123 @@
+use alloc::string::String;
+use core::fmt;
+
+use crate::FsError;
+
+/// An embedded filesystem whose index is generated at compile time, declared with
+/// `include_fs!(static NAME: StaticFs = "bundle")`.
+///
+/// Unlike [`IncludeFs`](crate::IncludeFs), the files are a table of paths and slices of the
+/// embedded archive that is built into the binary, so there is nothing to initialize and no
+/// `LazyLock` to go through on access. Lookups are binary searches over the sorted paths:
+///
+/// ```rust,ignore
+/// include_fs!(pub static ASSETS: StaticFs = "assets");
+///
+/// let index = ASSETS.get("assets/index.html")?;
+/// ```
+///
+/// This only supports files that can be borrowed as they are stored, so bundles with
+/// compressed or encrypted files are rejected by the macro, and passthrough is not available.
+#[derive(Clone, Copy)]
+pub struct StaticFs {
+  /// Paths and data of the files, sorted by path.
+  files: &'static [(&'static str, &'static [u8])],
+  case_insensitive: bool,
+}
+
+impl fmt::Debug for StaticFs {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("StaticFs")
+      .field("files", &self.files.len())
+      .field("case_insensitive", &self.case_insensitive)
+      .finish_non_exhaustive()
+  }
+}
+
+impl StaticFs {
+  /// This function is only meant to be called by the `include_fs!` macro, which sorts the
+  /// files by path.
+  #[doc(hidden)]
+  pub const fn __new(
+    files: &'static [(&'static str, &'static [u8])],
+    case_insensitive: bool,
+  ) -> Self {
+    Self {
+      files,
+      case_insensitive,
+    }
+  }
+
+  fn find(&self, path: &str) -> Option<&'static [u8]> {
+    let path = crate::path::normalize(path)?;
+    let lowercase: String;
+    let path = match self.case_insensitive {
+      true => {
+        lowercase = path.to_lowercase();
+        &lowercase
+      }
+      false => &*path,
+    };
+
+    let i = self
+      .files
+      .binary_search_by(|(file, _)| (*file).cmp(path))
+      .ok()?;
+    Some(self.files[i].1)
+  }
+
+  pub fn exists(&self, path: impl AsRef<str>) -> bool {
+    self.find(path.as_ref()).is_some()
+  }
+
+  pub fn get(&self, path: impl AsRef<str>) -> Result<&'static [u8], FsError> {
+    self.find(path.as_ref()).ok_or(FsError::NotFound)
+  }
+
+  /// All paths with the data of their files, sorted by path.
+  pub fn iter(&self) -> impl ExactSizeIterator<Item = (&'static str, &'static [u8])> + 'static {
+    self.files.iter().copied()
+  }
+
+  /// All paths, sorted.
+  pub fn list_paths(&self) -> impl ExactSizeIterator<Item = &'static str> + 'static {
+    self.files.iter().map(|(path, _)| *path)
+  }
+
+  /// The number of files.
+  pub const fn len(&self) -> usize {
+    self.files.len()
+  }
+
+  pub const fn is_empty(&self) -> bool {
+    self.files.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  static ASSETS: StaticFs = StaticFs::__new(
+    &[
+      ("assets/index.html", b"<html></html>"),
+      ("assets/style.css", b"body {}"),
+      ("readme.md", b"readme"),
+    ],
+    false,
+  );
+
+  #[test]
+  fn test_static_fs() {
+    assert_eq!(ASSETS.get("assets/style.css").unwrap(), b"body {}");
+    assert!(ASSETS.exists("readme.md"));
+    assert!(ASSETS.exists("./assets//style.css"));
+    assert!(!ASSETS.exists("../readme.md"));
+    assert!(matches!(ASSETS.get("assets"), Err(FsError::NotFound)));
+    assert_eq!(ASSETS.len(), 3);
+    assert_eq!(ASSETS.list_paths().next(), Some("assets/index.html"));
+
+    let insensitive = StaticFs::__new(&[("readme.md", b"readme")], true);
+    assert_eq!(insensitive.get("README.md").unwrap(), b"readme");
+  }
+}
@@ -0,0 +1,68 @@
+//! Linking archives from a static library, see [`Bundle::static_lib`].
+//!
+//! [`Bundle::static_lib`]: crate::Bundle::static_lib
+
+use std::fs;
+use std::path::Path;
+
+use crate::ArchiveError;
+
+/// Name of the extern static containing the archive of a bundle.
+pub(crate) fn symbol(bundle_name: &str) -> String {
+  let name: String = bundle_name
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect();
+
+  format!("include_fs_{name}")
+}
+
+/// Compile the archive at `archive_path` into a static library exporting it as `symbol`, and
+/// link it into the crate. The archive is aligned to at least 16 bytes.
+pub(crate) fn compile(
+  archive_path: &Path,
+  symbol: &str,
+  alignment: u32,
+) -> Result<(), ArchiveError> {
+  let alignment = alignment.max(16);
+  // Assemblers expect forward slashes, even on Windows
+  let archive_path = archive_path.to_string_lossy().replace('\\', "/");
+  let source = format!(
+    r#"#if defined(__APPLE__)
+  .section __TEXT,__const
+  .globl _{symbol}
+  .balign {alignment}
+_{symbol}:
+#else
+#if defined(_WIN32)
+  .section .rdata,"dr"
+#else
+  .section .rodata.{symbol},"a"
+#endif
+  .globl {symbol}
+  .balign {alignment}
+{symbol}:
+#endif
+  .incbin "{archive_path}"
+"#
+  );
+
+  let source_path = format!("{archive_path}.S");
+  fs::write(&source_path, source)?;
+
+  cc::Build::new()
+    .file(&source_path)
+    .try_compile(symbol)
+    .map_err(ArchiveError::StaticLib)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_symbol() {
+    assert_eq!(symbol("assets"), "include_fs_assets");
+    assert_eq!(symbol("web/dist-v2"), "include_fs_web_dist_v2");
+  }
+}
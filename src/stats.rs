@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{EmbeddedPath, IncludeFsInner};
+
+/// Number and size of the embedded files with an extension, see
+/// [`IncludeFsInner::stats_by_extension`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionStats {
+  /// The lowercased extension without the leading dot, or an empty string for files without an
+  /// extension.
+  pub extension: String,
+  /// Number of files with the extension.
+  pub files: usize,
+  /// Total size of the files with the extension, in bytes.
+  pub bytes: u64,
+}
+
+impl IncludeFsInner {
+  /// The number and total size of files per extension, sorted by size with the largest first.
+  ///
+  /// This only reads the index, so it is cheap enough for diagnostics pages.
+  pub fn stats_by_extension(&self) -> Vec<ExtensionStats> {
+    let mut by_extension: HashMap<String, ExtensionStats> = HashMap::new();
+    for (path, entry) in self.index.files.iter(self.archive_bytes) {
+      let extension = Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+      let stats = by_extension
+        .entry(extension.clone())
+        .or_insert(ExtensionStats {
+          extension,
+          files: 0,
+          bytes: 0,
+        });
+
+      stats.files += 1;
+      stats.bytes += entry.size;
+    }
+
+    let mut stats: Vec<_> = by_extension.into_values().collect();
+    stats.sort_by(|a, b| {
+      b.bytes
+        .cmp(&a.bytes)
+        .then_with(|| a.extension.cmp(&b.extension))
+    });
+    stats
+  }
+
+  /// The `n` largest files with their sizes in bytes, sorted with the largest first.
+  pub fn largest(&self, n: usize) -> Vec<(&EmbeddedPath, u64)> {
+    let mut files: Vec<_> = self
+      .index
+      .files
+      .iter(self.archive_bytes)
+      .map(|(path, entry)| (EmbeddedPath::new_unchecked(path), entry.size))
+      .collect();
+
+    files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    files.truncate(n);
+    files
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+
+  #[test]
+  fn test_stats() {
+    let fs = test_fs(&[
+      ("img/logo.png", b"0123456789"),
+      ("img/icon.PNG", b"01234"),
+      ("css/style.css", b"p {}"),
+      ("LICENSE", b"MIT License"),
+      ("img/.hidden", b"."),
+    ]);
+
+    let stats = fs.stats_by_extension();
+    assert_eq!(
+      stats[0],
+      ExtensionStats {
+        extension: "png".to_string(),
+        files: 2,
+        bytes: 15,
+      }
+    );
+
+    let extensions: Vec<_> = stats.iter().map(|stats| stats.extension.as_str()).collect();
+    assert_eq!(extensions, ["png", "", "css"]);
+    assert_eq!(stats[1].files, 2);
+
+    let largest: Vec<_> = fs
+      .largest(2)
+      .into_iter()
+      .map(|(path, size)| (path.as_str(), size))
+      .collect();
+    assert_eq!(largest, [("LICENSE", 11), ("img/logo.png", 10)]);
+    assert_eq!(fs.largest(10).len(), 5);
+  }
+}
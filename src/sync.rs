@@ -0,0 +1,25 @@
+//! The lazily initialized cells used by the reader, from `std` or, without it, from `spin`.
+
+#[cfg(not(feature = "std"))]
+pub use spin::LazyLock;
+#[cfg(feature = "std")]
+pub use std::sync::{LazyLock, OnceLock};
+
+/// [`spin::Once`] with the interface of [`std::sync::OnceLock`].
+#[cfg(not(feature = "std"))]
+pub struct OnceLock<T>(spin::Once<T>);
+
+#[cfg(not(feature = "std"))]
+impl<T> OnceLock<T> {
+  pub const fn new() -> Self {
+    Self(spin::Once::new())
+  }
+
+  pub fn get(&self) -> Option<&T> {
+    self.0.get()
+  }
+
+  pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+    self.0.call_once(init)
+  }
+}
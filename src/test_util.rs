@@ -0,0 +1,147 @@
+//! Failure injection for testing code that consumes embedded files.
+//!
+//! Embedded files are always present, which makes error handling hard to exercise. Faults can
+//! be injected into a copy of a filesystem, which is then passed to the code under test:
+//!
+//! ```rust,ignore
+//! use include_fs::test_util::Faults;
+//!
+//! let assets = ASSETS.with_faults(
+//!   Faults::new()
+//!     .not_found("config/default.toml")
+//!     .corrupted("locales/*.ftl")
+//!     .latency("models/**", Duration::from_millis(200)),
+//! );
+//!
+//! assert!(load_config(&assets).is_err());
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use globset::{Glob, GlobMatcher};
+
+use crate::{FsError, IncludeFsInner};
+
+/// A fault injected into lookups of matching paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+  /// Return [`FsError::NotFound`], as if the file was not embedded.
+  NotFound,
+  /// Return [`FsError::InvalidArchive`], as if the archive was corrupted.
+  Corrupted,
+  /// Block for the given duration, then succeed.
+  Latency(Duration),
+}
+
+/// A set of faults for [`IncludeFsInner::with_faults`], each applying to the paths matching a
+/// glob. The first matching fault is used.
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+  faults: Vec<(GlobMatcher, Fault)>,
+}
+
+impl Faults {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Inject `fault` into lookups of paths matching `glob`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `glob` is invalid.
+  pub fn add(mut self, glob: &str, fault: Fault) -> Self {
+    let matcher = Glob::new(glob)
+      .unwrap_or_else(|err| panic!("invalid glob {glob}: {err}"))
+      .compile_matcher();
+
+    self.faults.push((matcher, fault));
+    self
+  }
+
+  /// Make paths matching `glob` return [`FsError::NotFound`].
+  pub fn not_found(self, glob: &str) -> Self {
+    self.add(glob, Fault::NotFound)
+  }
+
+  /// Make paths matching `glob` return [`FsError::InvalidArchive`].
+  pub fn corrupted(self, glob: &str) -> Self {
+    self.add(glob, Fault::Corrupted)
+  }
+
+  /// Delay lookups of paths matching `glob` by `latency`.
+  pub fn latency(self, glob: &str, latency: Duration) -> Self {
+    self.add(glob, Fault::Latency(latency))
+  }
+
+  /// Apply the fault for `path`, if any.
+  pub(crate) fn apply(&self, path: &str) -> Result<(), FsError> {
+    let Some((_, fault)) = self
+      .faults
+      .iter()
+      .find(|(matcher, _)| matcher.is_match(path))
+    else {
+      return Ok(());
+    };
+
+    match fault {
+      Fault::NotFound => Err(FsError::NotFound),
+      Fault::Corrupted => Err(FsError::InvalidArchive),
+      Fault::Latency(latency) => {
+        std::thread::sleep(*latency);
+        Ok(())
+      }
+    }
+  }
+}
+
+impl IncludeFsInner {
+  /// A copy of the filesystem that injects `faults` into lookups, replacing any previously
+  /// injected faults.
+  ///
+  /// Faults apply to everything that looks up a path, such as [`get`](Self::get) and
+  /// [`exists`](Self::exists), but not to [`list_paths`](Self::list_paths).
+  pub fn with_faults(&self, faults: Faults) -> Self {
+    let mut fs = self.clone();
+    fs.faults = Some(Arc::new(faults));
+    fs
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::test_fs;
+  use std::time::Instant;
+
+  #[test]
+  fn test_faults() {
+    let fs = test_fs(&[
+      ("config.toml", b"config"),
+      ("locales/en.ftl", b"en"),
+      ("models/big.bin", b"model"),
+    ]);
+
+    let faulty = fs.with_faults(
+      Faults::new()
+        .not_found("config.toml")
+        .corrupted("locales/*")
+        .latency("models/**", Duration::from_millis(20)),
+    );
+
+    assert!(matches!(faulty.get("config.toml"), Err(FsError::NotFound)));
+    assert!(!faulty.exists("config.toml"));
+    assert!(matches!(
+      faulty.get("locales/en.ftl"),
+      Err(FsError::InvalidArchive)
+    ));
+
+    let start = Instant::now();
+    assert_eq!(faulty.get("models/big.bin").unwrap(), b"model");
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    assert_eq!(faulty.list_paths().len(), 3);
+    assert_eq!(fs.get("config.toml").unwrap(), b"config");
+  }
+}
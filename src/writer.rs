@@ -0,0 +1,145 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::archive::{ArchiveOptions, FileEntry, write_archive};
+use crate::{ArchiveError, EmbeddedPath};
+
+/// Create an archive from files added one by one, instead of walking a directory like
+/// [`Bundle`](crate::Bundle).
+///
+/// This allows build scripts to embed generated code, downloaded files or the contents of other
+/// archives. The result is a standard archive that is included with `include_fs!`:
+///
+/// ```rust,ignore
+/// // In build.rs
+/// let mut writer = include_fs::ArchiveWriter::new();
+/// writer.add_bytes("version.txt", env!("CARGO_PKG_VERSION"))?;
+/// writer.add("schema.json", File::open(download_schema()?)?)?;
+/// writer.build("generated")?;
+///
+/// // In main.rs
+/// static GENERATED: IncludeFs = include_fs!("generated");
+/// ```
+///
+/// Files are sorted by path when the archive is written, so the order they are added in doesn't
+/// affect the output.
+#[derive(Debug, Default)]
+pub struct ArchiveWriter {
+  files: Vec<FileEntry>,
+  case_insensitive: bool,
+}
+
+impl ArchiveWriter {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add a file with the contents of `reader`.
+  ///
+  /// The path has to be a valid [`EmbeddedPath`], otherwise [`ArchiveError::InvalidPath`] is
+  /// returned.
+  pub fn add(&mut self, path: &str, mut reader: impl Read) -> Result<(), ArchiveError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    self.add_bytes(path, data)
+  }
+
+  /// Add a file with the given contents.
+  pub fn add_bytes(&mut self, path: &str, data: impl Into<Vec<u8>>) -> Result<(), ArchiveError> {
+    let path = EmbeddedPath::new(path).map_err(|_| ArchiveError::InvalidPath(path.to_string()))?;
+    self.files.push(FileEntry::new(path.as_str(), data));
+    Ok(())
+  }
+
+  /// Lowercase paths and make lookups case-insensitive, like
+  /// [`Bundle::case_insensitive`](crate::Bundle::case_insensitive).
+  pub fn case_insensitive(&mut self, case_insensitive: bool) -> &mut Self {
+    self.case_insensitive = case_insensitive;
+    self
+  }
+
+  /// Number of files added so far.
+  pub fn len(&self) -> usize {
+    self.files.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.files.is_empty()
+  }
+
+  /// Write the archive to `out`.
+  ///
+  /// Returns [`ArchiveError::DuplicatePath`] if a path was added more than once.
+  pub fn write_to<W: Write>(mut self, out: &mut W) -> Result<(), ArchiveError> {
+    if self.case_insensitive {
+      for file in &mut self.files {
+        file.path = file.path.to_lowercase();
+      }
+    }
+
+    self.files.sort_by(|a, b| a.path.cmp(&b.path));
+    if let Some(pair) = self
+      .files
+      .windows(2)
+      .find(|pair| pair[0].path == pair[1].path)
+    {
+      return Err(ArchiveError::DuplicatePath(pair[0].path.clone()));
+    }
+
+    let options = ArchiveOptions {
+      case_insensitive: self.case_insensitive,
+      crate_version: env::var("CARGO_PKG_VERSION").ok(),
+      ..Default::default()
+    };
+
+    write_archive(&self.files, &options, out)
+  }
+
+  /// Write the archive to `OUT_DIR`, to be included with `include_fs!(bundle_name)`. This must
+  /// be called in a build script.
+  pub fn build(self, bundle_name: &str) -> Result<(), ArchiveError> {
+    let out_dir = env::var("OUT_DIR").expect("no OUT_DIR");
+    let output_path = Path::new(&out_dir).join(format!("{bundle_name}.embed_fs"));
+
+    let mut out = BufWriter::new(File::create(output_path)?);
+    self.write_to(&mut out)?;
+    out.flush()?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::IncludeFsInner;
+
+  #[test]
+  fn test_archive_writer() {
+    let mut writer = ArchiveWriter::new();
+    writer.add_bytes("b/generated.rs", "fn main() {}").unwrap();
+    writer.add("a.txt", &b"from a reader"[..]).unwrap();
+    assert!(matches!(
+      writer.add_bytes("../escape.txt", "x"),
+      Err(ArchiveError::InvalidPath(path)) if path == "../escape.txt"
+    ));
+    assert_eq!(writer.len(), 2);
+
+    let mut archive = Vec::new();
+    writer.write_to(&mut archive).unwrap();
+    let fs = IncludeFsInner::new(Box::leak(archive.into_boxed_slice())).unwrap();
+    assert_eq!(fs.get("a.txt").unwrap(), b"from a reader");
+    assert_eq!(fs.get("b/generated.rs").unwrap(), b"fn main() {}");
+
+    let mut writer = ArchiveWriter::new();
+    writer.case_insensitive(true);
+    writer.add_bytes("A.txt", "a").unwrap();
+    writer.add_bytes("a.txt", "b").unwrap();
+    assert!(matches!(
+      writer.write_to(&mut Vec::new()),
+      Err(ArchiveError::DuplicatePath(path)) if path == "a.txt"
+    ));
+  }
+}